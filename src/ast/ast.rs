@@ -13,99 +13,501 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::type_system::type_system::Type;
+use crate::lexer::lexer::Span;
+use crate::type_system::type_system::{ParamKind, Type};
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum ASTNode {
     Module {
         body: Vec<ASTNode>,
+        span: Span,
     },
     Import {
         names: Vec<String>,
+        span: Span,
     },
+    /// `nonlocal a, b`: assignments to these names in the enclosing
+    /// function rebind the variables of the scope they are found in.
+    Nonlocal {
+        names: Vec<String>,
+        span: Span,
+    },
+    /// Drops the temporaries of `await`s split out of a statement once it
+    /// has run. Only made by the parser.
+    Unbind {
+        names: Vec<String>,
+        span: Span,
+    },
+    /// `decorators` are the `@` expressions above the definition, top
+    /// first; the name is bound to the result of applying them.
     FunctionDef {
         name: String,
-        args: Vec<(String, Type)>,
+        args: Vec<Parameter>,
         return_type: Type,
         body: Vec<ASTNode>,
+        decorators: Vec<ASTNode>,
+        span: Span,
     },
     ClassDef {
         name: String,
+        bases: Vec<String>,
         methods: Vec<ASTNode>,
+        decorators: Vec<ASTNode>,
+        span: Span,
     },
     Assign {
-        target: String,
+        target: Box<ASTNode>,
         value: Box<ASTNode>,
+        span: Span,
     },
     Expr {
         value: Box<ASTNode>,
+        span: Span,
     },
     Call {
-        func: String,
-        args: Vec<ASTNode>,
+        callee: Box<ASTNode>,
+        args: Vec<Argument>,
+        span: Span,
     },
     Str {
         value: String,
+        span: Span,
     },
-    Num {
+    Int {
+        value: i64,
+        span: Span,
+    },
+    Float {
         value: f64,
+        span: Span,
     },
     Bool {
         value: bool,
+        span: Span,
     },
     Name {
         id: String,
+        span: Span,
     },
     BinOp {
         left: Box<ASTNode>,
-        op: String,
+        op: BinaryOperator,
         right: Box<ASTNode>,
+        span: Span,
+    },
+    UnaryOp {
+        op: UnaryOperator,
+        operand: Box<ASTNode>,
+        span: Span,
     },
     If {
         condition: Box<ASTNode>,
         body: Vec<ASTNode>,
         orelse: Vec<ASTNode>,
+        span: Span,
     },
     While {
         condition: Box<ASTNode>,
         body: Vec<ASTNode>,
+        span: Span,
     },
     For {
-        target: String,
+        target: Box<ASTNode>,
         iter: Box<ASTNode>,
         body: Vec<ASTNode>,
+        span: Span,
+    },
+    Try {
+        body: Vec<ASTNode>,
+        handlers: Vec<ExceptHandler>,
+        finally: Vec<ASTNode>,
+        span: Span,
+    },
+    Raise {
+        value: Box<ASTNode>,
+        span: Span,
     },
     Return {
         value: Option<Box<ASTNode>>,
+        span: Span,
     },
-    Closure {
-        params: Vec<(String, Type)>,
-        body: Box<ASTNode>,
+    Break {
+        span: Span,
+    },
+    Continue {
+        span: Span,
     },
-    Generator {
+    /// An anonymous function. Its body returns the value of its one
+    /// expression; `return_type` is `None` when left to inference.
+    Closure {
+        params: Vec<Parameter>,
+        return_type: Option<Type>,
         body: Vec<ASTNode>,
+        span: Span,
     },
     Yield {
         value: Box<ASTNode>,
+        span: Span,
     },
     Async {
         body: Vec<ASTNode>,
+        span: Span,
     },
     Await {
         value: Box<ASTNode>,
+        span: Span,
     },
     List {
         elements: Vec<ASTNode>,
+        span: Span,
+    },
+    Tuple {
+        elements: Vec<ASTNode>,
+        span: Span,
+    },
+    Set {
+        elements: Vec<ASTNode>,
+        span: Span,
     },
     Dict {
         pairs: Vec<(ASTNode, ASTNode)>,
+        span: Span,
+    },
+    Subscript {
+        value: Box<ASTNode>,
+        index: Box<ASTNode>,
+        span: Span,
+    },
+    /// `start:stop:step` inside a subscript; every part is optional.
+    Slice {
+        start: Option<Box<ASTNode>>,
+        stop: Option<Box<ASTNode>>,
+        step: Option<Box<ASTNode>>,
+        span: Span,
     },
     Attribute {
         value: Box<ASTNode>,
         attr: String,
+        span: Span,
     },
-    InteropCall {
-        language: String,
-        code: String,
-    },
+}
+
+impl ASTNode {
+    pub fn span(&self) -> &Span {
+        match self {
+            ASTNode::Module { span, .. }
+            | ASTNode::Import { span, .. }
+            | ASTNode::Nonlocal { span, .. }
+            | ASTNode::Unbind { span, .. }
+            | ASTNode::FunctionDef { span, .. }
+            | ASTNode::ClassDef { span, .. }
+            | ASTNode::Assign { span, .. }
+            | ASTNode::Expr { span, .. }
+            | ASTNode::Call { span, .. }
+            | ASTNode::Str { span, .. }
+            | ASTNode::Int { span, .. }
+            | ASTNode::Float { span, .. }
+            | ASTNode::Bool { span, .. }
+            | ASTNode::Name { span, .. }
+            | ASTNode::BinOp { span, .. }
+            | ASTNode::UnaryOp { span, .. }
+            | ASTNode::If { span, .. }
+            | ASTNode::While { span, .. }
+            | ASTNode::For { span, .. }
+            | ASTNode::Try { span, .. }
+            | ASTNode::Raise { span, .. }
+            | ASTNode::Return { span, .. }
+            | ASTNode::Break { span }
+            | ASTNode::Continue { span }
+            | ASTNode::Closure { span, .. }
+            | ASTNode::Yield { span, .. }
+            | ASTNode::Async { span, .. }
+            | ASTNode::Await { span, .. }
+            | ASTNode::List { span, .. }
+            | ASTNode::Tuple { span, .. }
+            | ASTNode::Set { span, .. }
+            | ASTNode::Dict { span, .. }
+            | ASTNode::Subscript { span, .. }
+            | ASTNode::Slice { span, .. }
+            | ASTNode::Attribute { span, .. } => span,
+        }
+    }
+
+    /// The function an `async define` wraps, and whether it was wrapped.
+    /// Other nodes are returned as they are.
+    pub fn unwrap_async(&self) -> (&ASTNode, bool) {
+        match self {
+            ASTNode::Async { body, .. } if body.len() == 1 => (&body[0], true),
+            other => (other, false),
+        }
+    }
+
+    /// Whether this statement yields, directly or from a nested block. A
+    /// function whose body yields is a generator. Nested definitions are
+    /// their own functions and don't count.
+    pub fn contains_yield(&self) -> bool {
+        self.suspends_at(|node| matches!(node, ASTNode::Yield { .. }))
+    }
+
+    /// Whether this statement awaits, directly or from a nested block.
+    pub fn contains_await(&self) -> bool {
+        self.suspends_at(|node| matches!(node, ASTNode::Await { .. }))
+    }
+
+    /// Whether `point` matches a whole statement, the value of an
+    /// assignment or of a `return`, here or in a nested block. Those are
+    /// the places a generator or async function can be suspended.
+    fn suspends_at(&self, point: fn(&ASTNode) -> bool) -> bool {
+        let any = |body: &[ASTNode]| body.iter().any(|stmt| stmt.suspends_at(point));
+        match self {
+            _ if point(self) => true,
+            ASTNode::Expr { value, .. } | ASTNode::Assign { value, .. } => point(value),
+            ASTNode::Return {
+                value: Some(value), ..
+            } => point(value),
+            ASTNode::If { body, orelse, .. } => any(body) || any(orelse),
+            ASTNode::While { body, .. } | ASTNode::For { body, .. } => any(body),
+            ASTNode::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => any(body) || handlers.iter().any(|h| any(&h.body)) || any(finally),
+            _ => false,
+        }
+    }
+
+    /// Whether this expression awaits anywhere inside it. Anonymous
+    /// functions are not looked into.
+    pub fn awaits_within(&self) -> bool {
+        match self {
+            ASTNode::Await { .. } => true,
+            ASTNode::BinOp { left, right, .. } => left.awaits_within() || right.awaits_within(),
+            ASTNode::UnaryOp { operand: value, .. }
+            | ASTNode::Attribute { value, .. }
+            | ASTNode::Yield { value, .. } => value.awaits_within(),
+            ASTNode::Call { callee, args, .. } => {
+                callee.awaits_within() || args.iter().any(|arg| arg.value().awaits_within())
+            }
+            ASTNode::List { elements, .. }
+            | ASTNode::Tuple { elements, .. }
+            | ASTNode::Set { elements, .. } => elements.iter().any(ASTNode::awaits_within),
+            ASTNode::Dict { pairs, .. } => pairs
+                .iter()
+                .any(|(key, value)| key.awaits_within() || value.awaits_within()),
+            ASTNode::Subscript { value, index, .. } => {
+                value.awaits_within() || index.awaits_within()
+            }
+            ASTNode::Slice {
+                start, stop, step, ..
+            } => [start, stop, step]
+                .into_iter()
+                .flatten()
+                .any(|part| part.awaits_within()),
+            _ => false,
+        }
+    }
+
+    /// The expressions directly inside this one, in the order they are
+    /// evaluated.
+    pub fn subexpressions_mut(&mut self) -> Vec<&mut ASTNode> {
+        match self {
+            ASTNode::BinOp { left, right, .. } => vec![&mut **left, &mut **right],
+            ASTNode::UnaryOp { operand: value, .. }
+            | ASTNode::Attribute { value, .. }
+            | ASTNode::Yield { value, .. }
+            | ASTNode::Await { value, .. } => vec![&mut **value],
+            ASTNode::Call { callee, args, .. } => std::iter::once(&mut **callee)
+                .chain(args.iter_mut().map(Argument::value_mut))
+                .collect(),
+            ASTNode::List { elements, .. }
+            | ASTNode::Tuple { elements, .. }
+            | ASTNode::Set { elements, .. } => elements.iter_mut().collect(),
+            ASTNode::Dict { pairs, .. } => pairs
+                .iter_mut()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+            ASTNode::Subscript { value, index, .. } => vec![&mut **value, &mut **index],
+            ASTNode::Slice {
+                start, stop, step, ..
+            } => [start, stop, step]
+                .into_iter()
+                .flatten()
+                .map(|part| &mut **part)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// One `except [Class [as name]]:` clause of a `try` statement. A clause
+/// without a class catches every exception.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExceptHandler {
+    pub class: Option<String>,
+    pub name: Option<String>,
+    pub body: Vec<ASTNode>,
+    pub span: Span,
+}
+
+/// A parameter of a function definition or anonymous function. Only
+/// `ParamKind::Optional` parameters have a `default`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub type_: Type,
+    pub default: Option<ASTNode>,
+    pub kind: ParamKind,
+}
+
+/// One argument of a call.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Argument {
+    Positional(ASTNode),
+    /// `name=value`
+    Keyword(String, ASTNode),
+    /// `*items`: each element of an iterable as a positional argument.
+    Unpack(ASTNode),
+    /// `**mapping`: each entry of a dict as a keyword argument.
+    UnpackKeywords(ASTNode),
+}
+
+impl Argument {
+    pub fn value(&self) -> &ASTNode {
+        match self {
+            Argument::Positional(value)
+            | Argument::Keyword(_, value)
+            | Argument::Unpack(value)
+            | Argument::UnpackKeywords(value) => value,
+        }
+    }
+
+    pub fn value_mut(&mut self) -> &mut ASTNode {
+        match self {
+            Argument::Positional(value)
+            | Argument::Keyword(_, value)
+            | Argument::Unpack(value)
+            | Argument::UnpackKeywords(value) => value,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    /// Identity: the same list, dict or object, or equal plain values.
+    Is,
+    IsNot,
+    /// Membership in a collection, or a substring of a string.
+    In,
+    NotIn,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    /// Binding strength, loosest first: `or`, `and`, (`not`), comparison,
+    /// additive, multiplicative, then power (which binds tighter than unary
+    /// minus).
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::And => 2,
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessEqual
+            | BinaryOperator::GreaterEqual
+            | BinaryOperator::Is
+            | BinaryOperator::IsNot
+            | BinaryOperator::In
+            | BinaryOperator::NotIn => 4,
+            BinaryOperator::Add | BinaryOperator::Subtract => 5,
+            BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::FloorDivide
+            | BinaryOperator::Modulo => 6,
+            BinaryOperator::Power => 7,
+        }
+    }
+
+    pub fn is_right_associative(self) -> bool {
+        self == BinaryOperator::Power
+    }
+
+    pub fn is_comparison(self) -> bool {
+        self.precedence() == 4
+    }
+
+    pub fn is_logical(self) -> bool {
+        matches!(self, BinaryOperator::And | BinaryOperator::Or)
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::FloorDivide => "//",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Power => "**",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Is => "is",
+            BinaryOperator::IsNot => "is not",
+            BinaryOperator::In => "in",
+            BinaryOperator::NotIn => "not in",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+        }
+    }
+}
+
+impl std::fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+impl UnaryOperator {
+    /// `not` sits between `and` and the comparisons, so `not a == b` negates
+    /// the comparison. Negation is handled by the parser above `**`.
+    pub const NOT_PRECEDENCE: u8 = 3;
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Not => "not",
+        }
+    }
+}
+
+impl std::fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
 }
@@ -28,25 +28,82 @@ use interpreter::interpreter::BellronosInterpreter;
 use package_manager::package_manager::PackageManager;
 use std::env;
 use std::fs;
+use std::process;
+use std::thread;
 
 #[global_allocator]
 static GLOBAL: std::alloc::System = std::alloc::System;
 
-fn main() -> Result<(), BellronosError> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        println!("Usage: bellronos <filename> [--install <package>]");
+/// Stack size of the thread programs run on. Only the pages a program
+/// touches are committed.
+const INTERPRETER_STACK_SIZE: usize = 1 << 30;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+fn main() {
+    let mut error_format = ErrorFormat::Human;
+    let mut args: Vec<String> = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--error-format=") {
+            Some("human") => error_format = ErrorFormat::Human,
+            Some("json") => error_format = ErrorFormat::Json,
+            Some(other) => {
+                eprintln!(
+                    "Unknown error format: {} (expected `human` or `json`)",
+                    other
+                );
+                process::exit(2);
+            }
+            None => args.push(arg),
+        }
+    }
+
+    // Programs run on a thread of their own, whose stack is large enough
+    // for the interpreter's recursion limit
+    let interpreter = thread::Builder::new()
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(move || {
+            if let Err(err) = run(&args) {
+                match error_format {
+                    ErrorFormat::Human => {
+                        eprint!("{}", err.render(&|file| fs::read_to_string(file).ok()))
+                    }
+                    ErrorFormat::Json => {
+                        for error in err.flatten() {
+                            eprintln!("{}", error.to_json());
+                        }
+                    }
+                }
+                process::exit(1);
+            }
+        })
+        .expect("failed to start the interpreter thread");
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), BellronosError> {
+    if args.is_empty() {
+        println!("Usage: bellronos [--error-format=human|json] <filename> [--install <package>]");
         return Ok(());
     }
 
-    if args[1] == "--install" && args.len() == 3 {
+    if args[0] == "--install" && args.len() == 2 {
+        // Downloads block on a runtime, which the interpreter otherwise owns
+        let runtime = tokio::runtime::Runtime::new()?;
+        let _runtime = runtime.enter();
         let package_manager = PackageManager::new("packages".to_string());
-        package_manager.install_package(&args[2])?;
-        println!("Package {} installed successfully", args[2]);
+        package_manager.install_package(&args[1])?;
+        println!("Package {} installed successfully", args[1]);
         return Ok(());
     }
 
-    let filename = &args[1];
+    let filename = &args[0];
     let contents = fs::read_to_string(filename)?;
 
     let mut interpreter = BellronosInterpreter::new();
@@ -13,29 +13,453 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::lexer::lexer::Span;
+use serde_json::json;
 use std::error::Error;
 use std::fmt;
 use std::io;
 
+/// Stable identifiers for every diagnostic the toolchain can emit. The codes
+/// are part of the public output (human and JSON) and must never be reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidNumber,
+    InconsistentIndentation,
+    UnexpectedIndent,
+    ExpectedIndentedBlock,
+    UnexpectedToken,
+    ExpectedIdentifier,
+    ExpectedType,
+    TooManyErrors,
+    InvalidAssignmentTarget,
+    UndefinedName,
+    TypeMismatch,
+    ArgumentCount,
+    NotCallable,
+    InvalidOperation,
+    UnknownAttribute,
+    UnknownClass,
+    UnknownLanguage,
+    DivisionByZero,
+    UncaughtException,
+    IndexOutOfRange,
+    KeyNotFound,
+    InvalidValue,
+    YieldOutsideGenerator,
+    InteropFailure,
+    MisplacedControlFlow,
+    InvalidPackage,
+    MisplacedAwait,
+    TaskDeadlock,
+    ArgumentOrder,
+    DuplicateParameter,
+    UnexpectedKeyword,
+    FileNotFound,
+    IOFailure,
+    RecursionLimit,
+    MisplacedNonlocal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0101",
+            ErrorCode::UnterminatedString => "E0102",
+            ErrorCode::InvalidNumber => "E0103",
+            ErrorCode::InconsistentIndentation => "E0104",
+            ErrorCode::UnexpectedIndent => "E0105",
+            ErrorCode::ExpectedIndentedBlock => "E0106",
+            ErrorCode::UnexpectedToken => "E0107",
+            ErrorCode::ExpectedIdentifier => "E0108",
+            ErrorCode::ExpectedType => "E0109",
+            ErrorCode::TooManyErrors => "E0110",
+            ErrorCode::InvalidAssignmentTarget => "E0111",
+            ErrorCode::ArgumentOrder => "E0112",
+            ErrorCode::DuplicateParameter => "E0113",
+            ErrorCode::UndefinedName => "E0201",
+            ErrorCode::TypeMismatch => "E0202",
+            ErrorCode::ArgumentCount => "E0203",
+            ErrorCode::NotCallable => "E0204",
+            ErrorCode::InvalidOperation => "E0205",
+            ErrorCode::UnknownAttribute => "E0206",
+            ErrorCode::UnknownClass => "E0207",
+            ErrorCode::UnknownLanguage => "E0208",
+            ErrorCode::DivisionByZero => "E0209",
+            ErrorCode::UncaughtException => "E0210",
+            ErrorCode::IndexOutOfRange => "E0211",
+            ErrorCode::KeyNotFound => "E0212",
+            ErrorCode::InvalidValue => "E0213",
+            ErrorCode::TaskDeadlock => "E0214",
+            ErrorCode::UnexpectedKeyword => "E0215",
+            ErrorCode::FileNotFound => "E0216",
+            ErrorCode::IOFailure => "E0217",
+            ErrorCode::RecursionLimit => "E0218",
+            ErrorCode::YieldOutsideGenerator => "E0301",
+            ErrorCode::InteropFailure => "E0302",
+            ErrorCode::MisplacedControlFlow => "E0303",
+            ErrorCode::MisplacedAwait => "E0304",
+            ErrorCode::MisplacedNonlocal => "E0305",
+            ErrorCode::InvalidPackage => "E0401",
+        }
+    }
+
+    /// The built-in exception class a runtime error with this code is raised
+    /// as inside a `try` block.
+    pub fn exception_class(&self) -> &'static str {
+        match self {
+            ErrorCode::UndefinedName | ErrorCode::UnknownClass => "NameError",
+            ErrorCode::TypeMismatch
+            | ErrorCode::ArgumentCount
+            | ErrorCode::UnexpectedKeyword
+            | ErrorCode::NotCallable
+            | ErrorCode::InvalidOperation => "TypeError",
+            ErrorCode::UnknownAttribute => "AttributeError",
+            ErrorCode::DivisionByZero => "DivisionByZeroError",
+            ErrorCode::IndexOutOfRange => "IndexError",
+            ErrorCode::KeyNotFound => "KeyError",
+            ErrorCode::InvalidValue => "ValueError",
+            ErrorCode::InteropFailure | ErrorCode::UnknownLanguage => "InteropError",
+            ErrorCode::InvalidPackage => "ImportError",
+            ErrorCode::FileNotFound => "FileNotFoundError",
+            ErrorCode::IOFailure => "IOError",
+            ErrorCode::RecursionLimit => "RecursionError",
+            _ => "RuntimeError",
+        }
+    }
+}
+
+/// Syntax errors collected before giving up on a module.
+pub const MAX_ERRORS: usize = 20;
+
+/// Built-in exception classes as `(name, base)` pairs, bases first.
+pub const BUILTIN_EXCEPTIONS: &[(&str, Option<&str>)] = &[
+    ("Exception", None),
+    ("RuntimeError", Some("Exception")),
+    ("RecursionError", Some("RuntimeError")),
+    ("NameError", Some("Exception")),
+    ("TypeError", Some("Exception")),
+    ("ValueError", Some("Exception")),
+    ("AttributeError", Some("Exception")),
+    ("IndexError", Some("Exception")),
+    ("KeyError", Some("Exception")),
+    ("DivisionByZeroError", Some("Exception")),
+    ("IOError", Some("Exception")),
+    ("FileNotFoundError", Some("IOError")),
+    ("ImportError", Some("Exception")),
+    ("InteropError", Some("Exception")),
+    ("StopIteration", Some("Exception")),
+    ("TimeoutError", Some("Exception")),
+];
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A secondary location attached to a diagnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A structured error report: what went wrong, where, and how to fix it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic for a terminal, quoting the offending source
+    /// lines. `read_source` maps a file name to its contents.
+    pub fn render(&self, kind: &str, read_source: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut out = format!("{} error[{}]: {}\n", kind, self.code, self.message);
+        let gutter = std::iter::once(self.span.as_ref())
+            .chain(self.labels.iter().map(|l| Some(&l.span)))
+            .flatten()
+            .map(|s| s.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = &self.span {
+            out.push_str(&format!("{}--> {}\n", pad, span));
+            out.push_str(&render_snippet(span, '^', "", gutter, read_source));
+        }
+        for label in &self.labels {
+            if self.span.as_ref().map(|s| &s.file) != Some(&label.span.file) {
+                out.push_str(&format!("{}--> {}\n", pad, label.span));
+            }
+            out.push_str(&render_snippet(
+                &label.span,
+                '-',
+                &label.message,
+                gutter,
+                read_source,
+            ));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", pad, note));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{} = help: {}\n", pad, help));
+        }
+        out
+    }
+
+    pub fn to_json(&self, kind: &str) -> serde_json::Value {
+        json!({
+            "kind": kind,
+            "code": self.code.as_str(),
+            "message": self.message,
+            "span": self.span.as_ref().map(span_to_json),
+            "labels": self
+                .labels
+                .iter()
+                .map(|l| json!({ "span": span_to_json(&l.span), "message": l.message }))
+                .collect::<Vec<_>>(),
+            "notes": self.notes,
+            "help": self.help,
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "[{}] {}: {}", self.code, span, self.message),
+            None => write!(f, "[{}] {}", self.code, self.message),
+        }
+    }
+}
+
+fn render_snippet(
+    span: &Span,
+    marker: char,
+    message: &str,
+    gutter: usize,
+    read_source: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    let pad = " ".repeat(gutter);
+    let source = match read_source(&span.file) {
+        Some(source) => source,
+        None => return String::new(),
+    };
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = source[line_start..]
+        .find('\n')
+        .map(|i| line_start + i)
+        .unwrap_or(source.len());
+    let text = &source[line_start..line_end];
+    let underline_from = source[line_start..start.min(line_end)].chars().count();
+    let underline_len = source
+        .get(start.min(line_end)..span.end.min(line_end))
+        .map(|s| s.chars().count())
+        .unwrap_or(0)
+        .max(1);
+    let mut underline = " ".repeat(underline_from);
+    underline.extend(std::iter::repeat_n(marker, underline_len));
+    if !message.is_empty() {
+        underline.push(' ');
+        underline.push_str(message);
+    }
+    format!(
+        "{pad} |\n{:>gutter$} | {}\n{pad} | {}\n",
+        span.line,
+        text,
+        underline,
+        pad = pad,
+        gutter = gutter
+    )
+}
+
+fn span_to_json(span: &Span) -> serde_json::Value {
+    json!({
+        "file": &*span.file,
+        "line": span.line,
+        "column": span.column,
+        "start": span.start,
+        "end": span.end,
+    })
+}
+
 #[derive(Debug)]
 pub enum BellronosError {
     IO(io::Error),
-    Parser(String),
-    Type(String),
-    Runtime(String),
+    Parser(Box<Diagnostic>),
+    Type(Box<Diagnostic>),
+    Runtime(Box<Diagnostic>),
     Network(String),
-    Package(String),
+    /// Several independent errors reported together, e.g. every syntax
+    /// error found while parsing a module.
+    Multiple(Vec<BellronosError>),
+}
+
+impl BellronosError {
+    fn kind(&self) -> &'static str {
+        match self {
+            BellronosError::IO(_) => "IO",
+            BellronosError::Parser(_) => "Parser",
+            BellronosError::Type(_) => "Type",
+            BellronosError::Runtime(_) => "Runtime",
+            BellronosError::Network(_) => "Network",
+            BellronosError::Multiple(_) => "Multiple",
+        }
+    }
+
+    /// The individual errors, with any `Multiple` nesting flattened away.
+    pub fn flatten(&self) -> Vec<&BellronosError> {
+        match self {
+            BellronosError::Multiple(errors) => errors.iter().flat_map(|e| e.flatten()).collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// The exception class this error is raised as, or `None` for errors
+    /// reported before the program runs (syntax and type errors).
+    pub fn exception_class(&self) -> Option<&'static str> {
+        match self {
+            BellronosError::IO(err) if err.kind() == io::ErrorKind::NotFound => {
+                Some("FileNotFoundError")
+            }
+            BellronosError::IO(_) | BellronosError::Network(_) => Some("IOError"),
+            BellronosError::Runtime(diagnostic) => Some(diagnostic.code.exception_class()),
+            BellronosError::Parser(_) | BellronosError::Type(_) | BellronosError::Multiple(_) => {
+                None
+            }
+        }
+    }
+
+    /// Locates an error raised by a builtin at the call that raised it.
+    /// I/O failures become runtime errors so that they carry the span.
+    pub fn at(self, span: &Span) -> BellronosError {
+        match self {
+            BellronosError::IO(err) => {
+                let code = match err.kind() {
+                    io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                    _ => ErrorCode::IOFailure,
+                };
+                BellronosError::Runtime(Box::new(
+                    Diagnostic::new(code, err.to_string()).with_span(span.clone()),
+                ))
+            }
+            BellronosError::Runtime(diagnostic) if diagnostic.span.is_none() => {
+                BellronosError::Runtime(Box::new(diagnostic.with_span(span.clone())))
+            }
+            other => other,
+        }
+    }
+
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            BellronosError::Parser(d) | BellronosError::Type(d) | BellronosError::Runtime(d) => {
+                Some(d)
+            }
+            _ => None,
+        }
+    }
+
+    /// Human-readable report with source snippets, as printed by the CLI.
+    pub fn render(&self, read_source: &dyn Fn(&str) -> Option<String>) -> String {
+        if let BellronosError::Multiple(_) = self {
+            let errors = self.flatten();
+            let mut out = errors
+                .iter()
+                .map(|e| e.render(read_source))
+                .collect::<Vec<_>>()
+                .join("\n");
+            out.push_str(&format!(
+                "\naborting due to {} previous errors\n",
+                errors.len()
+            ));
+            return out;
+        }
+        match self.diagnostic() {
+            Some(diagnostic) => diagnostic.render(self.kind(), read_source),
+            None => format!("{}\n", self),
+        }
+    }
+
+    /// Machine-readable report for editors and CI (`--error-format=json`).
+    pub fn to_json(&self) -> serde_json::Value {
+        if let BellronosError::Multiple(_) = self {
+            return serde_json::Value::Array(self.flatten().iter().map(|e| e.to_json()).collect());
+        }
+        match self.diagnostic() {
+            Some(diagnostic) => diagnostic.to_json(&self.kind().to_lowercase()),
+            None => json!({
+                "kind": self.kind().to_lowercase(),
+                "code": null,
+                "message": self.to_string(),
+                "span": null,
+                "labels": [],
+                "notes": [],
+                "help": null,
+            }),
+        }
+    }
 }
 
 impl fmt::Display for BellronosError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             BellronosError::IO(err) => write!(f, "IO error: {}", err),
-            BellronosError::Parser(msg) => write!(f, "Parser error: {}", msg),
-            BellronosError::Type(msg) => write!(f, "Type error: {}", msg),
-            BellronosError::Runtime(msg) => write!(f, "Runtime error: {}", msg),
+            BellronosError::Parser(diag) => write!(f, "Parser error{}", diag),
+            BellronosError::Type(diag) => write!(f, "Type error{}", diag),
+            BellronosError::Runtime(diag) => write!(f, "Runtime error{}", diag),
             BellronosError::Network(msg) => write!(f, "Network error: {}", msg),
-            BellronosError::Package(msg) => write!(f, "Package error: {}", msg),
+            BellronosError::Multiple(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", err)?;
+                }
+                Ok(())
+            }
         }
     }
 }
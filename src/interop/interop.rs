@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::error::BellronosError;
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode};
 use crate::interpreter::interpreter::Value;
 use std::process::Command;
 
@@ -32,10 +32,10 @@ impl LanguageInterop {
             "java" => self.execute_java(code),
             "rust" => self.execute_rust(code),
             "swift" => self.execute_swift(code),
-            _ => Err(BellronosError::Runtime(format!(
-                "Unsupported language: {}",
-                language
-            ))),
+            _ => Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::UnknownLanguage,
+                format!("Unsupported language: {}", language),
+            )))),
         }
     }
 
@@ -48,10 +48,13 @@ impl LanguageInterop {
             .output()?;
 
         if !output.status.success() {
-            return Err(BellronosError::Runtime(format!(
-                "C compilation error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+            return Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::InteropFailure,
+                format!(
+                    "C compilation error: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ))));
         }
 
         let output = Command::new("./temp_c").output()?;
@@ -86,10 +89,13 @@ impl LanguageInterop {
         let output = Command::new("javac").arg(temp_file).output()?;
 
         if !output.status.success() {
-            return Err(BellronosError::Runtime(format!(
-                "Java compilation error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+            return Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::InteropFailure,
+                format!(
+                    "Java compilation error: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ))));
         }
 
         let output = Command::new("java").arg("Temp").output()?;
@@ -109,10 +115,13 @@ impl LanguageInterop {
         let output = Command::new("rustc").arg(temp_file).output()?;
 
         if !output.status.success() {
-            return Err(BellronosError::Runtime(format!(
-                "Rust compilation error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+            return Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::InteropFailure,
+                format!(
+                    "Rust compilation error: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ))));
         }
 
         let output = Command::new("./temp").output()?;
@@ -138,10 +147,13 @@ impl LanguageInterop {
                 String::from_utf8_lossy(&output.stdout).to_string(),
             ))
         } else {
-            Err(BellronosError::Runtime(format!(
-                "Swift execution error: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )))
+            Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::InteropFailure,
+                format!(
+                    "Swift execution error: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ))))
         }
     }
 }
@@ -13,21 +13,92 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::ast::ast::ASTNode;
-use crate::error::error::BellronosError;
-use crate::interop::interop::LanguageInterop;
-use crate::lexer::lexer::Lexer;
+use crate::ast::ast::{ASTNode, Argument, BinaryOperator, ExceptHandler, Parameter, UnaryOperator};
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode, BUILTIN_EXCEPTIONS};
+use crate::lexer::lexer::{Lexer, Span};
 use crate::package_manager::package_manager::PackageManager;
 use crate::parser::parser::Parser;
-use crate::standard_library::standard_library::StandardLibrary;
-use crate::type_system::type_system::TypeChecker;
+use crate::standard_library::standard_library::{FileHandle, StandardLibrary};
+use crate::type_system::type_system::{
+    arity_text, required_params, ParamKind, ParamType, Type, TypeChecker,
+};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::f64;
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
-type Environment = Rc<RefCell<HashMap<String, Value>>>;
+/// How many function bodies and generators may run inside each other.
+const MAX_CALL_DEPTH: usize = 1000;
+
+pub type Environment = Rc<RefCell<Scope>>;
+
+/// One frame of lexical scope. Lookups that miss here continue in the
+/// enclosing frame, ending at the module's global scope.
+#[derive(Default)]
+pub struct Scope {
+    vars: HashMap<String, Value>,
+    parent: Option<Environment>,
+    /// Names declared `nonlocal` in this frame, which assignments rebind
+    /// in the enclosing frame that owns them.
+    nonlocals: HashSet<String>,
+}
+
+impl Scope {
+    pub fn new_environment(parent: Option<&Environment>) -> Environment {
+        Rc::new(RefCell::new(Scope {
+            vars: HashMap::new(),
+            parent: parent.map(Rc::clone),
+            nonlocals: HashSet::new(),
+        }))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref().and_then(|p| p.borrow().get(name)),
+        }
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.vars.insert(name, value);
+    }
+
+    /// Makes assignments to `name` in this frame rebind it in the enclosing
+    /// frame that owns it. Fails when no enclosing frame has it.
+    pub fn declare_nonlocal(&mut self, name: &str) -> bool {
+        let bound = self
+            .parent
+            .as_ref()
+            .is_some_and(|p| p.borrow().get(name).is_some());
+        if bound {
+            self.nonlocals.insert(name.to_string());
+        }
+        bound
+    }
+
+    /// Binds `name` for an assignment: in this frame, or where it is owned
+    /// when it was declared `nonlocal` here.
+    pub fn assign(&mut self, name: &str, value: Value) {
+        match &self.parent {
+            Some(parent) if self.nonlocals.contains(name) => {
+                parent.borrow_mut().rebind(name, value)
+            }
+            _ => self.define(name.to_string(), value),
+        }
+    }
+
+    fn rebind(&mut self, name: &str, value: Value) {
+        match (self.vars.get_mut(name), &self.parent) {
+            (Some(slot), _) => *slot = value,
+            (None, Some(parent)) => parent.borrow_mut().rebind(name, value),
+            (None, None) => self.define(name.to_string(), value),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub enum Value {
@@ -35,36 +106,460 @@ pub enum Value {
     Float(f64),
     String(String),
     Bool(bool),
-    List(Vec<Value>),
-    Dict(HashMap<String, Value>),
-    Function(Vec<String>, Vec<ASTNode>, Environment),
-    Class {
-        methods: HashMap<String, Value>,
-    },
-    Instance {
-        class: String,
-        attributes: HashMap<String, Value>,
-    },
-    Closure(Vec<String>, Vec<ASTNode>, Environment),
-    Generator(Vec<ASTNode>, Environment, usize),
+    /// Lists and dicts live on the heap: copying the value shares them, so
+    /// a mutation through one reference is seen through all of them.
+    List(Rc<RefCell<Vec<Value>>>),
+    Dict(Rc<RefCell<HashMap<String, Value>>>),
+    /// An immutable sequence, compared and hashed by its contents.
+    Tuple(Rc<[Value]>),
+    /// An unordered collection of distinct hashable values.
+    Set(Rc<RefCell<HashSet<Value>>>),
+    /// A defined or anonymous function, with the name errors report it
+    /// by. It keeps a reference to the scope it was defined in, so it sees
+    /// later changes to the names there.
+    Function(Rc<str>, Vec<FunctionParam>, Rc<[ASTNode]>, Environment),
+    /// An `async define` function. Calling it makes a task that runs the
+    /// body once awaited or spawned.
+    AsyncFunction(Rc<str>, Vec<FunctionParam>, Rc<[ASTNode]>, Environment),
+    Class(Rc<Class>),
+    /// Instances are shared: every reference sees mutations of its fields.
+    Instance(Rc<Instance>),
+    /// A builtin implemented in Rust.
+    NativeFunction(Rc<NativeFunction>),
+    /// A method looked up through an instance, called with the instance
+    /// as its first argument.
+    BoundMethod(Box<Value>, Box<Value>),
+    /// `super` inside a method: the receiver, and the class whose bases
+    /// the next lookup starts from.
+    Super(Box<Value>, Rc<Class>),
+    /// A suspended call of a function containing `yield`.
+    Generator(Rc<Generator>),
+    /// `range(start, stop, step)`, whose numbers are only produced while
+    /// it is iterated.
+    Range(i64, i64, i64),
+    File(Rc<FileHandle>),
+    Task(Rc<Task>),
+    Module(Rc<Module>),
     None,
 }
 
+/// A parameter of a defined function. Its default was evaluated when the
+/// function was defined, and is shared by every call that leaves it out.
+#[derive(Clone)]
+pub struct FunctionParam {
+    pub name: String,
+    pub default: Option<Value>,
+    pub kind: ParamKind,
+}
+
+pub struct Class {
+    pub name: String,
+    pub bases: Vec<Rc<Class>>,
+    pub methods: HashMap<String, Value>,
+    /// Set on the built-in exception classes.
+    pub builtin_exception: bool,
+}
+
+impl Class {
+    /// Resolves a method on this class or, depth first and left to right,
+    /// on its bases. Returns the class defining it along with the method.
+    pub fn find_method(&self, name: &str) -> Option<(&Class, &Value)> {
+        match self.methods.get(name) {
+            Some(method) => Some((self, method)),
+            None => self.bases.iter().find_map(|base| base.find_method(name)),
+        }
+    }
+
+    /// Whether this class is `ancestor` or inherits from it.
+    pub fn is_subclass_of(&self, ancestor: &Class) -> bool {
+        std::ptr::eq(self, ancestor) || self.bases.iter().any(|base| base.is_subclass_of(ancestor))
+    }
+
+    /// Whether this class is a built-in exception class or inherits from one.
+    pub fn is_exception(&self) -> bool {
+        self.builtin_exception || self.bases.iter().any(|base| base.is_exception())
+    }
+}
+
+/// An imported standard library module or package, whose members are
+/// read as its attributes.
+pub struct Module {
+    pub name: String,
+    pub members: HashMap<String, Value>,
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub attributes: RefCell<HashMap<String, Value>>,
+}
+
+/// A generator: the local scope of a call to a function containing
+/// `yield`, and where its body is suspended. The body of an `async`
+/// function is suspended the same way at each `await`.
+pub struct Generator {
+    state: RefCell<GeneratorState>,
+}
+
+struct GeneratorState {
+    env: Environment,
+    /// The blocks, loops and `try` statements the body is suspended in,
+    /// innermost last. Empty once the body has finished.
+    frames: Vec<Frame>,
+    /// What the statement the body is suspended at does with the value
+    /// it resumes with.
+    resumption: Resumption,
+    started: bool,
+    running: bool,
+}
+
+impl Generator {
+    fn new(body: Rc<[ASTNode]>, env: Environment) -> Self {
+        Generator {
+            state: RefCell::new(GeneratorState {
+                env,
+                frames: vec![Frame::Block { body, next: 0 }],
+                resumption: Resumption::Discard,
+                started: false,
+                running: false,
+            }),
+        }
+    }
+}
+
+/// A unit of work for the event loop: a call of an `async` function, or a
+/// timer or combinator from the `async` module. It starts once awaited,
+/// spawned or called as a statement, and keeps its outcome for every later
+/// `await`.
+pub struct Task {
+    job: Job,
+    /// The call that made the task, where its own failures are reported.
+    span: Span,
+    state: RefCell<TaskState>,
+}
+
+enum Job {
+    /// The suspended body of an `async` function.
+    Coroutine(Generator),
+    Sleep(Duration),
+    /// The results of every task in order, or the first failure.
+    Gather(Vec<Rc<Task>>),
+    /// The outcome of the task, unless the duration runs out first.
+    Timeout(Rc<Task>, Duration),
+}
+
+#[derive(Default)]
+struct TaskState {
+    started: bool,
+    outcome: Option<Outcome>,
+    /// The tasks notified once this one finishes.
+    waiters: Vec<Rc<Task>>,
+}
+
+/// How a task finished: its result, or the exception it raised and where.
+type Outcome = Result<Value, (Value, Span)>;
+
+impl Task {
+    /// A new task, not started until it is awaited or spawned.
+    fn wrap(job: Job, span: &Span) -> Value {
+        Value::Task(Rc::new(Task {
+            job,
+            span: span.clone(),
+            state: RefCell::default(),
+        }))
+    }
+
+    pub(crate) fn sleep(duration: Duration, span: &Span) -> Value {
+        Task::wrap(Job::Sleep(duration), span)
+    }
+
+    pub(crate) fn gather(tasks: Vec<Rc<Task>>, span: &Span) -> Value {
+        Task::wrap(Job::Gather(tasks), span)
+    }
+
+    pub(crate) fn timeout(task: Rc<Task>, duration: Duration, span: &Span) -> Value {
+        Task::wrap(Job::Timeout(task, duration), span)
+    }
+
+    fn outcome(&self) -> Option<Outcome> {
+        self.state.borrow().outcome.clone()
+    }
+}
+
+/// Runs tasks for `async.run`. Coroutines take turns on the interpreter's
+/// thread, while timers sleep on the tokio runtime and report back over a
+/// channel once they fire.
+struct EventLoop {
+    runtime: Runtime,
+    /// Coroutines ready to resume, with the outcome of what they awaited.
+    ready: VecDeque<(Rc<Task>, Outcome)>,
+    /// The sleeps and timeouts waiting on a timer, by timer id.
+    timers: HashMap<u64, Rc<Task>>,
+    next_timer: u64,
+    fired_sender: UnboundedSender<u64>,
+    fired: UnboundedReceiver<u64>,
+    running: bool,
+}
+
+impl EventLoop {
+    fn new() -> Self {
+        let (fired_sender, fired) = mpsc::unbounded_channel();
+        EventLoop {
+            runtime: Runtime::new().expect("failed to start the tokio runtime"),
+            ready: VecDeque::new(),
+            timers: HashMap::new(),
+            next_timer: 0,
+            fired_sender,
+            fired,
+            running: false,
+        }
+    }
+
+    fn start_timer(&mut self, task: &Rc<Task>, duration: Duration) {
+        let id = self.next_timer;
+        self.next_timer += 1;
+        self.timers.insert(id, Rc::clone(task));
+        let sender = self.fired_sender.clone();
+        self.runtime.spawn(async move {
+            tokio::time::sleep(duration).await;
+            // The loop is gone if the interpreter was dropped meanwhile
+            let _ = sender.send(id);
+        });
+    }
+}
+
+/// The Rust side of a builtin: it receives already evaluated arguments, the
+/// count of which the interpreter has checked against `params`, and the
+/// span of the call for its errors.
+pub type NativeFn = fn(&[Value], &Span) -> Result<Value, BellronosError>;
+
+/// A builtin that drives the interpreter itself, such as `next` resuming a
+/// generator. Its signals, exceptions included, propagate like those of
+/// user code.
+pub(crate) type InterpreterFn =
+    fn(&mut BellronosInterpreter, &[Value], &Span) -> Result<Value, ControlFlow>;
+
+pub(crate) enum NativeBody {
+    Plain(NativeFn),
+    Interpreter(InterpreterFn),
+}
+
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub params: Vec<Type>,
+    pub return_type: Type,
+    pub(crate) func: NativeBody,
+}
+
+impl NativeFunction {
+    /// Whether a call may pass `count` arguments, given the builtin's
+    /// optional trailing parameters.
+    pub fn accepts(&self, count: usize) -> bool {
+        (required_params(&self.param_types())..=self.params.len()).contains(&count)
+    }
+
+    pub fn arity(&self) -> String {
+        arity_text(&self.param_types())
+    }
+
+    /// The type the checker gives this builtin.
+    pub fn signature(&self) -> Type {
+        Type::Function(self.param_types(), Box::new(self.return_type.clone()))
+    }
+
+    fn param_types(&self) -> Vec<ParamType> {
+        self.params
+            .iter()
+            .map(|param| match param {
+                Type::Optional(type_) => ParamType {
+                    kind: ParamKind::Optional,
+                    ..ParamType::positional(*type_.clone())
+                },
+                type_ => ParamType::positional(type_.clone()),
+            })
+            .collect()
+    }
+}
+
+impl Value {
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn dict(entries: HashMap<String, Value>) -> Value {
+        Value::Dict(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn native(
+        name: &'static str,
+        params: Vec<Type>,
+        return_type: Type,
+        func: NativeFn,
+    ) -> Value {
+        Value::NativeFunction(Rc::new(NativeFunction {
+            name,
+            params,
+            return_type,
+            func: NativeBody::Plain(func),
+        }))
+    }
+
+    pub(crate) fn intrinsic(
+        name: &'static str,
+        params: Vec<Type>,
+        return_type: Type,
+        func: InterpreterFn,
+    ) -> Value {
+        Value::NativeFunction(Rc::new(NativeFunction {
+            name,
+            params,
+            return_type,
+            func: NativeBody::Interpreter(func),
+        }))
+    }
+
+    pub fn set(elements: impl IntoIterator<Item = Value>) -> Value {
+        Value::Set(Rc::new(RefCell::new(elements.into_iter().collect())))
+    }
+
+    /// The name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+            Value::Dict(_) => "dict",
+            Value::Tuple(_) => "tuple",
+            Value::Set(_) => "set",
+            Value::Function(..) | Value::AsyncFunction(..) | Value::NativeFunction(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::BoundMethod(..) => "method",
+            Value::Super(..) => "super",
+            Value::Generator(..) => "generator",
+            Value::Range(..) => "range",
+            Value::File(_) => "file",
+            Value::Task(_) => "task",
+            Value::Module(_) => "module",
+            Value::None => "none",
+        }
+    }
+
+    /// Whether the value can be a set element. Mutable collections cannot,
+    /// since changing them would change their hash.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Value::List(_) | Value::Dict(_) | Value::Set(_) => false,
+            Value::Tuple(items) => items.iter().all(Value::is_hashable),
+            _ => true,
+        }
+    }
+
+    /// Identity, as tested by `is`. Heap objects are identical only when
+    /// they are the same object; plain values such as numbers and strings
+    /// have no identity and compare by value.
+    pub fn is(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Dict(a), Value::Dict(b)) => Rc::ptr_eq(a, b),
+            (Value::Set(a), Value::Set(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => self == other,
+        }
+    }
+
+    /// Truthiness used by conditions and boolean operators: `None`, `false`,
+    /// zero and empty strings or collections are false, everything else is
+    /// true.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::None => false,
+            Value::Bool(b) => *b,
+            Value::Int(i) => *i != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::String(s) => !s.is_empty(),
+            Value::List(l) => !l.borrow().is_empty(),
+            Value::Dict(d) => !d.borrow().is_empty(),
+            Value::Tuple(t) => !t.is_empty(),
+            Value::Set(s) => !s.borrow().is_empty(),
+            Value::Range(start, stop, step) => range_contains(*start, *stop, *step, *start),
+            _ => true,
+        }
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut Vec::new())
+    }
+}
+
+impl Value {
+    /// Equality by contents. `comparing` holds the pairs of collections
+    /// already being compared, so one containing itself still ends.
+    fn equals(&self, other: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
         match (self, other) {
             (Value::Int(a), Value::Int(b)) => a == b,
-            (Value::Float(a), Value::Float(b)) => (a - b).abs() < f64::EPSILON,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            // Exact, as for ints too large for a float to tell apart
+            (Value::Int(i), Value::Float(f)) | (Value::Float(f), Value::Int(i)) => {
+                integral(*f) == Some(*i)
+            }
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Bool(a), Value::Bool(b)) => a == b,
-            (Value::List(a), Value::List(b)) => a == b,
-            (Value::Dict(a), Value::Dict(b)) => a == b,
-            // For Function, Class, Instance, Closure, and Generator, compare memory addresses
-            (Value::Function(_, _, _), Value::Function(_, _, _)) => std::ptr::eq(self, other),
-            (Value::Class { .. }, Value::Class { .. }) => std::ptr::eq(self, other),
-            (Value::Instance { .. }, Value::Instance { .. }) => std::ptr::eq(self, other),
-            (Value::Closure(_, _, _), Value::Closure(_, _, _)) => std::ptr::eq(self, other),
-            (Value::Generator(_, _, _), Value::Generator(_, _, _)) => std::ptr::eq(self, other),
+            (Value::List(a), Value::List(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || comparing.contains(&pair) {
+                    return true;
+                }
+                comparing.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let equal = a.len() == b.len()
+                    && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y, comparing));
+                comparing.pop();
+                equal
+            }
+            (Value::Dict(a), Value::Dict(b)) => {
+                let pair = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if Rc::ptr_eq(a, b) || comparing.contains(&pair) {
+                    return true;
+                }
+                comparing.push(pair);
+                let (a, b) = (a.borrow(), b.borrow());
+                let equal = a.len() == b.len()
+                    && a.iter()
+                        .all(|(key, x)| b.get(key).is_some_and(|y| x.equals(y, comparing)));
+                comparing.pop();
+                equal
+            }
+            (Value::Tuple(a), Value::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y, comparing))
+            }
+            // Set elements are hashable, so they hold no lists or dicts
+            (Value::Set(a), Value::Set(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            // Code and objects compare by identity
+            (Value::Function(_, _, a, env_a), Value::Function(_, _, b, env_b))
+            | (Value::AsyncFunction(_, _, a, env_a), Value::AsyncFunction(_, _, b, env_b)) => {
+                Rc::ptr_eq(a, b) && Rc::ptr_eq(env_a, env_b)
+            }
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::BoundMethod(receiver_a, a), Value::BoundMethod(receiver_b, b)) => {
+                receiver_a.is(receiver_b) && a == b
+            }
+            (Value::Super(a, class_a), Value::Super(b, class_b)) => {
+                a.is(b) && Rc::ptr_eq(class_a, class_b)
+            }
+            (Value::Generator(a), Value::Generator(b)) => Rc::ptr_eq(a, b),
+            (Value::Range(start_a, stop_a, step_a), Value::Range(start_b, stop_b, step_b)) => {
+                (start_a, stop_a, step_a) == (start_b, stop_b, step_b)
+            }
+            (Value::File(a), Value::File(b)) => Rc::ptr_eq(a, b),
+            (Value::Task(a), Value::Task(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
             (Value::None, Value::None) => true,
             _ => false,
         }
@@ -77,193 +572,443 @@ impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Value::Int(i) => i.hash(state),
-            Value::Float(f) => f.to_bits().hash(state),
+            // A whole float hashes like the int it equals, and -0.0 like 0.0
+            Value::Float(f) => match integral(*f) {
+                Some(i) => i.hash(state),
+                None => f.to_bits().hash(state),
+            },
             Value::String(s) => s.hash(state),
             Value::Bool(b) => b.hash(state),
-            Value::List(l) => l.hash(state),
-            Value::Dict(d) => {
-                for (k, v) in d {
-                    k.hash(state);
-                    v.hash(state);
-                }
+            Value::List(l) => l.borrow().hash(state),
+            // Entry order is unspecified, so only the size is stable
+            Value::Dict(d) => d.borrow().len().hash(state),
+            Value::Set(s) => s.borrow().len().hash(state),
+            Value::Tuple(t) => t.hash(state),
+            Value::Function(_, _, body, _) | Value::AsyncFunction(_, _, body, _) => {
+                body.as_ptr().hash(state)
             }
-            // For Function, Class, Instance, Closure, and Generator, hash memory addresses
-            Value::Function(_, _, _)
-            | Value::Class { .. }
-            | Value::Instance { .. }
-            | Value::Closure(_, _, _)
-            | Value::Generator(_, _, _) => (self as *const Value).hash(state),
+            Value::Generator(generator) => Rc::as_ptr(generator).hash(state),
+            Value::NativeFunction(native) => Rc::as_ptr(native).hash(state),
+            Value::Class(class) => Rc::as_ptr(class).hash(state),
+            Value::Instance(instance) => Rc::as_ptr(instance).hash(state),
+            Value::BoundMethod(_, method) => method.hash(state),
+            Value::Super(_, class) => Rc::as_ptr(class).hash(state),
+            Value::Range(start, stop, step) => (start, stop, step).hash(state),
+            Value::File(file) => Rc::as_ptr(file).hash(state),
+            Value::Task(task) => Rc::as_ptr(task).hash(state),
+            Value::Module(module) => Rc::as_ptr(module).hash(state),
             Value::None => 0.hash(state),
         }
     }
 }
 
+/// Non-local exits raised while interpreting a statement. Errors ride the
+/// same channel so that `?` unwinds through every enclosing block until the
+/// function call, loop or `try` that handles the signal.
+pub(crate) enum ControlFlow {
+    Return(Value),
+    Break,
+    Continue,
+    /// An exception instance thrown by `raise`, with the span of the raise.
+    Raise(Box<Value>, Span),
+    /// A built-in failure. Inside `try` it is caught as an instance of
+    /// `BellronosError::exception_class`.
+    Error(BellronosError),
+}
+
+impl From<BellronosError> for ControlFlow {
+    fn from(err: BellronosError) -> Self {
+        ControlFlow::Error(err)
+    }
+}
+
+/// The state of one pass of a `for` loop over an iterable.
+enum Iteration {
+    /// Items taken up front: a snapshot of a collection or the characters
+    /// of a string.
+    Values(std::vec::IntoIter<Value>),
+    Range {
+        next: i64,
+        stop: i64,
+        step: i64,
+    },
+    /// The remaining lines of a file.
+    Lines(Rc<FileHandle>),
+    /// An instance returned by `__iter__`, advanced with `__next__`.
+    Object(Value),
+    Generator(Rc<Generator>),
+}
+
+/// One level of a generator body that a `yield` can suspend. Statements
+/// that don't yield run straight through `interpret`; these frames only
+/// record the statements a `yield` is nested in.
+enum Frame {
+    Block {
+        body: Rc<[ASTNode]>,
+        next: usize,
+    },
+    While {
+        condition: Rc<ASTNode>,
+        body: Rc<[ASTNode]>,
+    },
+    For {
+        target: Rc<ASTNode>,
+        body: Rc<[ASTNode]>,
+        iteration: Iteration,
+        span: Span,
+    },
+    /// A `try` whose body, or once `handled` one of its handlers, is
+    /// running.
+    Try {
+        handlers: Rc<[ExceptHandler]>,
+        finally: Rc<[ASTNode]>,
+        handled: bool,
+    },
+}
+
+/// What a suspended statement does with the value its body resumes with:
+/// the value sent to a `yield`, or the result of the awaited task.
+enum Resumption {
+    Discard,
+    /// `set x to yield ...` or `set x to await ...`.
+    Assign(ASTNode),
+    /// `return await ...`.
+    Return,
+}
+
+/// A value yielded by a generator, or the task awaited by a coroutine,
+/// and what the statement suspended at does once it resumes.
+type Suspension = (Value, Resumption);
+
+enum Resumed {
+    Suspended(Value),
+    /// The body has finished, returning the value.
+    Finished(Value),
+}
+
+impl Resumed {
+    /// What a generator yielded, or `None` once it has finished.
+    fn yielded(self) -> Option<Value> {
+        match self {
+            Resumed::Suspended(value) => Some(value),
+            Resumed::Finished(_) => None,
+        }
+    }
+}
+
 pub struct BellronosInterpreter {
     global_env: Environment,
+    env: Environment,
     type_checker: TypeChecker,
     stdlib: StandardLibrary,
     package_manager: PackageManager,
-    language_interop: LanguageInterop,
+    /// The built-in exception classes, used to raise built-in failures
+    /// even where user code shadows their names.
+    exceptions: HashMap<&'static str, Rc<Class>>,
+    tasks: EventLoop,
+    /// Function bodies and generator resumptions currently running.
+    call_depth: usize,
 }
 
 impl BellronosInterpreter {
     pub fn new() -> Self {
-        let global_env = Rc::new(RefCell::new(HashMap::new()));
+        let global_env = Scope::new_environment(None);
         let type_checker = TypeChecker::new();
         let stdlib = StandardLibrary::new();
-        let package_manager = PackageManager::new("packages".to_string());
-        let language_interop = LanguageInterop::new();
+        let tasks = EventLoop::new();
+        // The package manager downloads on the loop's runtime
+        let package_manager = {
+            let _runtime = tasks.runtime.enter();
+            PackageManager::new("packages".to_string())
+        };
+
+        let mut exceptions: HashMap<&'static str, Rc<Class>> = HashMap::new();
+        for (name, base) in BUILTIN_EXCEPTIONS {
+            let mut methods = HashMap::new();
+            if base.is_none() {
+                methods.insert("__init__".to_string(), exception_init(&global_env));
+            }
+            let class = Rc::new(Class {
+                name: name.to_string(),
+                bases: base.iter().map(|b| Rc::clone(&exceptions[b])).collect(),
+                methods,
+                builtin_exception: true,
+            });
+            global_env
+                .borrow_mut()
+                .define(name.to_string(), Value::Class(Rc::clone(&class)));
+            exceptions.insert(name, class);
+        }
+
+        for (name, builtin) in stdlib.builtins() {
+            global_env
+                .borrow_mut()
+                .define(name.clone(), builtin.clone());
+        }
 
         BellronosInterpreter {
+            env: Rc::clone(&global_env),
             global_env,
             type_checker,
             stdlib,
             package_manager,
-            language_interop,
+            exceptions,
+            tasks,
+            call_depth: 0,
         }
     }
 
-    pub fn run(&mut self, code: &str, _filename: &str) -> Result<(), BellronosError> {
-        let mut lexer = Lexer::new(code);
-        let tokens = lexer.tokenize()?;
-        let mut parser = Parser::new(tokens);
+    pub fn run(&mut self, code: &str, filename: &str) -> Result<(), BellronosError> {
+        let mut lexer = Lexer::new(code, filename);
+        let (tokens, lexical_errors) = lexer.tokenize();
+        let mut parser = Parser::new(tokens, lexical_errors);
         let ast = parser.parse()?;
 
-        self.interpret(&ast)?;
+        self.type_checker.check(&ast)?;
 
-        Ok(())
+        match self.interpret(&ast) {
+            Ok(_) => Ok(()),
+            Err(ControlFlow::Error(err)) => Err(err),
+            Err(ControlFlow::Raise(exception, span)) => Err(runtime_error(
+                &span,
+                ErrorCode::UncaughtException,
+                describe_exception(&exception),
+            )),
+            // The parser rejects return/break/continue outside of their
+            // enclosing function or loop, so these cannot reach the module.
+            Err(_) => unreachable!("control flow signal escaped the module"),
+        }
     }
 
-    fn interpret(&mut self, node: &ASTNode) -> Result<Value, BellronosError> {
+    fn interpret(&mut self, node: &ASTNode) -> Result<Value, ControlFlow> {
         match node {
-            ASTNode::Module { body } => {
+            ASTNode::Module { body, .. } => {
                 let mut result = Value::None;
                 for stmt in body {
                     result = self.interpret(stmt)?;
                 }
                 Ok(result)
             }
-            ASTNode::Import { names } => {
+            ASTNode::Import { names, span } => {
                 for name in names {
-                    if let Some(module) = self.stdlib.get_module(name) {
-                        self.global_env
-                            .borrow_mut()
-                            .insert(name.clone(), Value::Dict(module.clone()));
+                    let members = if let Some(module) = self.stdlib.get_module(name) {
+                        module.clone()
                     } else {
-                        let package_code = self.package_manager.load_package(name)?;
-                        self.run(&package_code, name)?;
+                        let package_code =
+                            self.package_manager.load_package(name).map_err(|err| {
+                                runtime_error(
+                                    span,
+                                    ErrorCode::InvalidPackage,
+                                    format!("Cannot import '{}': {}", name, error_message(&err)),
+                                )
+                            })?;
+                        let package_path = self.package_manager.package_path(name);
+                        // A package runs in a scope of its own, whose names
+                        // become the module's members
+                        let package_env = Scope::new_environment(Some(&self.global_env));
+                        let importer_env =
+                            std::mem::replace(&mut self.env, Rc::clone(&package_env));
+                        let result = self.run(&package_code, &package_path.to_string_lossy());
+                        self.env = importer_env;
+                        result?;
+                        let members = package_env.borrow().vars.clone();
+                        members
+                    };
+                    let module = Module {
+                        name: name.clone(),
+                        members,
+                    };
+                    self.env
+                        .borrow_mut()
+                        .define(name.clone(), Value::Module(Rc::new(module)));
+                }
+                Ok(Value::None)
+            }
+            ASTNode::Nonlocal { names, span } => {
+                for name in names {
+                    if !self.env.borrow_mut().declare_nonlocal(name) {
+                        return Err(ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::UndefinedName,
+                            format!("No binding for nonlocal '{}' in an enclosing scope", name),
+                        )));
                     }
                 }
                 Ok(Value::None)
             }
-            ASTNode::FunctionDef {
-                name,
-                args,
-                return_type: _,
-                body,
-            } => {
-                let func = Value::Function(
-                    args.iter().map(|(name, _)| name.clone()).collect(),
-                    body.clone(),
-                    Rc::clone(&self.global_env),
-                );
-                self.global_env.borrow_mut().insert(name.clone(), func);
+            ASTNode::Unbind { names, .. } => {
+                let mut scope = self.env.borrow_mut();
+                for name in names {
+                    scope.vars.remove(name);
+                }
+                Ok(Value::None)
+            }
+            ASTNode::FunctionDef { .. } | ASTNode::Async { .. } => {
+                let env = Rc::clone(&self.env);
+                if let Some((name, func)) = self.function_value(node, &env)? {
+                    self.env.borrow_mut().define(name, func);
+                }
                 Ok(Value::None)
             }
-            ASTNode::ClassDef { name, methods } => {
+            ASTNode::ClassDef {
+                name,
+                bases,
+                methods,
+                decorators,
+                span,
+            } => {
+                let decorators = self.evaluate_all(decorators)?;
+                let mut base_classes = Vec::new();
+                for base in bases {
+                    match self.env.borrow().get(base) {
+                        Some(Value::Class(class)) => base_classes.push(class),
+                        _ => {
+                            return Err(ControlFlow::from(runtime_error(
+                                span,
+                                ErrorCode::UnknownClass,
+                                format!("Base class '{}' not found", base),
+                            )))
+                        }
+                    }
+                }
+                // Methods close over a class scope that binds `__class__`,
+                // which is where `super` starts its lookup
+                let class_env = Scope::new_environment(Some(&self.env));
                 let mut class_methods = HashMap::new();
                 for method in methods {
-                    if let ASTNode::FunctionDef {
-                        name: method_name,
-                        args,
-                        return_type: _,
-                        body,
-                    } = method
+                    if let Some((method_name, method_func)) =
+                        self.function_value(method, &class_env)?
                     {
-                        let method_func = Value::Function(
-                            args.iter().map(|(name, _)| name.clone()).collect(),
-                            body.clone(),
-                            Rc::clone(&self.global_env),
-                        );
-                        class_methods.insert(method_name.clone(), method_func);
+                        class_methods.insert(method_name, method_func);
                     }
                 }
-                let class = Value::Class {
+                let class = Value::Class(Rc::new(Class {
+                    name: name.clone(),
+                    bases: base_classes,
                     methods: class_methods,
-                };
-                self.global_env.borrow_mut().insert(name.clone(), class);
+                    builtin_exception: false,
+                }));
+                class_env
+                    .borrow_mut()
+                    .define("__class__".to_string(), class.clone());
+                let class = self.decorate(decorators, class)?;
+                self.env.borrow_mut().define(name.clone(), class);
                 Ok(Value::None)
             }
-            ASTNode::Assign { target, value } => {
+            ASTNode::Assign { target, value, .. } => {
                 let val = self.interpret(value)?;
-                self.global_env.borrow_mut().insert(target.clone(), val);
+                self.assign(target, val)?;
                 Ok(Value::None)
             }
-            ASTNode::Expr { value } => self.interpret(value),
-            ASTNode::Call { func, args } => {
-                let f = self.global_env.borrow().get(func).cloned().ok_or_else(|| {
-                    BellronosError::Runtime(format!("Undefined function: {}", func))
-                })?;
-                match f {
-                    Value::Function(params, body, env) => {
-                        let mut local_env = env.borrow().clone();
-                        for (param, arg) in params.iter().zip(args) {
-                            local_env.insert(param.clone(), self.interpret(arg)?);
+            ASTNode::Expr { value, span } => match self.interpret(value)? {
+                // A task called for its effect still runs: to completion
+                // from synchronous code, or alongside the running task
+                Value::Task(task) if !task.state.borrow().started => {
+                    if self.tasks.running {
+                        self.start_task(&task);
+                        Ok(Value::None)
+                    } else {
+                        self.run_task(&task, span)
+                    }
+                }
+                value => Ok(value),
+            },
+            ASTNode::Call { callee, args, span } => {
+                let function = self.interpret(callee)?;
+                let mut values = Vec::new();
+                let mut keywords = Vec::new();
+                for arg in args {
+                    match arg {
+                        Argument::Positional(value) => values.push(self.interpret(value)?),
+                        Argument::Keyword(name, value) => {
+                            keywords.push((name.clone(), self.interpret(value)?))
                         }
-                        let local_env = Rc::new(RefCell::new(local_env));
-                        let mut result = Value::None;
-                        for stmt in &body {
-                            result = self.interpret_with_env(stmt, &local_env)?;
+                        Argument::Unpack(value) => {
+                            let iterable = self.interpret(value)?;
+                            self.for_each(iterable, value.span(), |_, item| {
+                                values.push(item);
+                                Ok(())
+                            })?;
                         }
-                        Ok(result)
+                        Argument::UnpackKeywords(value) => match self.interpret(value)? {
+                            Value::Dict(entries) => keywords.extend(
+                                entries.borrow().iter().map(|(k, v)| (k.clone(), v.clone())),
+                            ),
+                            other => {
+                                return Err(ControlFlow::from(runtime_error(
+                                    value.span(),
+                                    ErrorCode::TypeMismatch,
+                                    format!(
+                                        "Argument after ** must be a dict, not {}",
+                                        other.type_name()
+                                    ),
+                                )))
+                            }
+                        },
                     }
-                    Value::Class { methods: _ } => {
-                        let instance = Value::Instance {
-                            class: func.clone(),
-                            attributes: HashMap::new(),
-                        };
-                        Ok(instance)
+                }
+                self.call_with_keywords(function, values, keywords, span)
+            }
+            ASTNode::Str { value, .. } => Ok(Value::String(value.clone())),
+            ASTNode::Int { value, .. } => Ok(Value::Int(*value)),
+            ASTNode::Float { value, .. } => Ok(Value::Float(*value)),
+            ASTNode::Bool { value, .. } => Ok(Value::Bool(*value)),
+            ASTNode::Name { id, .. } if id == "super" => {
+                let env = self.env.borrow();
+                match (env.get("self"), env.get("__class__")) {
+                    (Some(receiver), Some(Value::Class(class))) => {
+                        Ok(Value::Super(Box::new(receiver), class))
                     }
-                    _ => Err(BellronosError::Runtime(format!("{} is not callable", func))),
-                }
-            }
-            ASTNode::Str { value } => Ok(Value::String(value.clone())),
-            ASTNode::Num { value } => Ok(Value::Float(*value)),
-            ASTNode::Bool { value } => Ok(Value::Bool(*value)),
-            ASTNode::Name { id } => self
-                .global_env
-                .borrow()
-                .get(id)
-                .cloned()
-                .ok_or_else(|| BellronosError::Runtime(format!("Undefined variable: {}", id))),
-            ASTNode::BinOp { left, op, right } => {
+                    _ => Err(ControlFlow::from(runtime_error(
+                        node.span(),
+                        ErrorCode::UndefinedName,
+                        "'super' used outside of a method".to_string(),
+                    ))),
+                }
+            }
+            ASTNode::Name { id, span } => Ok(self.env.borrow().get(id).ok_or_else(|| {
+                runtime_error(
+                    span,
+                    ErrorCode::UndefinedName,
+                    format!("Undefined variable: {}", id),
+                )
+            })?),
+            ASTNode::BinOp {
+                left,
+                op,
+                right,
+                span,
+            } => {
                 let left_val = self.interpret(left)?;
+                if op.is_logical() {
+                    // Short-circuit: the right side only runs when it decides
+                    let left_truthy = left_val.is_truthy();
+                    if left_truthy == (*op == BinaryOperator::Or) {
+                        return Ok(Value::Bool(left_truthy));
+                    }
+                    return Ok(Value::Bool(self.interpret(right)?.is_truthy()));
+                }
                 let right_val = self.interpret(right)?;
-                match (left_val, op.as_str(), right_val) {
-                    (Value::Int(l), "+", Value::Int(r)) => Ok(Value::Int(l + r)),
-                    (Value::Float(l), "+", Value::Float(r)) => Ok(Value::Float(l + r)),
-                    (Value::String(l), "+", Value::String(r)) => Ok(Value::String(l + &r)),
-                    (Value::Int(l), "-", Value::Int(r)) => Ok(Value::Int(l - r)),
-                    (Value::Float(l), "-", Value::Float(r)) => Ok(Value::Float(l - r)),
-                    (Value::Int(l), "*", Value::Int(r)) => Ok(Value::Int(l * r)),
-                    (Value::Float(l), "*", Value::Float(r)) => Ok(Value::Float(l * r)),
-                    (Value::Int(l), "/", Value::Int(r)) => Ok(Value::Float(l as f64 / r as f64)),
-                    (Value::Float(l), "/", Value::Float(r)) => Ok(Value::Float(l / r)),
-                    (Value::Int(l), ">", Value::Int(r)) => Ok(Value::Bool(l > r)),
-                    (Value::Float(l), ">", Value::Float(r)) => Ok(Value::Bool(l > r)),
-                    (Value::Int(l), "<", Value::Int(r)) => Ok(Value::Bool(l < r)),
-                    (Value::Float(l), "<", Value::Float(r)) => Ok(Value::Bool(l < r)),
-                    (Value::Int(l), ">=", Value::Int(r)) => Ok(Value::Bool(l >= r)),
-                    (Value::Float(l), ">=", Value::Float(r)) => Ok(Value::Bool(l >= r)),
-                    (Value::Int(l), "<=", Value::Int(r)) => Ok(Value::Bool(l <= r)),
-                    (Value::Float(l), "<=", Value::Float(r)) => Ok(Value::Bool(l <= r)),
-                    (Value::Int(l), "==", Value::Int(r)) => Ok(Value::Bool(l == r)),
-                    (Value::Float(l), "==", Value::Float(r)) => Ok(Value::Bool(l == r)),
-                    (Value::String(l), "==", Value::String(r)) => Ok(Value::Bool(l == r)),
-                    (Value::Bool(l), "==", Value::Bool(r)) => Ok(Value::Bool(l == r)),
-                    _ => Err(BellronosError::Runtime(format!(
-                        "Unsupported operation: {:?} {} {:?}",
-                        left, op, right
+                Ok(binary_op(*op, left_val, right_val, span)?)
+            }
+            ASTNode::UnaryOp { op, operand, span } => {
+                let value = self.interpret(operand)?;
+                match (op, value) {
+                    (UnaryOperator::Not, value) => Ok(Value::Bool(!value.is_truthy())),
+                    (UnaryOperator::Negate, Value::Int(i)) => {
+                        Ok(Value::Int(i.checked_neg().ok_or_else(|| {
+                            runtime_error(
+                                span,
+                                ErrorCode::InvalidOperation,
+                                format!("Integer overflow in -{}", i),
+                            )
+                        })?))
+                    }
+                    (UnaryOperator::Negate, Value::Float(f)) => Ok(Value::Float(-f)),
+                    (UnaryOperator::Negate, value) => Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::InvalidOperation,
+                        format!("Unsupported operation: -{:?}", value),
                     ))),
                 }
             }
@@ -272,178 +1017,1993 @@ impl BellronosInterpreter {
                 condition,
                 body,
                 orelse,
+                ..
             } => {
                 let cond_value = self.interpret(condition)?;
-                if let Value::Bool(true) = cond_value {
-                    for stmt in body {
-                        self.interpret(stmt)?;
-                    }
-                } else {
-                    for stmt in orelse {
-                        self.interpret(stmt)?;
-                    }
+                let branch = if cond_value.is_truthy() { body } else { orelse };
+                for stmt in branch {
+                    self.interpret(stmt)?;
                 }
                 Ok(Value::None)
             }
-            ASTNode::While { condition, body } => {
-                loop {
-                    let cond_value = self.interpret(condition)?;
-                    if let Value::Bool(true) = cond_value {
-                        for stmt in body {
-                            self.interpret(stmt)?;
-                        }
-                    } else {
+            ASTNode::While {
+                condition, body, ..
+            } => {
+                while self.interpret(condition)?.is_truthy() {
+                    if !self.run_loop_body(body)? {
                         break;
                     }
                 }
                 Ok(Value::None)
             }
-            ASTNode::For { target, iter, body } => {
-                let iter_value = self.interpret(iter)?;
-                if let Value::List(items) = iter_value {
-                    for item in items {
-                        self.global_env.borrow_mut().insert(target.clone(), item);
-                        for stmt in body {
-                            self.interpret(stmt)?;
-                        }
+            ASTNode::For {
+                target, iter, body, ..
+            } => {
+                let iterable = self.interpret(iter)?;
+                let mut iteration = self.iterate(iterable, iter.span())?;
+                while let Some(item) = self.next_item(&mut iteration, iter.span())? {
+                    self.assign(target, item)?;
+                    if !self.run_loop_body(body)? {
+                        break;
                     }
-                } else {
-                    return Err(BellronosError::Runtime(
-                        "For loop iterable must be a list".to_string(),
-                    ));
                 }
                 Ok(Value::None)
             }
-            ASTNode::Return { value } => {
-                if let Some(v) = value {
-                    self.interpret(v)
-                } else {
-                    Ok(Value::None)
+            ASTNode::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                let result = match self.run_block(body) {
+                    Err(signal @ (ControlFlow::Raise(..) | ControlFlow::Error(_))) => {
+                        self.handle_exception(signal, handlers)
+                    }
+                    other => other,
+                };
+                // `finally` always runs; its own signal replaces the body's
+                self.run_block(finally)?;
+                result
+            }
+            ASTNode::Raise { value, span } => match self.interpret(value)? {
+                class @ Value::Class(_) if self.is_exception(&class) => {
+                    let exception = self.call_value(class, Vec::new(), span)?;
+                    Err(ControlFlow::Raise(Box::new(exception), span.clone()))
                 }
+                exception @ Value::Instance(_) if self.is_exception(&exception) => {
+                    Err(ControlFlow::Raise(Box::new(exception), span.clone()))
+                }
+                other => Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::TypeMismatch,
+                    format!("Exceptions must derive from Exception, not {:?}", other),
+                ))),
+            },
+            ASTNode::Return { value, .. } => {
+                let value = match value {
+                    Some(v) => self.interpret(v)?,
+                    None => Value::None,
+                };
+                Err(ControlFlow::Return(value))
             }
-            ASTNode::Closure { params, body } => Ok(Value::Closure(
-                params.iter().map(|(name, _)| name.clone()).collect(),
-                vec![*body.clone()],
-                Rc::clone(&self.global_env),
+            ASTNode::Break { .. } => Err(ControlFlow::Break),
+            ASTNode::Continue { .. } => Err(ControlFlow::Continue),
+            ASTNode::Closure { params, body, .. } => Ok(Value::Function(
+                "<anonymous>".into(),
+                self.parameters(params)?,
+                body.as_slice().into(),
+                Rc::clone(&self.env),
             )),
-            ASTNode::Generator { body } => Ok(Value::Generator(
-                body.clone(),
-                Rc::clone(&self.global_env),
-                0,
-            )),
-            ASTNode::Yield { value: _ } => Err(BellronosError::Runtime(
+            // Generator bodies suspend at their yields without coming here
+            ASTNode::Yield { span, .. } => Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::YieldOutsideGenerator,
                 "Yield outside of generator".to_string(),
-            )),
-            ASTNode::Async { body } => {
-                let mut result = Value::None;
-                for stmt in body {
-                    result = self.interpret(stmt)?;
-                }
-                Ok(result)
-            }
-            ASTNode::Await { value } => self.interpret(value),
-            ASTNode::List { elements } => {
+            ))),
+            // Coroutines suspend at their awaits without coming here
+            ASTNode::Await { span, .. } => Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::MisplacedAwait,
+                "'await' outside of a running task".to_string(),
+            ))),
+            ASTNode::List { elements, .. } => {
                 let mut list = Vec::new();
                 for elem in elements {
                     list.push(self.interpret(elem)?);
                 }
-                Ok(Value::List(list))
+                Ok(Value::list(list))
             }
-            ASTNode::Dict { pairs } => {
+            ASTNode::Tuple { elements, .. } => {
+                let mut items = Vec::new();
+                for elem in elements {
+                    items.push(self.interpret(elem)?);
+                }
+                Ok(Value::Tuple(items.into()))
+            }
+            ASTNode::Set { elements, .. } => {
+                let mut items = Vec::new();
+                for elem in elements {
+                    let value = self.interpret(elem)?;
+                    check_hashable(&value, elem.span())?;
+                    items.push(value);
+                }
+                Ok(Value::set(items))
+            }
+            ASTNode::Dict { pairs, .. } => {
                 let mut dict = HashMap::new();
                 for (key, value) in pairs {
                     if let Value::String(k) = self.interpret(key)? {
                         let v = self.interpret(value)?;
                         dict.insert(k, v);
                     } else {
-                        return Err(BellronosError::Runtime(
+                        return Err(ControlFlow::from(runtime_error(
+                            key.span(),
+                            ErrorCode::TypeMismatch,
                             "Dictionary keys must be strings".to_string(),
-                        ));
+                        )));
                     }
                 }
-                Ok(Value::Dict(dict))
+                Ok(Value::dict(dict))
+            }
+            ASTNode::Subscript { value, index, span } => {
+                let container = self.interpret(value)?;
+                if let ASTNode::Slice {
+                    start, stop, step, ..
+                } = &**index
+                {
+                    let start = self.slice_bound(start)?;
+                    let stop = self.slice_bound(stop)?;
+                    let step = self.slice_bound(step)?;
+                    return Ok(slice(&container, start, stop, step, span)?);
+                }
+                let key = self.interpret(index)?;
+                Ok(get_item(&container, &key, span)?)
             }
-            ASTNode::Attribute { value, attr } => {
+            ASTNode::Slice { span, .. } => Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::InvalidOperation,
+                "A slice can only be used inside a subscript".to_string(),
+            ))),
+            ASTNode::Attribute { value, attr, span } => {
                 let obj = self.interpret(value)?;
-                match obj {
-                    Value::Instance { class, attributes } => {
-                        if let Some(attr_value) = attributes.get(attr) {
-                            Ok(attr_value.clone())
-                        } else if let Some(Value::Class { methods }) =
-                            self.global_env.borrow().get(&class)
-                        {
-                            if let Some(method) = methods.get(attr) {
-                                Ok(method.clone())
-                            } else {
-                                Err(BellronosError::Runtime(format!(
+                match &obj {
+                    Value::Instance(instance) => {
+                        if let Some(attr_value) = instance.attributes.borrow().get(attr) {
+                            return Ok(attr_value.clone());
+                        }
+                        match instance.class.find_method(attr) {
+                            // Looking a method up through an instance binds it
+                            Some((_, method)) => Ok(Value::BoundMethod(
+                                Box::new(obj.clone()),
+                                Box::new(method.clone()),
+                            )),
+                            None => Err(ControlFlow::from(runtime_error(
+                                span,
+                                ErrorCode::UnknownAttribute,
+                                format!(
                                     "Attribute '{}' not found on instance of class '{}'",
-                                    attr, class
-                                )))
-                            }
-                        } else {
-                            Err(BellronosError::Runtime(format!(
-                                "Class '{}' not found",
-                                class
-                            )))
+                                    attr, instance.class.name
+                                ),
+                            ))),
                         }
                     }
-                    _ => Err(BellronosError::Runtime(format!(
-                        "Cannot access attribute '{}' on non-instance type",
-                        attr
-                    ))),
+                    Value::Class(class) => match class.find_method(attr) {
+                        Some((_, method)) => Ok(method.clone()),
+                        None => Err(ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::UnknownAttribute,
+                            format!("Class '{}' has no attribute '{}'", class.name, attr),
+                        ))),
+                    },
+                    Value::Super(receiver, class) => {
+                        match class.bases.iter().find_map(|base| base.find_method(attr)) {
+                            Some((_, method)) => Ok(Value::BoundMethod(
+                                receiver.clone(),
+                                Box::new(method.clone()),
+                            )),
+                            None => Err(ControlFlow::from(runtime_error(
+                                span,
+                                ErrorCode::UnknownAttribute,
+                                format!(
+                                    "No base of class '{}' has a method '{}'",
+                                    class.name, attr
+                                ),
+                            ))),
+                        }
+                    }
+                    Value::Module(module) => module.members.get(attr).cloned().ok_or_else(|| {
+                        ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::UnknownAttribute,
+                            format!("Module '{}' has no attribute '{}'", module.name, attr),
+                        ))
+                    }),
+                    // Methods of built-in types, such as `set.union`
+                    _ => match self.stdlib.get_method(obj.type_name(), attr) {
+                        Some(method) => Ok(Value::BoundMethod(
+                            Box::new(obj.clone()),
+                            Box::new(method.clone()),
+                        )),
+                        None => Err(ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::UnknownAttribute,
+                            format!("Cannot access attribute '{}' on {:?}", attr, obj),
+                        ))),
+                    },
                 }
             }
-            ASTNode::InteropCall { language, code } => {
-                self.language_interop.execute(language, code)
-            }
         }
     }
 
-    fn interpret_with_env(
+    /// Counts a body about to run, failing once `MAX_CALL_DEPTH` are
+    /// running. The caller decrements `call_depth` after.
+    fn enter_call(&mut self, span: &Span) -> Result<(), ControlFlow> {
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::RecursionLimit,
+                format!("Maximum recursion depth of {} exceeded", MAX_CALL_DEPTH),
+            )));
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    /// Runs a function body in `scope`, restoring the caller's scope
+    /// afterwards even when the body fails. A body that finishes without
+    /// `return` produces `None`.
+    fn call_in_scope(
+        &mut self,
+        body: &[ASTNode],
+        scope: Environment,
+    ) -> Result<Value, ControlFlow> {
+        let caller_env = std::mem::replace(&mut self.env, scope);
+        let result = body
+            .iter()
+            .try_for_each(|stmt| self.interpret(stmt).map(drop));
+        self.env = caller_env;
+        match result {
+            Ok(()) => Ok(Value::None),
+            Err(ControlFlow::Return(value)) => Ok(value),
+            Err(signal) => Err(signal),
+        }
+    }
+
+    /// The name and value a function definition binds, for plain and
+    /// `async define` functions alike.
+    fn function_value(
         &mut self,
-        node: &ASTNode,
+        def: &ASTNode,
         env: &Environment,
-    ) -> Result<Value, BellronosError> {
-        let old_env = self.global_env.clone();
-        *self.global_env.borrow_mut() = env.borrow().clone();
-        let result = self.interpret(node);
-        *self.global_env.borrow_mut() = old_env.borrow().clone();
-        result
+    ) -> Result<Option<(String, Value)>, ControlFlow> {
+        let (def, is_async) = def.unwrap_async();
+        let ASTNode::FunctionDef {
+            name,
+            args,
+            body,
+            decorators,
+            ..
+        } = def
+        else {
+            return Ok(None);
+        };
+        let decorators = self.evaluate_all(decorators)?;
+        let params = self.parameters(args)?;
+        let body = body.as_slice().into();
+        let func = if is_async {
+            Value::AsyncFunction(name.as_str().into(), params, body, Rc::clone(env))
+        } else {
+            Value::Function(name.as_str().into(), params, body, Rc::clone(env))
+        };
+        Ok(Some((name.clone(), self.decorate(decorators, func)?)))
     }
-}
 
-impl std::fmt::Debug for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Value::Int(i) => write!(f, "{}", i),
-            Value::Float(fl) => write!(f, "{}", fl),
-            Value::String(s) => write!(f, "\"{}\"", s),
+    /// Evaluates the decorators of a definition, top first, before the
+    /// definition itself.
+    fn evaluate_all<'a>(
+        &mut self,
+        nodes: &'a [ASTNode],
+    ) -> Result<Vec<(Value, &'a Span)>, ControlFlow> {
+        nodes
+            .iter()
+            .map(|node| Ok((self.interpret(node)?, node.span())))
+            .collect()
+    }
+
+    /// Applies decorators to a defined function or class, the one nearest
+    /// the definition first, so `@a @b define f` binds `a(b(f))`.
+    fn decorate(
+        &mut self,
+        decorators: Vec<(Value, &Span)>,
+        mut value: Value,
+    ) -> Result<Value, ControlFlow> {
+        for (decorator, span) in decorators.into_iter().rev() {
+            value = self.call_value(decorator, vec![value], span)?;
+        }
+        Ok(value)
+    }
+
+    /// Evaluates the defaults of a function's parameters in the scope it is
+    /// defined in.
+    fn parameters(&mut self, params: &[Parameter]) -> Result<Vec<FunctionParam>, ControlFlow> {
+        let mut evaluated = Vec::new();
+        for param in params {
+            let default = match &param.default {
+                Some(default) => Some(self.interpret(default)?),
+                None => None,
+            };
+            evaluated.push(FunctionParam {
+                name: param.name.clone(),
+                default,
+                kind: param.kind,
+            });
+        }
+        Ok(evaluated)
+    }
+
+    /// Calls a function, bound method or class with already evaluated
+    /// arguments.
+    pub(crate) fn call_value(
+        &mut self,
+        function: Value,
+        args: Vec<Value>,
+        span: &Span,
+    ) -> Result<Value, ControlFlow> {
+        self.call_with_keywords(function, args, Vec::new(), span)
+    }
+
+    /// Calls a function, bound method or class with evaluated positional
+    /// and keyword arguments, in the order they were written.
+    pub(crate) fn call_with_keywords(
+        &mut self,
+        function: Value,
+        mut args: Vec<Value>,
+        keywords: Vec<(String, Value)>,
+        span: &Span,
+    ) -> Result<Value, ControlFlow> {
+        let is_async = matches!(function, Value::AsyncFunction(..));
+        match function {
+            Value::Function(name, params, body, env)
+            | Value::AsyncFunction(name, params, body, env) => {
+                let local_env = Scope::new_environment(Some(&env));
+                bind_arguments(&name, &params, args, keywords, &local_env, span)?;
+                if is_async {
+                    let coroutine = Generator::new(body, local_env);
+                    return Ok(Task::wrap(Job::Coroutine(coroutine), span));
+                }
+                // A function that yields runs only as its generator resumes
+                if body.iter().any(ASTNode::contains_yield) {
+                    return Ok(Value::Generator(Rc::new(Generator::new(body, local_env))));
+                }
+                self.enter_call(span)?;
+                let result = self.call_in_scope(&body, local_env);
+                self.call_depth -= 1;
+                result
+            }
+            Value::NativeFunction(native) => {
+                if let Some((name, _)) = keywords.first() {
+                    return Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::UnexpectedKeyword,
+                        format!(
+                            "Function {} got an unexpected keyword argument '{}'",
+                            native.name, name
+                        ),
+                    )));
+                }
+                if !native.accepts(args.len()) {
+                    return Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::ArgumentCount,
+                        format!(
+                            "Function {} expects {} arguments, but {} were given",
+                            native.name,
+                            native.arity(),
+                            args.len()
+                        ),
+                    )));
+                }
+                match native.func {
+                    NativeBody::Plain(func) => Ok(func(&args, span).map_err(|err| err.at(span))?),
+                    NativeBody::Interpreter(func) => func(self, &args, span),
+                }
+            }
+            Value::BoundMethod(receiver, method) => {
+                args.insert(0, *receiver);
+                self.call_with_keywords(*method, args, keywords, span)
+            }
+            Value::Class(class) => {
+                let instance = Value::Instance(Rc::new(Instance {
+                    class: Rc::clone(&class),
+                    attributes: RefCell::new(HashMap::new()),
+                }));
+                let root = Rc::as_ptr(&self.exceptions["Exception"]);
+                let init = class
+                    .find_method("__init__")
+                    .map(|(owner, method)| (std::ptr::eq(owner, root), method.clone()));
+                match init {
+                    // Built-in exceptions may be created without a message
+                    Some((true, _)) if args.is_empty() && keywords.is_empty() => {
+                        set_attribute(&instance, "message", Value::String(String::new()));
+                    }
+                    Some((_, init)) => {
+                        args.insert(0, instance.clone());
+                        self.call_with_keywords(init, args, keywords, span)?;
+                    }
+                    None if args.is_empty() && keywords.is_empty() => {}
+                    None => {
+                        return Err(ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::ArgumentCount,
+                            format!(
+                                "Class '{}' takes no arguments, but {} were given",
+                                class.name,
+                                args.len() + keywords.len()
+                            ),
+                        )))
+                    }
+                }
+                Ok(instance)
+            }
+            other => Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::NotCallable,
+                format!("{:?} is not callable", other),
+            ))),
+        }
+    }
+
+    /// Stores `value` into an assignment target. Lists, dicts and instances
+    /// are shared, so elements and attributes are updated in place.
+    fn assign(&mut self, target: &ASTNode, value: Value) -> Result<(), ControlFlow> {
+        match target {
+            ASTNode::Name { id, .. } => {
+                self.env.borrow_mut().assign(id, value);
+                Ok(())
+            }
+            // Destructuring: the value is fully evaluated before any target
+            // is bound, so `set (a, b) to (b, a)` swaps
+            ASTNode::Tuple { elements, span } => {
+                let items = match &value {
+                    Value::Tuple(items) => items.to_vec(),
+                    Value::List(items) => items.borrow().clone(),
+                    other => {
+                        return Err(ControlFlow::from(runtime_error(
+                            span,
+                            ErrorCode::TypeMismatch,
+                            format!("Cannot unpack {:?}", other),
+                        )))
+                    }
+                };
+                if items.len() != elements.len() {
+                    return Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::InvalidValue,
+                        format!(
+                            "Expected {} values to unpack, but got {}",
+                            elements.len(),
+                            items.len()
+                        ),
+                    )));
+                }
+                for (element, item) in elements.iter().zip(items) {
+                    self.assign(element, item)?;
+                }
+                Ok(())
+            }
+            ASTNode::Subscript {
+                value: source,
+                index,
+                span,
+            } => {
+                if let ASTNode::Slice { span, .. } = &**index {
+                    return Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::InvalidOperation,
+                        "Slice assignment is not supported".to_string(),
+                    )));
+                }
+                let key = self.interpret(index)?;
+                let container = self.interpret(source)?;
+                Ok(set_item(&container, key, value, span)?)
+            }
+            ASTNode::Attribute {
+                value: source,
+                attr,
+                span,
+            } => match self.interpret(source)? {
+                obj @ Value::Instance(_) => {
+                    set_attribute(&obj, attr, value);
+                    Ok(())
+                }
+                other => Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::UnknownAttribute,
+                    format!("Cannot set attribute '{}' on {:?}", attr, other),
+                ))),
+            },
+            other => Err(ControlFlow::from(runtime_error(
+                other.span(),
+                ErrorCode::InvalidAssignmentTarget,
+                "Cannot assign to this expression".to_string(),
+            ))),
+        }
+    }
+
+    fn slice_bound(&mut self, part: &Option<Box<ASTNode>>) -> Result<Option<i64>, ControlFlow> {
+        match part {
+            None => Ok(None),
+            Some(expr) => match self.interpret(expr)? {
+                Value::Int(i) => Ok(Some(i)),
+                Value::None => Ok(None),
+                other => Err(ControlFlow::from(runtime_error(
+                    expr.span(),
+                    ErrorCode::TypeMismatch,
+                    format!("Slice indices must be integers, not {:?}", other),
+                ))),
+            },
+        }
+    }
+
+    fn run_block(&mut self, body: &[ASTNode]) -> Result<Value, ControlFlow> {
+        for stmt in body {
+            self.interpret(stmt)?;
+        }
+        Ok(Value::None)
+    }
+
+    /// Runs the first `except` clause matching a raised exception. Signals
+    /// no clause matches, and errors that aren't exceptions, pass through.
+    fn handle_exception(
+        &mut self,
+        signal: ControlFlow,
+        handlers: &[ExceptHandler],
+    ) -> Result<Value, ControlFlow> {
+        match self.find_handler(&signal, handlers)? {
+            Some(handler) => self.run_block(&handler.body),
+            None => Err(signal),
+        }
+    }
+
+    /// The first `except` clause matching a raised exception, with the
+    /// exception already bound to the clause's name.
+    fn find_handler<'a>(
+        &mut self,
+        signal: &ControlFlow,
+        handlers: &'a [ExceptHandler],
+    ) -> Result<Option<&'a ExceptHandler>, ControlFlow> {
+        let exception = match signal {
+            ControlFlow::Raise(exception, _) => (**exception).clone(),
+            ControlFlow::Error(err) => match err.exception_class() {
+                Some(class) => self.new_exception(class, error_message(err)),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let Value::Instance(instance) = &exception else {
+            return Ok(None);
+        };
+        for handler in handlers {
+            let matches = match &handler.class {
+                None => true,
+                Some(expected) => match self.env.borrow().get(expected) {
+                    Some(Value::Class(expected)) => instance.class.is_subclass_of(&expected),
+                    _ => {
+                        return Err(ControlFlow::from(runtime_error(
+                            &handler.span,
+                            ErrorCode::UnknownClass,
+                            format!("Exception class '{}' not found", expected),
+                        )))
+                    }
+                },
+            };
+            if matches {
+                if let Some(name) = &handler.name {
+                    self.env.borrow_mut().define(name.clone(), exception);
+                }
+                return Ok(Some(handler));
+            }
+        }
+        Ok(None)
+    }
+
+    fn new_exception(&self, class: &str, message: String) -> Value {
+        let mut attributes = HashMap::new();
+        attributes.insert("message".to_string(), Value::String(message));
+        Value::Instance(Rc::new(Instance {
+            class: Rc::clone(&self.exceptions[class]),
+            attributes: RefCell::new(attributes),
+        }))
+    }
+
+    /// Whether `value` is an exception class or an instance of one.
+    fn is_exception(&self, value: &Value) -> bool {
+        match value {
+            Value::Class(class) => class.is_exception(),
+            Value::Instance(instance) => instance.class.is_exception(),
+            _ => false,
+        }
+    }
+
+    /// Starts a pass over `iterable` for a `for` loop. Collections are
+    /// iterated over a snapshot so the loop body may mutate them; an
+    /// instance is iterated through its `__iter__` method.
+    fn iterate(&mut self, iterable: Value, span: &Span) -> Result<Iteration, ControlFlow> {
+        if let Some(iter_method) = bound_method(&iterable, "__iter__") {
+            let iterator = self.call_value(iter_method, Vec::new(), span)?;
+            return match (&iterator, bound_method(&iterator, "__next__")) {
+                (_, Some(_)) => Ok(Iteration::Object(iterator)),
+                (Value::Instance(_), None) => Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::TypeMismatch,
+                    format!(
+                        "__iter__ returned {:?}, which has no __next__ method",
+                        iterator
+                    ),
+                ))),
+                // `__iter__` may also hand back any other iterable
+                (_, None) => self.iterate(iterator, span),
+            };
+        }
+        let items = match iterable {
+            Value::List(items) => items.borrow().clone(),
+            Value::Tuple(items) => items.to_vec(),
+            Value::Set(elements) => sorted_elements(elements.borrow().iter()),
+            // Dict keys, like set elements, come out in a stable order
+            Value::Dict(entries) => {
+                let mut keys: Vec<String> = entries.borrow().keys().cloned().collect();
+                keys.sort();
+                keys.into_iter().map(Value::String).collect()
+            }
+            Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+            Value::Range(start, stop, step) => {
+                return Ok(Iteration::Range {
+                    next: start,
+                    stop,
+                    step,
+                })
+            }
+            Value::File(file) => return Ok(Iteration::Lines(file)),
+            Value::Generator(generator) => return Ok(Iteration::Generator(generator)),
+            other => {
+                return Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::TypeMismatch,
+                    format!("Type '{}' is not iterable", other.type_name()),
+                )))
+            }
+        };
+        Ok(Iteration::Values(items.into_iter()))
+    }
+
+    /// Runs `f` on each item of an iterable in turn, for builtins that
+    /// consume one the way a `for` loop does.
+    pub(crate) fn for_each(
+        &mut self,
+        iterable: Value,
+        span: &Span,
+        mut f: impl FnMut(&mut Self, Value) -> Result<(), ControlFlow>,
+    ) -> Result<(), ControlFlow> {
+        let mut iteration = self.iterate(iterable, span)?;
+        while let Some(item) = self.next_item(&mut iteration, span)? {
+            f(self, item)?;
+        }
+        Ok(())
+    }
+
+    /// Advances a `for` loop's iteration. Returns `None` once it is
+    /// exhausted, which for a user iterator is when `__next__` raises
+    /// `StopIteration`.
+    fn next_item(
+        &mut self,
+        iteration: &mut Iteration,
+        span: &Span,
+    ) -> Result<Option<Value>, ControlFlow> {
+        match iteration {
+            Iteration::Values(items) => Ok(items.next()),
+            Iteration::Range { next, stop, step } => {
+                if !range_contains(*next, *stop, *step, *next) {
+                    return Ok(None);
+                }
+                let current = *next;
+                // Stepping past i64's range ends the iteration
+                *next = next.checked_add(*step).unwrap_or(*stop);
+                Ok(Some(Value::Int(current)))
+            }
+            Iteration::Lines(file) => Ok(file.read_line(span)?.map(Value::String)),
+            Iteration::Generator(generator) => {
+                Ok(self.resume(generator, Ok(Value::None), span)?.yielded())
+            }
+            Iteration::Object(iterator) => {
+                let next_method =
+                    bound_method(iterator, "__next__").expect("checked when the iteration started");
+                match self.call_value(next_method, Vec::new(), span) {
+                    Err(ControlFlow::Raise(exception, _))
+                        if is_instance_of(&exception, &self.exceptions["StopIteration"]) =>
+                    {
+                        Ok(None)
+                    }
+                    result => result.map(Some),
+                }
+            }
+        }
+    }
+
+    /// Resumes a generator for `next` or `send`, raising `StopIteration`
+    /// once it is exhausted, with the value it returned as `value`. An
+    /// instance with a `__next__` method is advanced through it.
+    pub(crate) fn send(
+        &mut self,
+        iterator: &Value,
+        sent: Value,
+        span: &Span,
+    ) -> Result<Value, ControlFlow> {
+        let resumed = match iterator {
+            Value::Generator(generator) => self.resume(generator, Ok(sent), span)?,
+            _ => match bound_method(iterator, "__next__") {
+                Some(next_method) => return self.call_value(next_method, Vec::new(), span),
+                None => {
+                    return Err(ControlFlow::from(runtime_error(
+                        span,
+                        ErrorCode::TypeMismatch,
+                        format!("Type '{}' is not an iterator", iterator.type_name()),
+                    )))
+                }
+            },
+        };
+        match resumed {
+            Resumed::Suspended(value) => Ok(value),
+            Resumed::Finished(value) => {
+                let exception = self.new_exception("StopIteration", String::new());
+                if let Value::Instance(instance) = &exception {
+                    instance
+                        .attributes
+                        .borrow_mut()
+                        .insert("value".to_string(), value);
+                }
+                Err(ControlFlow::Raise(Box::new(exception), span.clone()))
+            }
+        }
+    }
+
+    /// Runs a generator's body until the next `yield` or `await`, first
+    /// handing `input` to the statement it is suspended at.
+    fn resume(
+        &mut self,
+        generator: &Generator,
+        input: Result<Value, ControlFlow>,
+        span: &Span,
+    ) -> Result<Resumed, ControlFlow> {
+        self.enter_call(span)?;
+        let resumed = self.resume_body(generator, input, span);
+        self.call_depth -= 1;
+        resumed
+    }
+
+    fn resume_body(
+        &mut self,
+        generator: &Generator,
+        input: Result<Value, ControlFlow>,
+        span: &Span,
+    ) -> Result<Resumed, ControlFlow> {
+        let (env, mut frames, resumption) = {
+            let mut state = generator.state.borrow_mut();
+            if state.running {
+                return Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::InvalidValue,
+                    "Generator is already running".to_string(),
+                )));
+            }
+            if !state.started && matches!(&input, Ok(sent) if *sent != Value::None) {
+                return Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::TypeMismatch,
+                    "Cannot send a value to a generator that has not started".to_string(),
+                )));
+            }
+            state.started = true;
+            state.running = true;
+            (
+                Rc::clone(&state.env),
+                std::mem::take(&mut state.frames),
+                std::mem::replace(&mut state.resumption, Resumption::Discard),
+            )
+        };
+        let caller_env = std::mem::replace(&mut self.env, env);
+        let result = match (input, resumption) {
+            (Err(signal), _) => self.unwind(&mut frames, signal),
+            (Ok(value), Resumption::Assign(target)) => self.assign(&target, value),
+            (Ok(value), Resumption::Return) => self.unwind(&mut frames, ControlFlow::Return(value)),
+            (Ok(_), Resumption::Discard) => Ok(()),
+        }
+        .and_then(|()| self.run_frames(&mut frames));
+        self.env = caller_env;
+
+        let mut state = generator.state.borrow_mut();
+        state.running = false;
+        match result {
+            Ok(Some((value, resumption))) => {
+                state.frames = frames;
+                state.resumption = resumption;
+                Ok(Resumed::Suspended(value))
+            }
+            Ok(None) => Ok(Resumed::Finished(Value::None)),
+            Err(ControlFlow::Return(value)) => Ok(Resumed::Finished(value)),
+            Err(signal) => Err(signal),
+        }
+    }
+
+    /// Steps through a generator's frames until a `yield` or `await`
+    /// suspends it or the frames run out.
+    fn run_frames(&mut self, frames: &mut Vec<Frame>) -> Result<Option<Suspension>, ControlFlow> {
+        while !frames.is_empty() {
+            match self.step_frame(frames) {
+                Ok(Some(suspension)) => return Ok(Some(suspension)),
+                Ok(None) => {}
+                Err(signal) => self.unwind(frames, signal)?,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Advances the innermost frame by one statement or loop iteration.
+    fn step_frame(&mut self, frames: &mut Vec<Frame>) -> Result<Option<Suspension>, ControlFlow> {
+        let Some(frame) = frames.last_mut() else {
+            return Ok(None);
+        };
+        match frame {
+            Frame::Block { body, next } => {
+                if *next == body.len() {
+                    frames.pop();
+                    return Ok(None);
+                }
+                let body = Rc::clone(body);
+                *next += 1;
+                self.step_statement(&body[*next - 1], frames)
+            }
+            Frame::While { condition, body } => {
+                let (condition, body) = (Rc::clone(condition), Rc::clone(body));
+                if self.interpret(&condition)?.is_truthy() {
+                    frames.push(Frame::Block { body, next: 0 });
+                } else {
+                    frames.pop();
+                }
+                Ok(None)
+            }
+            Frame::For {
+                target,
+                body,
+                iteration,
+                span,
+            } => {
+                let (target, body, span) = (Rc::clone(target), Rc::clone(body), span.clone());
+                match self.next_item(iteration, &span)? {
+                    Some(item) => {
+                        self.assign(&target, item)?;
+                        frames.push(Frame::Block { body, next: 0 });
+                    }
+                    None => {
+                        frames.pop();
+                    }
+                }
+                Ok(None)
+            }
+            // The body or handler finished without a signal
+            Frame::Try { finally, .. } => {
+                let finally = Rc::clone(finally);
+                frames.pop();
+                self.run_block(&finally)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs one statement of a generator or coroutine body, opening a
+    /// frame for any block it enters.
+    fn step_statement(
+        &mut self,
+        stmt: &ASTNode,
+        frames: &mut Vec<Frame>,
+    ) -> Result<Option<Suspension>, ControlFlow> {
+        if !stmt.contains_yield() && !stmt.contains_await() {
+            self.interpret(stmt)?;
+            return Ok(None);
+        }
+        let (point, resumption) = match stmt {
+            ASTNode::Yield { .. } => (stmt, Resumption::Discard),
+            ASTNode::Expr { value, .. } => (&**value, Resumption::Discard),
+            ASTNode::Assign { target, value, .. } => {
+                (&**value, Resumption::Assign((**target).clone()))
+            }
+            ASTNode::Return {
+                value: Some(value), ..
+            } => (&**value, Resumption::Return),
+            _ => return self.open_frame(stmt, frames).map(|()| None),
+        };
+        match point {
+            ASTNode::Yield { value, .. } | ASTNode::Await { value, .. } => {
+                Ok(Some((self.interpret(value)?, resumption)))
+            }
+            _ => unreachable!("only a yield or an await suspends a statement"),
+        }
+    }
+
+    /// Pushes the frames of a compound statement that suspends within its
+    /// body.
+    fn open_frame(&mut self, stmt: &ASTNode, frames: &mut Vec<Frame>) -> Result<(), ControlFlow> {
+        match stmt {
+            ASTNode::If {
+                condition,
+                body,
+                orelse,
+                ..
+            } => {
+                let branch = if self.interpret(condition)?.is_truthy() {
+                    body
+                } else {
+                    orelse
+                };
+                frames.push(Frame::Block {
+                    body: branch.as_slice().into(),
+                    next: 0,
+                });
+                Ok(())
+            }
+            ASTNode::While {
+                condition, body, ..
+            } => {
+                frames.push(Frame::While {
+                    condition: Rc::new((**condition).clone()),
+                    body: body.as_slice().into(),
+                });
+                Ok(())
+            }
+            ASTNode::For {
+                target, iter, body, ..
+            } => {
+                let iterable = self.interpret(iter)?;
+                let iteration = self.iterate(iterable, iter.span())?;
+                frames.push(Frame::For {
+                    target: Rc::new((**target).clone()),
+                    body: body.as_slice().into(),
+                    iteration,
+                    span: iter.span().clone(),
+                });
+                Ok(())
+            }
+            ASTNode::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                frames.push(Frame::Try {
+                    handlers: handlers.as_slice().into(),
+                    finally: finally.as_slice().into(),
+                    handled: false,
+                });
+                frames.push(Frame::Block {
+                    body: body.as_slice().into(),
+                    next: 0,
+                });
+                Ok(())
+            }
+            _ => unreachable!("no other statement suspends in its body"),
+        }
+    }
+
+    /// Pops a generator's frames until one handles `signal`, running the
+    /// `finally` blocks left on the way.
+    fn unwind(
+        &mut self,
+        frames: &mut Vec<Frame>,
+        mut signal: ControlFlow,
+    ) -> Result<(), ControlFlow> {
+        while let Some(frame) = frames.last_mut() {
+            match frame {
+                Frame::While { .. } | Frame::For { .. } => match signal {
+                    ControlFlow::Break => {
+                        frames.pop();
+                        return Ok(());
+                    }
+                    ControlFlow::Continue => return Ok(()),
+                    _ => {
+                        frames.pop();
+                    }
+                },
+                Frame::Try {
+                    handlers,
+                    finally,
+                    handled,
+                } => {
+                    let (handlers, finally) = (Rc::clone(handlers), Rc::clone(finally));
+                    if !*handled && matches!(signal, ControlFlow::Raise(..) | ControlFlow::Error(_))
+                    {
+                        *handled = true;
+                        if let Some(handler) = self.find_handler(&signal, &handlers)? {
+                            frames.push(Frame::Block {
+                                body: handler.body.as_slice().into(),
+                                next: 0,
+                            });
+                            return Ok(());
+                        }
+                    }
+                    frames.pop();
+                    // A signal from `finally` replaces the one unwinding
+                    if let Err(finally_signal) = self.run_block(&finally) {
+                        signal = finally_signal;
+                    }
+                }
+                Frame::Block { .. } => {
+                    frames.pop();
+                }
+            }
+        }
+        Err(signal)
+    }
+
+    /// Runs the event loop until `task` finishes, returning its result or
+    /// raising its exception. Tasks still unfinished then are abandoned.
+    pub(crate) fn run_task(&mut self, task: &Rc<Task>, span: &Span) -> Result<Value, ControlFlow> {
+        if self.tasks.running {
+            return Err(ControlFlow::from(runtime_error(
+                span,
+                ErrorCode::MisplacedAwait,
+                "async.run cannot be called from a running task; await the task instead"
+                    .to_string(),
+            )));
+        }
+        self.tasks.running = true;
+        self.start_task(task);
+        let result = self.run_until_finished(task, span);
+        self.tasks.running = false;
+        self.tasks.ready.clear();
+        self.tasks.timers.clear();
+        result
+    }
+
+    fn run_until_finished(&mut self, task: &Rc<Task>, span: &Span) -> Result<Value, ControlFlow> {
+        loop {
+            if let Some(outcome) = task.outcome() {
+                return outcome
+                    .map_err(|(exception, span)| ControlFlow::Raise(Box::new(exception), span));
+            }
+            if let Some((ready, input)) = self.tasks.ready.pop_front() {
+                self.step_task(&ready, input);
+                continue;
+            }
+            if self.tasks.timers.is_empty() {
+                return Err(ControlFlow::from(runtime_error(
+                    span,
+                    ErrorCode::TaskDeadlock,
+                    "Task can never finish: every task it waits on is waiting too".to_string(),
+                )));
+            }
+            let EventLoop { runtime, fired, .. } = &mut self.tasks;
+            if let Some(id) = runtime.block_on(fired.recv()) {
+                self.fire_timer(id);
+            }
+        }
+    }
+
+    /// Schedules a task the first time it is awaited or spawned.
+    pub(crate) fn start_task(&mut self, task: &Rc<Task>) {
+        if std::mem::replace(&mut task.state.borrow_mut().started, true) {
+            return;
+        }
+        match &task.job {
+            Job::Coroutine(_) => self
+                .tasks
+                .ready
+                .push_back((Rc::clone(task), Ok(Value::None))),
+            Job::Sleep(duration) => self.tasks.start_timer(task, *duration),
+            Job::Gather(tasks) if tasks.is_empty() => {
+                self.finish_task(task, Ok(Value::list(Vec::new())))
+            }
+            Job::Gather(tasks) => {
+                for awaited in tasks {
+                    self.start_task(awaited);
+                    self.watch(awaited, task);
+                }
+            }
+            Job::Timeout(awaited, duration) => {
+                self.tasks.start_timer(task, *duration);
+                self.start_task(awaited);
+                self.watch(awaited, task);
+            }
+        }
+    }
+
+    /// Resumes a coroutine with the outcome of what it awaited, until it
+    /// awaits again or finishes.
+    fn step_task(&mut self, task: &Rc<Task>, input: Outcome) {
+        let Job::Coroutine(coroutine) = &task.job else {
+            unreachable!("only coroutines wait in the ready queue");
+        };
+        // Cut short by a timeout while it was waiting
+        if task.outcome().is_some() {
+            return;
+        }
+        let input =
+            input.map_err(|(exception, span)| ControlFlow::Raise(Box::new(exception), span));
+        match self.resume(coroutine, input, &task.span) {
+            Ok(Resumed::Suspended(Value::Task(awaited))) => {
+                self.start_task(&awaited);
+                self.watch(&awaited, task);
+            }
+            Ok(Resumed::Suspended(other)) => {
+                let exception = self.new_exception(
+                    "TypeError",
+                    format!("Object of type '{}' cannot be awaited", other.type_name()),
+                );
+                let failure = Err((exception, task.span.clone()));
+                self.tasks.ready.push_back((Rc::clone(task), failure));
+            }
+            Ok(Resumed::Finished(value)) => self.finish_task(task, Ok(value)),
+            Err(signal) => {
+                let failure = self.failure(signal, &task.span);
+                self.finish_task(task, Err(failure));
+            }
+        }
+    }
+
+    /// Notifies `waiter` once `task` finishes, right away if it already
+    /// has.
+    fn watch(&mut self, task: &Rc<Task>, waiter: &Rc<Task>) {
+        match task.outcome() {
+            Some(outcome) => self.notify(waiter, outcome),
+            None => task.state.borrow_mut().waiters.push(Rc::clone(waiter)),
+        }
+    }
+
+    fn notify(&mut self, waiter: &Rc<Task>, outcome: Outcome) {
+        match &waiter.job {
+            Job::Coroutine(_) => self.tasks.ready.push_back((Rc::clone(waiter), outcome)),
+            Job::Gather(tasks) => {
+                if outcome.is_err() {
+                    return self.finish_task(waiter, outcome);
+                }
+                let results: Option<Vec<Value>> = tasks
+                    .iter()
+                    .map(|task| task.outcome().and_then(Result::ok))
+                    .collect();
+                if let Some(results) = results {
+                    self.finish_task(waiter, Ok(Value::list(results)));
+                }
+            }
+            Job::Timeout(..) => self.finish_task(waiter, outcome),
+            Job::Sleep(_) => unreachable!("a sleep waits on no task"),
+        }
+    }
+
+    /// Records how a task finished, unless it already has, and notifies
+    /// the tasks waiting on it.
+    fn finish_task(&mut self, task: &Rc<Task>, outcome: Outcome) {
+        let waiters = {
+            let mut state = task.state.borrow_mut();
+            if state.outcome.is_some() {
+                return;
+            }
+            state.outcome = Some(outcome.clone());
+            std::mem::take(&mut state.waiters)
+        };
+        if let Job::Timeout(..) = task.job {
+            self.tasks
+                .timers
+                .retain(|_, timer| !Rc::ptr_eq(timer, task));
+        }
+        for waiter in waiters {
+            self.notify(&waiter, outcome.clone());
+        }
+    }
+
+    /// Finishes a sleep, or a timeout whose task is still running. That
+    /// task is cancelled with the same `TimeoutError`.
+    fn fire_timer(&mut self, id: u64) {
+        let Some(task) = self.tasks.timers.remove(&id) else {
+            return;
+        };
+        match &task.job {
+            Job::Timeout(awaited, duration) => {
+                let exception = self.new_exception(
+                    "TimeoutError",
+                    format!(
+                        "Task did not finish within {} seconds",
+                        duration.as_secs_f64()
+                    ),
+                );
+                let failure: Outcome = Err((exception, task.span.clone()));
+                self.finish_task(&task, failure.clone());
+                self.finish_task(awaited, failure);
+            }
+            _ => self.finish_task(&task, Ok(Value::None)),
+        }
+    }
+
+    /// The exception a failed task raises where it is awaited. Built-in
+    /// errors become instances of their exception class, as in `try`.
+    fn failure(&self, signal: ControlFlow, span: &Span) -> (Value, Span) {
+        match signal {
+            ControlFlow::Raise(exception, span) => (*exception, span),
+            ControlFlow::Error(err) => {
+                let class = err.exception_class().unwrap_or("RuntimeError");
+                let span = err
+                    .diagnostic()
+                    .and_then(|diagnostic| diagnostic.span.clone())
+                    .unwrap_or_else(|| span.clone());
+                (self.new_exception(class, error_message(&err)), span)
+            }
+            // The parser keeps break and continue inside their loops
+            _ => unreachable!("loop control signal escaped a task"),
+        }
+    }
+
+    /// Runs one iteration of a loop body. Returns `false` once the loop
+    /// should stop because of `break`.
+    fn run_loop_body(&mut self, body: &[ASTNode]) -> Result<bool, ControlFlow> {
+        for stmt in body {
+            match self.interpret(stmt) {
+                Ok(_) => {}
+                Err(ControlFlow::Break) => return Ok(false),
+                Err(ControlFlow::Continue) => return Ok(true),
+                Err(signal) => return Err(signal),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Applies a binary operator. Int-only arithmetic stays in `i64` (true
+/// division aside); mixing ints and floats promotes both sides to `f64`.
+fn binary_op(
+    op: BinaryOperator,
+    left: Value,
+    right: Value,
+    span: &Span,
+) -> Result<Value, BellronosError> {
+    use BinaryOperator::*;
+    let zero = matches!(right, Value::Int(0)) || matches!(right, Value::Float(r) if r == 0.0);
+    if zero && matches!(op, Divide | FloorDivide | Modulo) {
+        return Err(runtime_error(
+            span,
+            ErrorCode::DivisionByZero,
+            format!("Division by zero: {:?} {} {:?}", left, op, right),
+        ));
+    }
+    let result = match (&left, &right) {
+        _ if op == Equal => Some(Value::Bool(left == right)),
+        _ if op == NotEqual => Some(Value::Bool(left != right)),
+        _ if op == Is => Some(Value::Bool(left.is(&right))),
+        _ if op == IsNot => Some(Value::Bool(!left.is(&right))),
+        _ if op == In => Some(Value::Bool(contains(&right, &left, span)?)),
+        _ if op == NotIn => Some(Value::Bool(!contains(&right, &left, span)?)),
+        (Value::Int(l), Value::Int(r)) => {
+            let value = int_op(op, *l, *r);
+            if value.is_none() && !op.is_comparison() {
+                return Err(runtime_error(
+                    span,
+                    ErrorCode::InvalidOperation,
+                    format!("Integer overflow in {} {} {}", l, op, r),
+                ));
+            }
+            value
+        }
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+            float_op(op, as_float(&left), as_float(&right))
+        }
+        (Value::String(l), Value::String(r)) => match op {
+            Add => Some(Value::String(format!("{}{}", l, r))),
+            _ => compare(op, l, r),
+        },
+        // Concatenation makes a new list
+        (Value::List(l), Value::List(r)) if op == Add => {
+            let mut items = l.borrow().clone();
+            items.extend(r.borrow().iter().cloned());
+            Some(Value::list(items))
+        }
+        _ => None,
+    };
+    result.ok_or_else(|| {
+        runtime_error(
+            span,
+            ErrorCode::InvalidOperation,
+            format!("Unsupported operation: {:?} {} {:?}", left, op, right),
+        )
+    })
+}
+
+/// Integer arithmetic with Python's flooring `//` and `%`. Returns `None`
+/// on overflow.
+fn int_op(op: BinaryOperator, l: i64, r: i64) -> Option<Value> {
+    use BinaryOperator::*;
+    match op {
+        Add => l.checked_add(r).map(Value::Int),
+        Subtract => l.checked_sub(r).map(Value::Int),
+        Multiply => l.checked_mul(r).map(Value::Int),
+        Divide => Some(Value::Float(l as f64 / r as f64)),
+        FloorDivide => {
+            let quotient = l.checked_div(r)?;
+            let floor = if l % r != 0 && (l < 0) != (r < 0) {
+                quotient - 1
+            } else {
+                quotient
+            };
+            Some(Value::Int(floor))
+        }
+        Modulo => {
+            let rem = l.checked_rem(r)?;
+            Some(Value::Int(if rem != 0 && (rem < 0) != (r < 0) {
+                rem + r
+            } else {
+                rem
+            }))
+        }
+        Power if r >= 0 => u32::try_from(r)
+            .ok()
+            .and_then(|exp| l.checked_pow(exp))
+            .map(Value::Int),
+        Power => Some(Value::Float((l as f64).powf(r as f64))),
+        _ => compare(op, &l, &r),
+    }
+}
+
+fn float_op(op: BinaryOperator, l: f64, r: f64) -> Option<Value> {
+    use BinaryOperator::*;
+    match op {
+        Add => Some(Value::Float(l + r)),
+        Subtract => Some(Value::Float(l - r)),
+        Multiply => Some(Value::Float(l * r)),
+        Divide => Some(Value::Float(l / r)),
+        FloorDivide => Some(Value::Float((l / r).floor())),
+        Modulo => {
+            let rem = l % r;
+            Some(Value::Float(if rem != 0.0 && (rem < 0.0) != (r < 0.0) {
+                rem + r
+            } else {
+                rem
+            }))
+        }
+        Power => Some(Value::Float(l.powf(r))),
+        _ => compare(op, &l, &r),
+    }
+}
+
+fn compare<T: PartialOrd + ?Sized>(op: BinaryOperator, l: &T, r: &T) -> Option<Value> {
+    match op {
+        BinaryOperator::Less => Some(Value::Bool(l < r)),
+        BinaryOperator::Greater => Some(Value::Bool(l > r)),
+        BinaryOperator::LessEqual => Some(Value::Bool(l <= r)),
+        BinaryOperator::GreaterEqual => Some(Value::Bool(l >= r)),
+        _ => None,
+    }
+}
+
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+/// The int a float equals, if it is a whole number within range.
+fn integral(f: f64) -> Option<i64> {
+    // -2^63 converts exactly, so the bounds hold exactly too
+    let bound = -(i64::MIN as f64);
+    (f.fract() == 0.0 && (-bound..bound).contains(&f)).then_some(f as i64)
+}
+
+/// Whether `item` is in `container`: an element of a sequence or set, a
+/// key of a dict, or a substring of a string.
+fn contains(container: &Value, item: &Value, span: &Span) -> Result<bool, BellronosError> {
+    match (container, item) {
+        (Value::List(items), _) => Ok(items.borrow().iter().any(|i| i == item)),
+        (Value::Tuple(items), _) => Ok(items.iter().any(|i| i == item)),
+        (Value::Set(elements), _) => {
+            check_hashable(item, span)?;
+            Ok(elements.borrow().contains(item))
+        }
+        (Value::Range(start, stop, step), Value::Int(i)) => {
+            Ok(range_contains(*start, *stop, *step, *i))
+        }
+        (Value::Range(start, stop, step), Value::Float(f)) => {
+            Ok(integral(*f).is_some_and(|i| range_contains(*start, *stop, *step, i)))
+        }
+        (Value::Range(..), _) => Ok(false),
+        (Value::Dict(entries), Value::String(key)) => Ok(entries.borrow().contains_key(key)),
+        (Value::Dict(_), _) => Ok(false),
+        (Value::String(s), Value::String(sub)) => Ok(s.contains(sub.as_str())),
+        (Value::String(_), other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("'in <string>' requires a string, not {}", other.type_name()),
+        )),
+        (other, _) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!(
+                "Argument of type '{}' is not a collection",
+                other.type_name()
+            ),
+        )),
+    }
+}
+
+/// Whether `range(start, stop, step)` produces `i`.
+fn range_contains(start: i64, stop: i64, step: i64, i: i64) -> bool {
+    let in_bounds = if step > 0 {
+        start <= i && i < stop
+    } else {
+        stop < i && i <= start
+    };
+    in_bounds && (i as i128 - start as i128) % step as i128 == 0
+}
+
+pub(crate) fn check_hashable(value: &Value, span: &Span) -> Result<(), BellronosError> {
+    if value.is_hashable() {
+        Ok(())
+    } else {
+        Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("Unhashable type: '{}'", value.type_name()),
+        ))
+    }
+}
+
+/// The elements of a set in a stable order, numbers first by value and
+/// then everything else by how it prints, so that iterating and printing
+/// sets is deterministic.
+pub(crate) fn sorted_elements<'a>(elements: impl IntoIterator<Item = &'a Value>) -> Vec<Value> {
+    let key = |value: &Value| match value {
+        Value::Int(_) | Value::Float(_) => (0, as_float(value), String::new()),
+        other => (1, 0.0, format!("{:?}", other)),
+    };
+    let mut sorted: Vec<Value> = elements.into_iter().cloned().collect();
+    sorted.sort_by(|a, b| {
+        let (rank_a, num_a, text_a) = key(a);
+        let (rank_b, num_b, text_b) = key(b);
+        rank_a
+            .cmp(&rank_b)
+            .then(num_a.total_cmp(&num_b))
+            .then(text_a.cmp(&text_b))
+    });
+    sorted
+}
+
+/// Resolves a possibly negative index against a sequence of `len` items.
+pub(crate) fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let index = if index < 0 { index + len } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+fn get_item(container: &Value, key: &Value, span: &Span) -> Result<Value, BellronosError> {
+    match (container, key) {
+        (Value::List(items), Value::Int(i)) => {
+            let items = items.borrow();
+            resolve_index(*i, items.len())
+                .map(|i| items[i].clone())
+                .ok_or_else(|| {
+                    runtime_error(
+                        span,
+                        ErrorCode::IndexOutOfRange,
+                        format!("List index {} out of range for length {}", i, items.len()),
+                    )
+                })
+        }
+        (Value::Tuple(items), Value::Int(i)) => resolve_index(*i, items.len())
+            .map(|i| items[i].clone())
+            .ok_or_else(|| {
+                runtime_error(
+                    span,
+                    ErrorCode::IndexOutOfRange,
+                    format!("Tuple index {} out of range for length {}", i, items.len()),
+                )
+            }),
+        (Value::String(s), Value::Int(i)) => {
+            let chars: Vec<char> = s.chars().collect();
+            resolve_index(*i, chars.len())
+                .map(|i| Value::String(chars[i].to_string()))
+                .ok_or_else(|| {
+                    runtime_error(
+                        span,
+                        ErrorCode::IndexOutOfRange,
+                        format!("String index {} out of range for length {}", i, chars.len()),
+                    )
+                })
+        }
+        (Value::Dict(entries), Value::String(k)) => {
+            entries.borrow().get(k).cloned().ok_or_else(|| {
+                runtime_error(
+                    span,
+                    ErrorCode::KeyNotFound,
+                    format!("Key {:?} not found", k),
+                )
+            })
+        }
+        (Value::List(_) | Value::Tuple(_) | Value::String(_), other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("Indices must be integers, not {:?}", other),
+        )),
+        (Value::Dict(_), other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("Dictionary keys must be strings, not {:?}", other),
+        )),
+        (other, _) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{:?} is not subscriptable", other),
+        )),
+    }
+}
+
+fn set_item(
+    container: &Value,
+    key: Value,
+    value: Value,
+    span: &Span,
+) -> Result<(), BellronosError> {
+    match (container, key) {
+        (Value::List(items), Value::Int(i)) => {
+            let mut items = items.borrow_mut();
+            let len = items.len();
+            let slot = resolve_index(i, len).ok_or_else(|| {
+                runtime_error(
+                    span,
+                    ErrorCode::IndexOutOfRange,
+                    format!(
+                        "List assignment index {} out of range for length {}",
+                        i, len
+                    ),
+                )
+            })?;
+            items[slot] = value;
+            Ok(())
+        }
+        (Value::Dict(entries), Value::String(k)) => {
+            entries.borrow_mut().insert(k, value);
+            Ok(())
+        }
+        (Value::List(_), other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("List indices must be integers, not {:?}", other),
+        )),
+        (Value::Dict(_), other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("Dictionary keys must be strings, not {:?}", other),
+        )),
+        (other, _) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{:?} does not support item assignment", other),
+        )),
+    }
+}
+
+/// Positions selected by a `start:stop:step` slice over `len` items, with
+/// Python's clamping of out-of-range bounds.
+fn slice_indices(len: usize, start: Option<i64>, stop: Option<i64>, step: i64) -> Vec<usize> {
+    let len = len as i64;
+    let clamp = |bound: i64, low: i64, high: i64| {
+        let bound = if bound < 0 { bound + len } else { bound };
+        bound.clamp(low, high)
+    };
+    if step > 0 {
+        let start = start.map_or(0, |b| clamp(b, 0, len));
+        let stop = stop.map_or(len, |b| clamp(b, 0, len));
+        (start..stop.max(start))
+            .step_by(step as usize)
+            .map(|i| i as usize)
+            .collect()
+    } else {
+        let start = start.map_or(len - 1, |b| clamp(b, -1, len - 1));
+        let stop = stop.map_or(-1, |b| clamp(b, -1, len - 1));
+        let mut indices = Vec::new();
+        let mut i = start;
+        while i > stop {
+            indices.push(i as usize);
+            i += step;
+        }
+        indices
+    }
+}
+
+fn slice(
+    container: &Value,
+    start: Option<i64>,
+    stop: Option<i64>,
+    step: Option<i64>,
+    span: &Span,
+) -> Result<Value, BellronosError> {
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err(runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            "Slice step cannot be zero".to_string(),
+        ));
+    }
+    match container {
+        Value::List(items) => {
+            let items = items.borrow();
+            Ok(Value::list(
+                slice_indices(items.len(), start, stop, step)
+                    .into_iter()
+                    .map(|i| items[i].clone())
+                    .collect(),
+            ))
+        }
+        Value::Tuple(items) => Ok(Value::Tuple(
+            slice_indices(items.len(), start, stop, step)
+                .into_iter()
+                .map(|i| items[i].clone())
+                .collect(),
+        )),
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(Value::String(
+                slice_indices(chars.len(), start, stop, step)
+                    .into_iter()
+                    .map(|i| chars[i])
+                    .collect(),
+            ))
+        }
+        other => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{:?} cannot be sliced", other),
+        )),
+    }
+}
+
+/// The message of a built-in failure once it is raised as an exception.
+fn error_message(err: &BellronosError) -> String {
+    match err {
+        BellronosError::IO(io_err) => io_err.to_string(),
+        BellronosError::Network(message) => message.clone(),
+        _ => err
+            .diagnostic()
+            .map(|d| d.message.clone())
+            .unwrap_or_else(|| err.to_string()),
+    }
+}
+
+/// `Class: message`, as reported for an exception nobody caught.
+fn describe_exception(exception: &Value) -> String {
+    match exception {
+        Value::Instance(instance) => {
+            let class = &instance.class.name;
+            match instance.attributes.borrow().get("message") {
+                Some(Value::String(message)) if !message.is_empty() => {
+                    format!("{}: {}", class, message)
+                }
+                Some(Value::String(_)) | None => class.clone(),
+                Some(message) => format!("{}: {:?}", class, message),
+            }
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+/// The method `name` of an instance, bound to it.
+fn bound_method(receiver: &Value, name: &str) -> Option<Value> {
+    let Value::Instance(instance) = receiver else {
+        return None;
+    };
+    let (_, method) = instance.class.find_method(name)?;
+    Some(Value::BoundMethod(
+        Box::new(receiver.clone()),
+        Box::new(method.clone()),
+    ))
+}
+
+fn is_instance_of(value: &Value, class: &Class) -> bool {
+    matches!(value, Value::Instance(instance) if instance.class.is_subclass_of(class))
+}
+
+fn set_attribute(instance: &Value, name: &str, value: Value) {
+    if let Value::Instance(instance) = instance {
+        instance
+            .attributes
+            .borrow_mut()
+            .insert(name.to_string(), value);
+    }
+}
+
+/// Binds the arguments of a call to a function's parameters in its new
+/// scope: positional arguments in order, the rest into `*args`, keywords
+/// by name or into `**kwargs`, and defaults for whatever is left out.
+fn bind_arguments(
+    function: &str,
+    params: &[FunctionParam],
+    args: Vec<Value>,
+    keywords: Vec<(String, Value)>,
+    local_env: &Environment,
+    span: &Span,
+) -> Result<(), ControlFlow> {
+    let error = |code, message| Err(ControlFlow::from(runtime_error(span, code, message)));
+    let positional = params
+        .iter()
+        .take_while(|p| matches!(p.kind, ParamKind::Required | ParamKind::Optional))
+        .count();
+    let given = args.len();
+    let mut values: Vec<Option<Value>> = vec![None; params.len()];
+    let mut rest = Vec::new();
+    for (i, value) in args.into_iter().enumerate() {
+        if i < positional {
+            values[i] = Some(value);
+        } else {
+            rest.push(value);
+        }
+    }
+    let variadic = params.iter().position(|p| p.kind == ParamKind::Variadic);
+    let count_error = || {
+        let signature: Vec<ParamType> = params
+            .iter()
+            .map(|p| ParamType {
+                name: Some(p.name.clone()),
+                type_: Type::Any,
+                kind: p.kind,
+            })
+            .collect();
+        format!(
+            "Function {} expects {} arguments, but {} were given",
+            function,
+            arity_text(&signature),
+            given
+        )
+    };
+    match variadic {
+        Some(i) => values[i] = Some(Value::Tuple(rest.into())),
+        None if !rest.is_empty() => return error(ErrorCode::ArgumentCount, count_error()),
+        None => {}
+    }
+    let keyword_variadic = params
+        .iter()
+        .position(|p| p.kind == ParamKind::KeywordVariadic);
+    let mut extra = HashMap::new();
+    let has_keywords = !keywords.is_empty();
+    for (name, value) in keywords {
+        let named = params.iter().position(|p| {
+            p.name == name && matches!(p.kind, ParamKind::Required | ParamKind::Optional)
+        });
+        match named {
+            Some(i) if values[i].is_some() => {
+                return error(
+                    ErrorCode::UnexpectedKeyword,
+                    format!(
+                        "Function {} got multiple values for argument '{}'",
+                        function, name
+                    ),
+                )
+            }
+            Some(i) => values[i] = Some(value),
+            None if keyword_variadic.is_none() => {
+                return error(
+                    ErrorCode::UnexpectedKeyword,
+                    format!(
+                        "Function {} got an unexpected keyword argument '{}'",
+                        function, name
+                    ),
+                )
+            }
+            None if extra.contains_key(&name) => {
+                return error(
+                    ErrorCode::UnexpectedKeyword,
+                    format!(
+                        "Function {} got multiple values for argument '{}'",
+                        function, name
+                    ),
+                )
+            }
+            None => {
+                extra.insert(name, value);
+            }
+        }
+    }
+    if let Some(i) = keyword_variadic {
+        values[i] = Some(Value::Dict(Rc::new(RefCell::new(extra))));
+    }
+    for (i, (param, value)) in params.iter().zip(values).enumerate() {
+        let value = match (value, &param.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => default.clone(),
+            (None, None) if has_keywords || i >= positional => {
+                return error(
+                    ErrorCode::ArgumentCount,
+                    format!("Function {} is missing argument '{}'", function, param.name),
+                )
+            }
+            (None, None) => return error(ErrorCode::ArgumentCount, count_error()),
+        };
+        local_env.borrow_mut().define(param.name.clone(), value);
+    }
+    Ok(())
+}
+
+/// `Exception.__init__`, which stores the message every exception carries:
+/// `set self.message to message`.
+fn exception_init(env: &Environment) -> Value {
+    let span = Span::default();
+    let name = |id: &str| ASTNode::Name {
+        id: id.to_string(),
+        span: span.clone(),
+    };
+    let body = vec![ASTNode::Assign {
+        target: Box::new(ASTNode::Attribute {
+            value: Box::new(name("self")),
+            attr: "message".to_string(),
+            span: span.clone(),
+        }),
+        value: Box::new(name("message")),
+        span: span.clone(),
+    }];
+    let param = |name: &str| FunctionParam {
+        name: name.to_string(),
+        default: None,
+        kind: ParamKind::Required,
+    };
+    Value::Function(
+        "__init__".into(),
+        vec![param("self"), param("message")],
+        body.into(),
+        Rc::clone(env),
+    )
+}
+
+pub(crate) fn runtime_error(span: &Span, code: ErrorCode, message: String) -> BellronosError {
+    BellronosError::Runtime(Box::new(
+        Diagnostic::new(code, message).with_span(span.clone()),
+    ))
+}
+
+/// How `io.print` and `string.to_string` render a value: strings without
+/// quotes, everything else as its debug form.
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => f.write_str(s),
+            // An exception reads as its message
+            Value::Instance(instance) if instance.class.is_exception() => {
+                match instance.attributes.borrow().get("message") {
+                    Some(message) => write!(f, "{}", message),
+                    None => Ok(()),
+                }
+            }
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_debug(f, &mut Vec::new())
+    }
+}
+
+impl Value {
+    /// Writes the debug form. `printing` holds the lists, dicts and
+    /// exceptions being written further up, so one that contains itself is
+    /// shown as `[...]`, `{...}` or `...` where it recurs.
+    fn write_debug(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
+        let id = match self {
+            Value::List(l) => Rc::as_ptr(l) as *const (),
+            Value::Dict(d) => Rc::as_ptr(d) as *const (),
+            Value::Instance(instance) => Rc::as_ptr(instance) as *const (),
+            _ => std::ptr::null(),
+        };
+        if !id.is_null() && printing.contains(&id) {
+            return match self {
+                Value::List(_) => write!(f, "[...]"),
+                Value::Dict(_) => write!(f, "{{...}}"),
+                _ => write!(f, "..."),
+            };
+        }
+        printing.push(id);
+        let written = self.write_debug_contents(f, printing);
+        printing.pop();
+        written
+    }
+
+    fn write_debug_contents(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            // Keep whole floats visibly distinct from ints
+            Value::Float(fl) if fl.is_finite() && fl.fract() == 0.0 => write!(f, "{:.1}", fl),
+            Value::Float(fl) => write!(f, "{}", fl),
+            Value::String(s) => write!(f, "\"{}\"", s),
             Value::Bool(b) => write!(f, "{}", b),
             Value::List(l) => {
                 write!(f, "[")?;
-                for (i, item) in l.iter().enumerate() {
+                for (i, item) in l.borrow().iter().enumerate() {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "{:?}", item)?;
+                    item.write_debug(f, printing)?;
                 }
                 write!(f, "]")
             }
+            Value::Tuple(t) => {
+                write!(f, "(")?;
+                for (i, item) in t.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write_debug(f, printing)?;
+                }
+                // A one-element tuple keeps its comma, as in the literal
+                if t.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Set(s) if s.borrow().is_empty() => write!(f, "set()"),
+            Value::Set(s) => {
+                write!(f, "{{")?;
+                for (i, item) in sorted_elements(s.borrow().iter()).iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write_debug(f, printing)?;
+                }
+                write!(f, "}}")
+            }
             Value::Dict(d) => {
                 write!(f, "{{")?;
-                for (i, (k, v)) in d.iter().enumerate() {
+                for (i, (k, v)) in d.borrow().iter().enumerate() {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "\"{}\": {:?}", k, v)?;
+                    write!(f, "\"{}\": ", k)?;
+                    v.write_debug(f, printing)?;
                 }
                 write!(f, "}}")
             }
-            Value::Function(_, _, _) => write!(f, "<function>"),
-            Value::Class { .. } => write!(f, "<class>"),
-            Value::Instance { class, .. } => write!(f, "<instance of {}>", class),
-            Value::Closure(_, _, _) => write!(f, "<closure>"),
-            Value::Generator(_, _, _) => write!(f, "<generator>"),
+            Value::Function(..) => write!(f, "<function>"),
+            Value::AsyncFunction(..) => write!(f, "<async function>"),
+            Value::Class(class) => write!(f, "<class {}>", class.name),
+            Value::Instance(instance) if instance.class.is_exception() => {
+                match instance.attributes.borrow().get("message") {
+                    Some(message) => {
+                        write!(f, "{}(", instance.class.name)?;
+                        message.write_debug(f, printing)?;
+                        write!(f, ")")
+                    }
+                    None => write!(f, "{}()", instance.class.name),
+                }
+            }
+            Value::Instance(instance) => write!(f, "<instance of {}>", instance.class.name),
+            Value::NativeFunction(native) => write!(f, "<builtin function {}>", native.name),
+            Value::BoundMethod(receiver, _) => {
+                write!(f, "<bound method of ")?;
+                receiver.write_debug(f, printing)?;
+                write!(f, ">")
+            }
+            Value::Super(receiver, class) => {
+                write!(f, "<super of {} for ", class.name)?;
+                receiver.write_debug(f, printing)?;
+                write!(f, ">")
+            }
+            Value::Generator(_) => write!(f, "<generator>"),
+            Value::Range(start, stop, 1) => write!(f, "range({}, {})", start, stop),
+            Value::Range(start, stop, step) => write!(f, "range({}, {}, {})", start, stop, step),
+            Value::File(file) => write!(f, "<file \"{}\">", file.path),
+            Value::Task(_) => write!(f, "<task>"),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
             Value::None => write!(f, "None"),
         }
     }
@@ -13,7 +13,40 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::error::BellronosError;
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode, MAX_ERRORS};
+use std::collections::VecDeque;
+use std::fmt;
+use std::rc::Rc;
+
+/// Location of a token or AST node: file, 1-based line and column, and the
+/// byte range it covers in the source text.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Span starting where `self` starts and ending where `end` ends.
+    pub fn to(&self, end: &Span) -> Span {
+        Span {
+            file: Rc::clone(&self.file),
+            line: self.line,
+            column: self.column,
+            start: self.start,
+            end: end.end.max(self.start),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -23,20 +56,34 @@ pub enum Token {
     Set,
     To,
     If,
+    Elif,
     Else,
     While,
     For,
     In,
     Return,
+    Try,
+    Except,
+    Finally,
+    Raise,
+    As,
+    Break,
+    Continue,
+    Nonlocal,
     Async,
     Await,
     Yield,
     Closure,
+    And,
+    Or,
+    Not,
+    Is,
     True,
     False,
     Identifier(String),
     String(String),
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     LeftParen,
     RightParen,
     LeftBrace,
@@ -50,6 +97,9 @@ pub enum Token {
     Minus,
     Multiply,
     Divide,
+    FloorDivide,
+    Modulo,
+    Power,
     Equals,
     NotEquals,
     LessThan,
@@ -57,202 +107,559 @@ pub enum Token {
     LessThanOrEqual,
     GreaterThanOrEqual,
     Arrow,
+    At,
     Newline,
+    Indent,
+    Dedent,
     EOF,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 pub struct Lexer {
     input: Vec<char>,
+    file: Rc<str>,
     position: usize,
+    byte_position: usize,
     line: usize,
     column: usize,
+    indent_stack: Vec<usize>,
+    pending: VecDeque<SpannedToken>,
+    at_line_start: bool,
+    nesting: usize,
 }
 
 impl Lexer {
-    pub fn new(input: &str) -> Self {
+    pub fn new(input: &str, file: &str) -> Self {
         Lexer {
             input: input.chars().collect(),
+            file: Rc::from(file),
             position: 0,
+            byte_position: 0,
             line: 1,
             column: 1,
+            indent_stack: vec![0],
+            pending: VecDeque::new(),
+            at_line_start: true,
+            nesting: 0,
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, BellronosError> {
-        let mut tokens = Vec::new();
-        while let Some(token) = self.next_token()? {
-            tokens.push(token);
+    /// Lexes the whole input. Lexical errors do not stop it: the offending
+    /// characters are skipped, and a malformed literal still yields a
+    /// token, so the parser goes on to report the syntax errors after them.
+    /// Lexing stops once `MAX_ERRORS` have been met.
+    pub fn tokenize(&mut self) -> (Vec<SpannedToken>, Vec<BellronosError>) {
+        let mut tokens: Vec<SpannedToken> = Vec::new();
+        let mut errors = Vec::new();
+        while errors.len() < MAX_ERRORS {
+            match self.next_token() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => break,
+                Err(err) => errors.push(err),
+            }
+        }
+        // Terminate the last logical line and close every open block
+        let end = self.span_from(self.mark());
+        if !matches!(
+            tokens.last().map(|t| &t.token),
+            None | Some(Token::Newline) | Some(Token::Dedent)
+        ) {
+            tokens.push(SpannedToken {
+                token: Token::Newline,
+                span: end.clone(),
+            });
+        }
+        while self.indent_stack.len() > 1 {
+            self.indent_stack.pop();
+            tokens.push(SpannedToken {
+                token: Token::Dedent,
+                span: end.clone(),
+            });
         }
-        tokens.push(Token::EOF);
-        Ok(tokens)
+        tokens.push(SpannedToken {
+            token: Token::EOF,
+            span: end,
+        });
+        (tokens, errors)
     }
 
-    fn next_token(&mut self) -> Result<Option<Token>, BellronosError> {
+    fn next_token(&mut self) -> Result<Option<SpannedToken>, BellronosError> {
+        if let Some(token) = self.pending.pop_front() {
+            return Ok(Some(token));
+        }
+
+        if self.at_line_start && self.nesting == 0 {
+            self.at_line_start = false;
+            if let Some(token) = self.tokenize_indentation()? {
+                return Ok(Some(token));
+            }
+        }
+
         self.skip_whitespace();
 
         if self.position >= self.input.len() {
             return Ok(None);
         }
 
+        let start = self.mark();
         let token = match self.current_char() {
             '(' => {
                 self.advance();
-                Ok(Some(Token::LeftParen))
+                self.nesting += 1;
+                Token::LeftParen
             }
             ')' => {
                 self.advance();
-                Ok(Some(Token::RightParen))
+                self.nesting = self.nesting.saturating_sub(1);
+                Token::RightParen
             }
             '{' => {
                 self.advance();
-                Ok(Some(Token::LeftBrace))
+                self.nesting += 1;
+                Token::LeftBrace
             }
             '}' => {
                 self.advance();
-                Ok(Some(Token::RightBrace))
+                self.nesting = self.nesting.saturating_sub(1);
+                Token::RightBrace
             }
             '[' => {
                 self.advance();
-                Ok(Some(Token::LeftBracket))
+                self.nesting += 1;
+                Token::LeftBracket
             }
             ']' => {
                 self.advance();
-                Ok(Some(Token::RightBracket))
+                self.nesting = self.nesting.saturating_sub(1);
+                Token::RightBracket
             }
             ':' => {
                 self.advance();
-                Ok(Some(Token::Colon))
+                Token::Colon
             }
             ',' => {
                 self.advance();
-                Ok(Some(Token::Comma))
+                Token::Comma
             }
             '.' => {
                 self.advance();
-                Ok(Some(Token::Dot))
+                Token::Dot
             }
             '+' => {
                 self.advance();
-                Ok(Some(Token::Plus))
+                Token::Plus
             }
             '-' => {
                 self.advance();
                 if self.current_char() == '>' {
                     self.advance();
-                    Ok(Some(Token::Arrow))
+                    Token::Arrow
                 } else {
-                    Ok(Some(Token::Minus))
+                    Token::Minus
                 }
             }
+            '*' if self.is_dunder_name() => self.tokenize_dunder_name(),
             '*' => {
                 self.advance();
-                Ok(Some(Token::Multiply))
+                if self.current_char() == '*' {
+                    self.advance();
+                    Token::Power
+                } else {
+                    Token::Multiply
+                }
             }
             '/' => {
                 self.advance();
-                Ok(Some(Token::Divide))
+                if self.current_char() == '/' {
+                    self.advance();
+                    Token::FloorDivide
+                } else {
+                    Token::Divide
+                }
+            }
+            '%' => {
+                self.advance();
+                Token::Modulo
+            }
+            '@' => {
+                self.advance();
+                Token::At
             }
             '=' => {
                 self.advance();
                 if self.current_char() == '=' {
                     self.advance();
-                    Ok(Some(Token::Equals))
+                    Token::Equals
                 } else {
-                    Ok(Some(Token::Set))
+                    Token::Set
                 }
             }
             '!' => {
                 self.advance();
                 if self.current_char() == '=' {
                     self.advance();
-                    Ok(Some(Token::NotEquals))
+                    Token::NotEquals
                 } else {
-                    Err(BellronosError::Parser(format!(
-                        "Unexpected character: {}",
-                        self.current_char()
-                    )))
+                    return Err(BellronosError::Parser(Box::new(
+                        Diagnostic::new(ErrorCode::UnexpectedCharacter, "Unexpected character: !")
+                            .with_span(self.span_from(start))
+                            .with_help("use `!=` for inequality"),
+                    )));
                 }
             }
             '<' => {
                 self.advance();
                 if self.current_char() == '=' {
                     self.advance();
-                    Ok(Some(Token::LessThanOrEqual))
+                    Token::LessThanOrEqual
                 } else {
-                    Ok(Some(Token::LessThan))
+                    Token::LessThan
                 }
             }
             '>' => {
                 self.advance();
                 if self.current_char() == '=' {
                     self.advance();
-                    Ok(Some(Token::GreaterThanOrEqual))
+                    Token::GreaterThanOrEqual
                 } else {
-                    Ok(Some(Token::GreaterThan))
+                    Token::GreaterThan
                 }
             }
             '\n' => {
                 self.advance_line();
-                Ok(Some(Token::Newline))
+                if self.nesting > 0 {
+                    // Newlines inside brackets only continue the logical line
+                    return self.next_token();
+                }
+                self.at_line_start = true;
+                Token::Newline
             }
-            '"' => self.tokenize_string(),
-            '0'..='9' => self.tokenize_number(),
+            // A malformed literal is reported and a placeholder takes its
+            // place, so that its statement still parses
+            '"' => match self.tokenize_string(&start) {
+                Ok(token) => token,
+                Err(err) => {
+                    self.pending.push_back(SpannedToken {
+                        token: Token::String(String::new()),
+                        span: self.span_from(start),
+                    });
+                    return Err(err);
+                }
+            },
+            '0'..='9' => match self.tokenize_number(&start) {
+                Ok(token) => token,
+                Err(err) => {
+                    self.pending.push_back(SpannedToken {
+                        token: Token::Integer(0),
+                        span: self.span_from(start),
+                    });
+                    return Err(err);
+                }
+            },
             'a'..='z' | 'A'..='Z' | '_' => self.tokenize_identifier(),
             '#' => {
-                while self.current_char() != '\n' && self.position < self.input.len() {
+                while self.position < self.input.len() && self.current_char() != '\n' {
                     self.advance();
                 }
-                self.next_token()
+                return self.next_token();
+            }
+            c => {
+                self.advance();
+                return Err(BellronosError::Parser(Box::new(
+                    Diagnostic::new(
+                        ErrorCode::UnexpectedCharacter,
+                        format!("Unexpected character: {}", c),
+                    )
+                    .with_span(self.span_from(start)),
+                )));
             }
-            _ => Err(BellronosError::Parser(format!(
-                "Unexpected character: {}",
-                self.current_char()
-            ))),
-        }?;
+        };
 
-        Ok(token)
+        Ok(Some(SpannedToken {
+            token,
+            span: self.span_from(start),
+        }))
     }
 
-    fn tokenize_string(&mut self) -> Result<Option<Token>, BellronosError> {
+    fn tokenize_indentation(&mut self) -> Result<Option<SpannedToken>, BellronosError> {
+        loop {
+            let line_start = self.mark();
+            let mut width = 0;
+            let mut seen_space = false;
+            let mut seen_tab = false;
+            while self.position < self.input.len()
+                && (self.current_char() == ' ' || self.current_char() == '\t')
+            {
+                if self.current_char() == '\t' {
+                    seen_tab = true;
+                } else {
+                    seen_space = true;
+                }
+                width += 1;
+                self.advance();
+            }
+
+            if self.position >= self.input.len() {
+                return Ok(None);
+            }
+
+            // Blank and comment-only lines never open or close a block
+            match self.current_char() {
+                '\r' if matches!(self.peek_char(), '\n' | '\0') => {
+                    self.advance();
+                    continue;
+                }
+                '\n' => {
+                    self.advance_line();
+                    continue;
+                }
+                '#' => {
+                    while self.position < self.input.len() && self.current_char() != '\n' {
+                        self.advance();
+                    }
+                    if self.position < self.input.len() {
+                        self.advance_line();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let span = self.span_from(line_start);
+            if seen_space && seen_tab {
+                return Err(BellronosError::Parser(Box::new(
+                    Diagnostic::new(
+                        ErrorCode::InconsistentIndentation,
+                        "Inconsistent use of tabs and spaces in indentation",
+                    )
+                    .with_span(span)
+                    .with_help("indent with either tabs or spaces, not both"),
+                )));
+            }
+
+            let current = *self.indent_stack.last().unwrap_or(&0);
+            if width > current {
+                self.indent_stack.push(width);
+                return Ok(Some(SpannedToken {
+                    token: Token::Indent,
+                    span,
+                }));
+            }
+            let enclosing = self.indent_stack.clone();
+            while width < *self.indent_stack.last().unwrap_or(&0) {
+                self.indent_stack.pop();
+                self.pending.push_back(SpannedToken {
+                    token: Token::Dedent,
+                    span: span.clone(),
+                });
+            }
+            if width != *self.indent_stack.last().unwrap_or(&0) {
+                return Err(BellronosError::Parser(Box::new(
+                    Diagnostic::new(
+                        ErrorCode::InconsistentIndentation,
+                        "Unindent does not match any outer indentation level",
+                    )
+                    .with_span(span)
+                    .with_note(format!(
+                        "this line is indented {} columns, but the enclosing blocks are indented {:?}",
+                        width, enclosing
+                    ))
+                    .with_help("indent to the same column as an enclosing block"),
+                )));
+            }
+            return Ok(self.pending.pop_front());
+        }
+    }
+
+    fn tokenize_string(&mut self, start: &(usize, usize, usize)) -> Result<Token, BellronosError> {
         self.advance(); // Skip opening quote
-        let start = self.position;
-        while self.current_char() != '"' && self.position < self.input.len() {
+        let content_start = self.position;
+        while self.position < self.input.len() && self.current_char() != '"' {
             if self.current_char() == '\n' {
-                return Err(BellronosError::Parser(format!(
-                    "Unexpected character: {}",
-                    self.current_char()
-                )));
+                break;
             }
             self.advance();
         }
-        if self.position >= self.input.len() {
-            return Err(BellronosError::Parser(format!(
-                "Unexpected character: {}",
-                self.current_char()
+        if self.position >= self.input.len() || self.current_char() != '"' {
+            return Err(BellronosError::Parser(Box::new(
+                Diagnostic::new(ErrorCode::UnterminatedString, "Unterminated string literal")
+                    .with_span(self.span_from(*start))
+                    .with_help("add a closing `\"` before the end of the line"),
             )));
         }
-        let value: String = self.input[start..self.position].iter().collect();
+        let value: String = self.input[content_start..self.position].iter().collect();
         self.advance(); // Skip closing quote
-        Ok(Some(Token::String(value)))
+        Ok(Token::String(value))
     }
 
-    fn tokenize_number(&mut self) -> Result<Option<Token>, BellronosError> {
-        let start = self.position;
+    /// Lexes an integer or float literal. Integers may use `0x`, `0o` or
+    /// `0b` prefixes, and any digit run may be split with single `_`s.
+    fn tokenize_number(&mut self, start: &(usize, usize, usize)) -> Result<Token, BellronosError> {
+        let radix = match (self.current_char(), self.peek_char()) {
+            ('0', 'x' | 'X') => Some(16),
+            ('0', 'o' | 'O') => Some(8),
+            ('0', 'b' | 'B') => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            self.advance();
+            self.advance();
+            let digits = self.take_digits(|c| c.is_ascii_alphanumeric());
+            self.expect_number_end(start)?;
+            // A separator may follow the prefix, as in `0x_ff`
+            let digits = digits.strip_prefix('_').unwrap_or(&digits);
+            if digits.is_empty() {
+                return Err(self.number_error(start, "expected digits after the base prefix"));
+            }
+            return match Self::strip_separators(digits) {
+                Some(clean) => i64::from_str_radix(&clean, radix)
+                    .map(Token::Integer)
+                    .map_err(|err| self.number_error(start, &err.to_string())),
+                None => Err(self.number_error(start, "'_' must sit between two digits")),
+            };
+        }
+
+        let mut text = self.take_digits(|c| c.is_ascii_digit());
+        let mut is_float = false;
+        if self.current_char() == '.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.advance();
+            text.push('.');
+            text.push_str(&self.take_digits(|c| c.is_ascii_digit()));
+        }
+        if matches!(self.current_char(), 'e' | 'E')
+            && (self.peek_char().is_ascii_digit()
+                || (matches!(self.peek_char(), '+' | '-')
+                    && self
+                        .input
+                        .get(self.position + 2)
+                        .is_some_and(|c| c.is_ascii_digit())))
+        {
+            is_float = true;
+            text.push('e');
+            self.advance();
+            if matches!(self.current_char(), '+' | '-') {
+                text.push(self.current_char());
+                self.advance();
+            }
+            text.push_str(&self.take_digits(|c| c.is_ascii_digit()));
+        }
+        self.expect_number_end(start)?;
+
+        let clean = text
+            .split(['.', 'e'])
+            .map(|part| part.trim_start_matches(['+', '-']))
+            .all(|part| Self::strip_separators(part).is_some())
+            .then(|| text.replace('_', ""))
+            .ok_or_else(|| self.number_error(start, "'_' must sit between two digits"))?;
+        if is_float {
+            clean
+                .parse::<f64>()
+                .map(Token::Float)
+                .map_err(|err| self.number_error(start, &err.to_string()))
+        } else {
+            clean
+                .parse::<i64>()
+                .map(Token::Integer)
+                .map_err(|err| self.number_error(start, &err.to_string()))
+        }
+    }
+
+    fn take_digits(&mut self, is_digit: impl Fn(char) -> bool) -> String {
+        let mut digits = String::new();
         while self.position < self.input.len()
-            && (self.current_char().is_digit(10) || self.current_char() == '.')
+            && (is_digit(self.current_char()) || self.current_char() == '_')
         {
+            digits.push(self.current_char());
             self.advance();
         }
-        let value: String = self.input[start..self.position].iter().collect();
-        value
-            .parse::<f64>()
-            .map(|n| Some(Token::Number(n)))
-            .map_err(|_| {
-                BellronosError::Parser(format!("Unexpected character: {}", self.current_char()))
-            })
+        digits
+    }
+
+    /// Removes `_` separators, or returns `None` when one is leading,
+    /// trailing or doubled.
+    fn strip_separators(digits: &str) -> Option<String> {
+        if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+            None
+        } else {
+            Some(digits.replace('_', ""))
+        }
     }
 
-    fn tokenize_identifier(&mut self) -> Result<Option<Token>, BellronosError> {
+    /// Rejects literals that run straight into an identifier, such as `12ab`.
+    fn expect_number_end(&mut self, start: &(usize, usize, usize)) -> Result<(), BellronosError> {
+        if self.current_char().is_alphanumeric() || self.current_char() == '_' {
+            while self.current_char().is_alphanumeric() || self.current_char() == '_' {
+                self.advance();
+            }
+            return Err(self.number_error(start, "unexpected characters after number"));
+        }
+        Ok(())
+    }
+
+    fn number_error(&self, start: &(usize, usize, usize), reason: &str) -> BellronosError {
+        // Number literals never span lines, so the column tells how far back
+        // the literal started.
+        let length = self.column - start.1;
+        let literal: String = self.input[self.position - length..self.position]
+            .iter()
+            .collect();
+        BellronosError::Parser(Box::new(
+            Diagnostic::new(
+                ErrorCode::InvalidNumber,
+                format!("Invalid number literal: {}", literal),
+            )
+            .with_span(self.span_from(*start))
+            .with_note(reason.to_string()),
+        ))
+    }
+
+    /// Whether a call of `**init**` or `**init__` starts here, which
+    /// `2 ** x ** 3` never is.
+    fn is_dunder_name(&self) -> bool {
+        let at = |i: usize| self.input.get(i).copied().unwrap_or('\0');
+        let starts_word = self.position == 0 || {
+            let before = at(self.position - 1);
+            before.is_whitespace() || before == '.'
+        };
+        if !starts_word || at(self.position + 1) != '*' {
+            return false;
+        }
+        let mut end = self.position + 2;
+        if !(at(end).is_alphabetic() || at(end) == '_') {
+            return false;
+        }
+        while at(end).is_alphanumeric() || at(end) == '_' {
+            end += 1;
+        }
+        let name_closed = at(end - 1) == '_' && at(end - 2) == '_';
+        (at(end) == '*' && at(end + 1) == '*' && at(end + 2) == '(')
+            || (name_closed && end - self.position > 4 && at(end) == '(')
+    }
+
+    /// Lexes `**name**`, or `**name__`, as the identifier `__name__`.
+    fn tokenize_dunder_name(&mut self) -> Token {
+        self.advance();
+        self.advance();
+        let mut name = String::from("__");
+        while self.current_char().is_alphanumeric() || self.current_char() == '_' {
+            name.push(self.current_char());
+            self.advance();
+        }
+        if self.current_char() == '*' {
+            self.advance();
+            self.advance();
+            name.push_str("__");
+        }
+        Token::Identifier(name)
+    }
+
+    fn tokenize_identifier(&mut self) -> Token {
         let start = self.position;
         while self.position < self.input.len()
             && (self.current_char().is_alphanumeric() || self.current_char() == '_')
@@ -260,43 +667,77 @@ impl Lexer {
             self.advance();
         }
         let value: String = self.input[start..self.position].iter().collect();
-        Ok(Some(match value.as_str() {
+        match value.as_str() {
             "import" => Token::Import,
             "define" => Token::Define,
             "class" => Token::Class,
             "set" => Token::Set,
             "to" => Token::To,
             "if" => Token::If,
+            "elif" => Token::Elif,
             "else" => Token::Else,
             "while" => Token::While,
             "for" => Token::For,
             "in" => Token::In,
             "return" => Token::Return,
+            "try" => Token::Try,
+            "except" => Token::Except,
+            "finally" => Token::Finally,
+            "raise" => Token::Raise,
+            "as" => Token::As,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "nonlocal" => Token::Nonlocal,
             "async" => Token::Async,
             "await" => Token::Await,
             "yield" => Token::Yield,
             "closure" => Token::Closure,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            "is" => Token::Is,
             "true" => Token::True,
             "false" => Token::False,
             _ => Token::Identifier(value),
-        }))
+        }
     }
 
     fn current_char(&self) -> char {
-        self.input[self.position]
+        self.input.get(self.position).copied().unwrap_or('\0')
+    }
+
+    fn peek_char(&self) -> char {
+        self.input.get(self.position + 1).copied().unwrap_or('\0')
     }
 
     fn advance(&mut self) {
+        self.byte_position += self.current_char().len_utf8();
         self.position += 1;
         self.column += 1;
     }
 
     fn advance_line(&mut self) {
+        self.byte_position += 1;
         self.position += 1;
         self.line += 1;
         self.column = 1;
     }
 
+    /// Line, column and byte offset of the current position.
+    fn mark(&self) -> (usize, usize, usize) {
+        (self.line, self.column, self.byte_position)
+    }
+
+    fn span_from(&self, (line, column, start): (usize, usize, usize)) -> Span {
+        Span {
+            file: Rc::clone(&self.file),
+            line,
+            column,
+            start,
+            end: self.byte_position,
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while self.position < self.input.len()
             && self.current_char().is_whitespace()
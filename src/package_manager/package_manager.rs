@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::error::error::BellronosError;
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode};
 use reqwest;
 use serde_json;
 use std::fs::{self, File};
@@ -75,11 +75,15 @@ impl PackageManager {
         Ok(())
     }
 
-    pub fn load_package(&self, package_name: &str) -> Result<String, BellronosError> {
-        let package_path = self
-            .package_dir
+    /// Where an installed package's source is kept.
+    pub fn package_path(&self, package_name: &str) -> PathBuf {
+        self.package_dir
             .join(package_name)
-            .with_extension("bellronos");
+            .with_extension("bellronos")
+    }
+
+    pub fn load_package(&self, package_name: &str) -> Result<String, BellronosError> {
+        let package_path = self.package_path(package_name);
         fs::read_to_string(&package_path).map_err(|e| {
             BellronosError::IO(IoError::new(
                 e.kind(),
@@ -147,7 +151,10 @@ impl PackageManager {
         })?;
 
         let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
-            BellronosError::Parser(format!("Failed to parse package metadata: {}", e))
+            BellronosError::Parser(Box::new(Diagnostic::new(
+                ErrorCode::InvalidPackage,
+                format!("Failed to parse package metadata: {}", e),
+            )))
         })?;
 
         Ok(PackageMetadata {
@@ -192,10 +199,10 @@ impl PackageManager {
     pub fn update_package(&self, package_name: &str) -> Result<(), BellronosError> {
         let installed_packages = self.list_installed_packages()?;
         if !installed_packages.contains(&package_name.to_string()) {
-            return Err(BellronosError::Package(format!(
-                "Package {} is not installed",
-                package_name
-            )));
+            return Err(BellronosError::Runtime(Box::new(Diagnostic::new(
+                ErrorCode::InvalidPackage,
+                format!("Package {} is not installed", package_name),
+            ))));
         }
 
         let metadata = self.fetch_package_metadata(package_name)?;
@@ -210,10 +217,7 @@ impl PackageManager {
     }
 
     fn get_installed_package_version(&self, package_name: &str) -> Result<String, BellronosError> {
-        let package_path = self
-            .package_dir
-            .join(package_name)
-            .with_extension("bellronos");
+        let package_path = self.package_path(package_name);
         let content = fs::read_to_string(&package_path).map_err(|e| {
             BellronosError::IO(IoError::new(
                 e.kind(),
@@ -230,7 +234,12 @@ impl PackageManager {
             .find(|line| line.starts_with("# Version:"))
             .and_then(|line| line.split(':').nth(1))
             .map(|version| version.trim().to_string())
-            .ok_or_else(|| BellronosError::Parser("Failed to extract package version".to_string()))
+            .ok_or_else(|| {
+                BellronosError::Parser(Box::new(Diagnostic::new(
+                    ErrorCode::InvalidPackage,
+                    "Failed to extract package version",
+                )))
+            })
     }
 
     pub fn search_packages(&self, query: &str) -> Result<Vec<String>, BellronosError> {
@@ -254,8 +263,12 @@ impl PackageManager {
             })
         })?;
 
-        serde_json::from_str(&text)
-            .map_err(|e| BellronosError::Parser(format!("Failed to parse search results: {}", e)))
+        serde_json::from_str(&text).map_err(|e| {
+            BellronosError::Parser(Box::new(Diagnostic::new(
+                ErrorCode::InvalidPackage,
+                format!("Failed to parse search results: {}", e),
+            )))
+        })
     }
 
     pub fn get_package_info(&self, package_name: &str) -> Result<String, BellronosError> {
@@ -13,54 +13,158 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::ast::ast::ASTNode;
-use crate::error::error::BellronosError;
-use crate::lexer::lexer::Token;
-use crate::type_system::type_system::Type;
+use crate::ast::ast::{ASTNode, Argument, BinaryOperator, ExceptHandler, Parameter, UnaryOperator};
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode, MAX_ERRORS};
+use crate::lexer::lexer::{Span, SpannedToken, Token};
+use crate::type_system::type_system::{ParamKind, Type};
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     position: usize,
+    errors: Vec<BellronosError>,
+    aborted: bool,
+    /// Enclosing function bodies, used to reject a stray `return`.
+    function_depth: usize,
+    /// Enclosing loop bodies within the current function.
+    loop_depth: usize,
+    /// Whether the innermost enclosing function is `async`, where `await`
+    /// is allowed.
+    in_async: bool,
+    /// Temporaries made so far for the `await`s split out of the current
+    /// statement.
+    temporaries: usize,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser {
+    /// A parser for `tokens`, reporting the `lexical_errors` met while
+    /// lexing them along with its own.
+    pub fn new(tokens: Vec<SpannedToken>, mut lexical_errors: Vec<BellronosError>) -> Self {
+        lexical_errors.truncate(MAX_ERRORS);
+        let mut parser = Parser {
             tokens,
             position: 0,
+            errors: lexical_errors,
+            aborted: false,
+            function_depth: 0,
+            loop_depth: 0,
+            in_async: false,
+            temporaries: 0,
+        };
+        if parser.errors.len() >= MAX_ERRORS {
+            parser.abort();
         }
+        parser
     }
 
+    /// Parses the whole module, failing with every syntax error found, in
+    /// the order they appear in the source.
     pub fn parse(&mut self) -> Result<ASTNode, BellronosError> {
+        let module = self.parse_module();
+        self.errors.sort_by_key(|err| {
+            let diagnostic = err.diagnostic();
+            (
+                diagnostic.is_some_and(|d| d.code == ErrorCode::TooManyErrors),
+                diagnostic.and_then(|d| d.span.as_ref()).map(|s| s.start),
+            )
+        });
+        match self.errors.len() {
+            0 => Ok(module),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(BellronosError::Multiple(std::mem::take(&mut self.errors))),
+        }
+    }
+
+    /// Parses the whole module, recovering from syntax errors at statement
+    /// boundaries. The returned module omits the statements that failed to
+    /// parse; the errors themselves are collected for `parse`.
+    fn parse_module(&mut self) -> ASTNode {
+        let start = self.current_span();
         let mut body = Vec::new();
-        while self.position < self.tokens.len() {
-            body.push(self.parse_statement()?);
+        while !self.aborted && self.current_token() != Token::EOF {
+            let statement_start = self.position;
+            match self.parse_statement() {
+                Ok(statement) => body.push(statement),
+                Err(err) => {
+                    if self.recover(err, statement_start).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        ASTNode::Module {
+            body,
+            span: start.to(&self.current_span()),
         }
-        Ok(ASTNode::Module { body })
     }
 
     fn parse_statement(&mut self) -> Result<ASTNode, BellronosError> {
         match self.current_token() {
+            Token::Indent => Err(BellronosError::Parser(Box::new(
+                Diagnostic::new(ErrorCode::UnexpectedIndent, "Unexpected indent")
+                    .with_span(self.current_span())
+                    .with_help("only the body of a block may be indented further"),
+            ))),
+            Token::Dedent => Err(self.error(
+                ErrorCode::InconsistentIndentation,
+                "Unindent does not match any outer indentation level",
+            )),
             Token::Import => self.parse_import(),
-            Token::Define => self.parse_function_def(),
+            // `define(...)` without a name is an anonymous function
+            Token::Define if self.peek_token() != Token::LeftParen => {
+                self.parse_function_def(false)
+            }
             Token::Class => self.parse_class_def(),
+            Token::At => self.parse_decorated(),
             Token::Set => self.parse_assignment(),
             Token::If => self.parse_if(),
             Token::While => self.parse_while(),
             Token::For => self.parse_for(),
             Token::Return => self.parse_return(),
-            Token::Async => self.parse_async(),
+            Token::Try => self.parse_try(),
+            Token::Raise => self.parse_raise(),
+            Token::Break | Token::Continue => self.parse_loop_control(),
+            Token::Nonlocal => self.parse_nonlocal(),
+            // `async.sleep(...)` and friends use the `async` module
+            Token::Async if self.peek_token() != Token::Dot => self.parse_async(),
             Token::Yield => self.parse_yield(),
-            Token::Closure => self.parse_closure(),
-            _ => self.parse_expression(),
+            _ => self.parse_expression_statement(),
         }
     }
 
+    /// Parses an expression statement, or a `target = value` assignment
+    /// such as `self.name = name`.
+    fn parse_expression_statement(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        let value = Box::new(self.parse_expression()?);
+        if self.current_token() == Token::Set {
+            let target = Box::new(self.check_assignment_target(*value)?);
+            self.advance(); // Consume '='
+            let value = Box::new(self.parse_assigned_value(Self::parse_expression)?);
+            let span = self.span_from(&start);
+            self.expect_token(Token::Newline)?;
+            return Ok(ASTNode::Assign {
+                target,
+                value,
+                span,
+            });
+        }
+        let span = self.span_from(&start);
+        self.expect_token(Token::Newline)?;
+        Ok(ASTNode::Expr { value, span })
+    }
+
     fn parse_import(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'import'
         let mut names = Vec::new();
-        while let Token::Identifier(name) = self.current_token() {
-            names.push(name.clone());
+        loop {
+            let name = match self.current_token() {
+                Token::Identifier(name) => name,
+                // The `async` module shares its name with the keyword
+                Token::Async => "async".to_string(),
+                _ => break,
+            };
+            names.push(name);
             self.advance();
             if self.current_token() == Token::Comma {
                 self.advance();
@@ -68,52 +172,181 @@ impl Parser {
                 break;
             }
         }
+        let span = self.span_from(&start);
         self.expect_token(Token::Newline)?;
-        Ok(ASTNode::Import { names })
+        Ok(ASTNode::Import { names, span })
     }
 
-    fn parse_function_def(&mut self) -> Result<ASTNode, BellronosError> {
+    fn parse_function_def(&mut self, is_async: bool) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'define'
         let name = self.expect_identifier()?;
         let args = self.parse_function_args()?;
         let return_type = self.parse_return_type()?;
+        let span = self.span_from(&start);
         self.expect_token(Token::Colon)?;
         self.expect_token(Token::Newline)?;
-        let body = self.parse_block()?;
+        let body = self.parse_function_body(is_async)?;
         Ok(ASTNode::FunctionDef {
             name,
             args,
             return_type,
             body,
+            decorators: Vec::new(),
+            span,
         })
     }
 
     fn parse_class_def(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'class'
         let name = self.expect_identifier()?;
+        let mut bases = Vec::new();
+        if self.current_token() == Token::LeftParen {
+            self.advance();
+            while self.current_token() != Token::RightParen {
+                bases.push(self.expect_identifier()?);
+                if self.current_token() == Token::Comma {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.expect_token(Token::RightParen)?;
+        }
+        let span = self.span_from(&start);
         self.expect_token(Token::Colon)?;
         self.expect_token(Token::Newline)?;
         let methods = self.parse_block()?;
-        Ok(ASTNode::ClassDef { name, methods })
+        Ok(ASTNode::ClassDef {
+            name,
+            bases,
+            methods,
+            decorators: Vec::new(),
+            span,
+        })
+    }
+
+    /// Parses `@decorator` lines and the function or class definition they
+    /// apply to. A decorator is any expression, such as `@retry(3)`.
+    fn parse_decorated(&mut self) -> Result<ASTNode, BellronosError> {
+        let mut decorators = Vec::new();
+        while self.current_token() == Token::At {
+            self.advance(); // Consume '@'
+            decorators.push(self.parse_expression()?);
+            self.expect_token(Token::Newline)?;
+        }
+        let mut definition = match self.current_token() {
+            Token::Define if self.peek_token() != Token::LeftParen => {
+                self.parse_function_def(false)?
+            }
+            Token::Async => self.parse_async()?,
+            Token::Class => self.parse_class_def()?,
+            _ => {
+                return Err(self.error(
+                    ErrorCode::UnexpectedToken,
+                    "Expected function or class definition after decorator",
+                ))
+            }
+        };
+        let target = match &mut definition {
+            ASTNode::Async { body, .. } => &mut body[0],
+            other => other,
+        };
+        if let ASTNode::FunctionDef { decorators: d, .. }
+        | ASTNode::ClassDef { decorators: d, .. } = target
+        {
+            *d = decorators;
+        }
+        Ok(definition)
     }
 
     fn parse_assignment(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'set'
-        let target = self.expect_identifier()?;
+        let target = Box::new(self.parse_assignment_target()?);
         self.expect_token(Token::To)?;
-        let value = Box::new(self.parse_expression()?);
+        let value = Box::new(self.parse_assigned_value(Self::parse_expression_list)?);
+        let span = self.span_from(&start);
         self.expect_token(Token::Newline)?;
-        Ok(ASTNode::Assign { target, value })
+        Ok(ASTNode::Assign {
+            target,
+            value,
+            span,
+        })
+    }
+
+    /// Parses the right-hand side of an assignment, which may be a `yield`
+    /// receiving the value sent into a generator.
+    fn parse_assigned_value(
+        &mut self,
+        parse_value: fn(&mut Self) -> Result<ASTNode, BellronosError>,
+    ) -> Result<ASTNode, BellronosError> {
+        if self.current_token() == Token::Yield {
+            self.parse_yield_expression()
+        } else {
+            parse_value(self)
+        }
+    }
+
+    /// Parses the target of `set ... to` or `for ... in`: a name, an
+    /// attribute, a subscript, or several of them separated by commas to
+    /// destructure a tuple.
+    fn parse_assignment_target(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        let target = self.parse_postfix()?;
+        if self.current_token() != Token::Comma {
+            return self.check_assignment_target(target);
+        }
+        let mut elements = vec![target];
+        while self.current_token() == Token::Comma {
+            self.advance();
+            elements.push(self.parse_postfix()?);
+        }
+        let span = self.span_from(&start);
+        self.check_assignment_target(ASTNode::Tuple { elements, span })
+    }
+
+    fn check_assignment_target(&self, target: ASTNode) -> Result<ASTNode, BellronosError> {
+        match target {
+            ASTNode::Name { .. } | ASTNode::Attribute { .. } | ASTNode::Subscript { .. } => {
+                Ok(target)
+            }
+            ASTNode::Tuple { elements, span } => {
+                let elements = elements
+                    .into_iter()
+                    .map(|element| self.check_assignment_target(element))
+                    .collect::<Result<_, _>>()?;
+                Ok(ASTNode::Tuple { elements, span })
+            }
+            _ => Err(BellronosError::Parser(Box::new(
+                Diagnostic::new(
+                    ErrorCode::InvalidAssignmentTarget,
+                    "Cannot assign to this expression",
+                )
+                .with_span(target.span().clone())
+                .with_help(
+                    "assign to a name, an attribute (`obj.x`), an index (`items[0]`) \
+                     or a tuple of these (`(a, b)`)",
+                ),
+            ))),
+        }
     }
 
+    /// Parses an `if` statement. Each `elif` becomes a nested `If` that is
+    /// the sole statement of the enclosing `orelse`.
     fn parse_if(&mut self) -> Result<ASTNode, BellronosError> {
-        self.advance(); // Consume 'if'
+        let start = self.current_span();
+        self.advance(); // Consume 'if' or 'elif'
         let condition = Box::new(self.parse_expression()?);
+        let span = self.span_from(&start);
         self.expect_token(Token::Colon)?;
         self.expect_token(Token::Newline)?;
         let body = self.parse_block()?;
         let mut orelse = Vec::new();
-        if self.current_token() == Token::Else {
+        if self.current_token() == Token::Elif {
+            orelse.push(self.parse_if()?);
+        } else if self.current_token() == Token::Else {
             self.advance();
             self.expect_token(Token::Colon)?;
             self.expect_token(Token::Newline)?;
@@ -123,108 +356,312 @@ impl Parser {
             condition,
             body,
             orelse,
+            span,
         })
     }
 
     fn parse_while(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'while'
         let condition = Box::new(self.parse_expression()?);
+        let span = self.span_from(&start);
         self.expect_token(Token::Colon)?;
         self.expect_token(Token::Newline)?;
-        let body = self.parse_block()?;
-        Ok(ASTNode::While { condition, body })
+        let body = self.parse_loop_body()?;
+        Ok(ASTNode::While {
+            condition,
+            body,
+            span,
+        })
     }
 
     fn parse_for(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'for'
-        let target = self.expect_identifier()?;
+        let target = Box::new(self.parse_assignment_target()?);
         self.expect_token(Token::In)?;
         let iter = Box::new(self.parse_expression()?);
+        let span = self.span_from(&start);
+        self.expect_token(Token::Colon)?;
+        self.expect_token(Token::Newline)?;
+        let body = self.parse_loop_body()?;
+        Ok(ASTNode::For {
+            target,
+            iter,
+            body,
+            span,
+        })
+    }
+
+    fn parse_try(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        self.advance(); // Consume 'try'
+        let span = self.span_from(&start);
         self.expect_token(Token::Colon)?;
         self.expect_token(Token::Newline)?;
         let body = self.parse_block()?;
-        Ok(ASTNode::For { target, iter, body })
+
+        let mut handlers = Vec::new();
+        while self.current_token() == Token::Except {
+            let handler_start = self.current_span();
+            self.advance(); // Consume 'except'
+            let mut class = None;
+            let mut name = None;
+            if self.current_token() != Token::Colon {
+                class = Some(self.expect_identifier()?);
+                if self.current_token() == Token::As {
+                    self.advance();
+                    name = Some(self.expect_identifier()?);
+                }
+            }
+            let handler_span = self.span_from(&handler_start);
+            self.expect_token(Token::Colon)?;
+            self.expect_token(Token::Newline)?;
+            handlers.push(ExceptHandler {
+                class,
+                name,
+                body: self.parse_block()?,
+                span: handler_span,
+            });
+        }
+
+        let mut finally = Vec::new();
+        if self.current_token() == Token::Finally {
+            self.advance();
+            self.expect_token(Token::Colon)?;
+            self.expect_token(Token::Newline)?;
+            finally = self.parse_block()?;
+        } else if handlers.is_empty() {
+            return Err(self.error(
+                ErrorCode::UnexpectedToken,
+                format!(
+                    "Expected 'except' or 'finally' after 'try' block, found {:?}",
+                    self.current_token()
+                ),
+            ));
+        }
+
+        Ok(ASTNode::Try {
+            body,
+            handlers,
+            finally,
+            span,
+        })
+    }
+
+    fn parse_raise(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        self.advance(); // Consume 'raise'
+        let value = Box::new(self.parse_expression()?);
+        let span = self.span_from(&start);
+        self.expect_token(Token::Newline)?;
+        Ok(ASTNode::Raise { value, span })
+    }
+
+    /// Parses a function body, where `return` is allowed and enclosing
+    /// loops no longer apply.
+    fn parse_function_body(&mut self, is_async: bool) -> Result<Vec<ASTNode>, BellronosError> {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let in_async = std::mem::replace(&mut self.in_async, is_async);
+        self.function_depth += 1;
+        let body = self.parse_block();
+        self.function_depth -= 1;
+        self.in_async = in_async;
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    fn parse_loop_body(&mut self) -> Result<Vec<ASTNode>, BellronosError> {
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+        body
     }
 
     fn parse_return(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        if self.function_depth == 0 {
+            return Err(self.error(ErrorCode::MisplacedControlFlow, "'return' outside function"));
+        }
         self.advance(); // Consume 'return'
         let value = if self.current_token() == Token::Newline {
             None
         } else {
-            Some(Box::new(self.parse_expression()?))
+            Some(Box::new(self.parse_expression_list()?))
         };
+        let span = self.span_from(&start);
         self.expect_token(Token::Newline)?;
-        Ok(ASTNode::Return { value })
+        Ok(ASTNode::Return { value, span })
+    }
+
+    fn parse_loop_control(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        let token = self.current_token();
+        if self.loop_depth == 0 {
+            let keyword = if token == Token::Break {
+                "break"
+            } else {
+                "continue"
+            };
+            return Err(self.error(
+                ErrorCode::MisplacedControlFlow,
+                format!("'{}' outside loop", keyword),
+            ));
+        }
+        self.advance(); // Consume 'break' or 'continue'
+        let span = self.span_from(&start);
+        self.expect_token(Token::Newline)?;
+        Ok(if token == Token::Break {
+            ASTNode::Break { span }
+        } else {
+            ASTNode::Continue { span }
+        })
+    }
+
+    fn parse_nonlocal(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        if self.function_depth == 0 {
+            return Err(self.error(ErrorCode::MisplacedNonlocal, "'nonlocal' outside function"));
+        }
+        self.advance(); // Consume 'nonlocal'
+        let mut names = vec![self.expect_identifier()?];
+        while self.current_token() == Token::Comma {
+            self.advance();
+            names.push(self.expect_identifier()?);
+        }
+        let span = self.span_from(&start);
+        self.expect_token(Token::Newline)?;
+        Ok(ASTNode::Nonlocal { names, span })
     }
 
     fn parse_async(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume 'async'
-        self.expect_token(Token::Define)?;
-        let function = self.parse_function_def()?;
-        if let ASTNode::FunctionDef {
-            name,
-            args,
-            return_type,
-            body,
-        } = function
-        {
-            Ok(ASTNode::Async {
-                body: vec![ASTNode::FunctionDef {
-                    name,
-                    args,
-                    return_type,
-                    body,
-                }],
-            })
-        } else {
-            Err(BellronosError::Parser(
-                "Expected function definition after 'async'".to_string(),
-            ))
+        if self.current_token() != Token::Define {
+            return Err(self.error(
+                ErrorCode::UnexpectedToken,
+                "Expected function definition after 'async'",
+            ));
         }
+        let function = self.parse_function_def(true)?;
+        let span = start.to(function.span());
+        Ok(ASTNode::Async {
+            body: vec![function],
+            span,
+        })
     }
 
     fn parse_yield(&mut self) -> Result<ASTNode, BellronosError> {
-        self.advance(); // Consume 'yield'
-        let value = Box::new(self.parse_expression()?);
+        let value = self.parse_yield_expression()?;
         self.expect_token(Token::Newline)?;
-        Ok(ASTNode::Yield { value })
+        Ok(value)
     }
 
-    fn parse_closure(&mut self) -> Result<ASTNode, BellronosError> {
-        self.advance(); // Consume 'closure'
+    /// Parses `yield value`, which makes the enclosing function a
+    /// generator.
+    fn parse_yield_expression(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        if self.function_depth == 0 {
+            return Err(self.error(ErrorCode::YieldOutsideGenerator, "'yield' outside function"));
+        }
+        if self.in_async {
+            return Err(self.error(
+                ErrorCode::YieldOutsideGenerator,
+                "'yield' inside async function",
+            ));
+        }
+        self.advance(); // Consume 'yield'
+        let value = Box::new(self.parse_expression_list()?);
+        let span = self.span_from(&start);
+        Ok(ASTNode::Yield { value, span })
+    }
+
+    /// Parses `define(x: int) -> int: x * x` or `closure (x: int): x * x`,
+    /// whose body is one expression.
+    fn parse_anonymous_function(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        self.advance(); // Consume 'define' or 'closure'
         let params = self.parse_function_args()?;
+        let return_type = match self.current_token() {
+            Token::Arrow => Some(self.parse_return_type()?),
+            _ => None,
+        };
         self.expect_token(Token::Colon)?;
-        let body = Box::new(self.parse_expression()?);
-        Ok(ASTNode::Closure { params, body })
+        let body_start = self.current_span();
+        if self.current_token() == Token::Return {
+            self.advance();
+        }
+        // An `await` in the body would belong to this function, which
+        // isn't async
+        let in_async = std::mem::replace(&mut self.in_async, false);
+        let value = self.parse_expression();
+        self.in_async = in_async;
+        let value = Box::new(value?);
+        let body = vec![ASTNode::Return {
+            value: Some(value),
+            span: self.span_from(&body_start),
+        }];
+        let span = self.span_from(&start);
+        Ok(ASTNode::Closure {
+            params,
+            return_type,
+            body,
+            span,
+        })
     }
 
     fn parse_expression(&mut self) -> Result<ASTNode, BellronosError> {
-        self.parse_binary_operation()
-    }
-
-    fn parse_binary_operation(&mut self) -> Result<ASTNode, BellronosError> {
-        let mut left = self.parse_unary()?;
-
-        while matches!(
-            self.current_token(),
-            Token::Plus
-                | Token::Minus
-                | Token::Multiply
-                | Token::Divide
-                | Token::Equals
-                | Token::NotEquals
-                | Token::LessThan
-                | Token::GreaterThan
-                | Token::LessThanOrEqual
-                | Token::GreaterThanOrEqual
-        ) {
-            let op = format!("{:?}", self.current_token());
+        self.parse_binary_operation(0)
+    }
+
+    /// Precedence climbing from `or` down to the multiplicative tier.
+    fn parse_binary_operation(&mut self, min_precedence: u8) -> Result<ASTNode, BellronosError> {
+        let mut left = if self.current_token() == Token::Not
+            && min_precedence <= UnaryOperator::NOT_PRECEDENCE
+        {
+            let start = self.current_span();
             self.advance();
-            let right = self.parse_unary()?;
+            let operand = self.parse_binary_operation(UnaryOperator::NOT_PRECEDENCE)?;
+            ASTNode::UnaryOp {
+                op: UnaryOperator::Not,
+                span: start.to(operand.span()),
+                operand: Box::new(operand),
+            }
+        } else {
+            self.parse_unary()?
+        };
+
+        loop {
+            let mut op = match self.current_token() {
+                // `not in` is the only binary operator starting with `not`
+                Token::Not if self.peek_token() == Token::In => BinaryOperator::NotIn,
+                token => match binary_operator(&token) {
+                    Some(op) => op,
+                    None => break,
+                },
+            };
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.advance();
+            if op == BinaryOperator::NotIn {
+                self.advance(); // Consume 'in'
+            } else if op == BinaryOperator::Is && self.current_token() == Token::Not {
+                self.advance();
+                op = BinaryOperator::IsNot;
+            }
+            let next_min = if op.is_right_associative() {
+                op.precedence()
+            } else {
+                op.precedence() + 1
+            };
+            let right = self.parse_binary_operation(next_min)?;
+            let span = left.span().to(right.span());
             left = ASTNode::BinOp {
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -234,80 +671,288 @@ impl Parser {
     fn parse_unary(&mut self) -> Result<ASTNode, BellronosError> {
         match self.current_token() {
             Token::Minus => {
+                let start = self.current_span();
                 self.advance();
                 let expr = self.parse_unary()?;
-                Ok(ASTNode::BinOp {
-                    left: Box::new(ASTNode::Num { value: 0.0 }),
-                    op: "-".to_string(),
-                    right: Box::new(expr),
+                let span = start.to(expr.span());
+                // Fold negative literals so `-1` stays an int constant
+                match expr {
+                    ASTNode::Int { value, .. } => {
+                        return Ok(ASTNode::Int {
+                            value: value.wrapping_neg(),
+                            span,
+                        })
+                    }
+                    ASTNode::Float { value, .. } => {
+                        return Ok(ASTNode::Float {
+                            value: -value,
+                            span,
+                        })
+                    }
+                    _ => {}
+                }
+                Ok(ASTNode::UnaryOp {
+                    op: UnaryOperator::Negate,
+                    operand: Box::new(expr),
+                    span,
                 })
             }
-            _ => self.parse_primary(),
+            Token::Await => {
+                let start = self.current_span();
+                if !self.in_async {
+                    return Err(
+                        self.error(ErrorCode::MisplacedAwait, "'await' outside async function")
+                    );
+                }
+                self.advance();
+                let value = Box::new(self.parse_unary()?);
+                let span = start.to(value.span());
+                Ok(ASTNode::Await { value, span })
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    /// `**` binds tighter than unary minus on its left (`-2 ** 2` is -4) and
+    /// accepts a signed exponent on its right (`2 ** -1`).
+    fn parse_power(&mut self) -> Result<ASTNode, BellronosError> {
+        let base = self.parse_postfix()?;
+        if self.current_token() != Token::Power {
+            return Ok(base);
+        }
+        self.advance();
+        let exponent = self.parse_unary()?;
+        let span = base.span().to(exponent.span());
+        Ok(ASTNode::BinOp {
+            left: Box::new(base),
+            op: BinaryOperator::Power,
+            right: Box::new(exponent),
+            span,
+        })
+    }
+
+    /// Parses a primary expression followed by any chain of `.name`
+    /// attribute accesses and `(args)` calls.
+    fn parse_postfix(&mut self) -> Result<ASTNode, BellronosError> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            match self.current_token() {
+                Token::Dot => {
+                    self.advance();
+                    let attr = self.expect_identifier()?;
+                    let span = self.span_from(expr.span());
+                    expr = ASTNode::Attribute {
+                        value: Box::new(expr),
+                        attr,
+                        span,
+                    };
+                }
+                Token::LeftParen => expr = self.parse_call(expr)?,
+                Token::LeftBracket => {
+                    self.advance();
+                    let index = self.parse_subscript_index()?;
+                    self.expect_token(Token::RightBracket)?;
+                    let span = self.span_from(expr.span());
+                    expr = ASTNode::Subscript {
+                        value: Box::new(expr),
+                        index: Box::new(index),
+                        span,
+                    };
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// Parses the inside of `[...]`: either an index expression or a
+    /// `start:stop:step` slice.
+    fn parse_subscript_index(&mut self) -> Result<ASTNode, BellronosError> {
+        let start_span = self.current_span();
+        let start = self.parse_slice_part()?;
+        if self.current_token() != Token::Colon {
+            return start.map(|index| *index).ok_or_else(|| {
+                self.error(
+                    ErrorCode::UnexpectedToken,
+                    format!("Expected index, found {:?}", self.current_token()),
+                )
+            });
+        }
+        self.advance();
+        let stop = self.parse_slice_part()?;
+        let mut step = None;
+        if self.current_token() == Token::Colon {
+            self.advance();
+            step = self.parse_slice_part()?;
+        }
+        Ok(ASTNode::Slice {
+            start,
+            stop,
+            step,
+            span: self.span_from(&start_span),
+        })
+    }
+
+    fn parse_slice_part(&mut self) -> Result<Option<Box<ASTNode>>, BellronosError> {
+        if matches!(self.current_token(), Token::Colon | Token::RightBracket) {
+            Ok(None)
+        } else {
+            Ok(Some(Box::new(self.parse_expression()?)))
         }
     }
 
     fn parse_primary(&mut self) -> Result<ASTNode, BellronosError> {
+        let span = self.current_span();
         match self.current_token() {
             Token::Identifier(name) => {
                 self.advance();
-                if self.current_token() == Token::LeftParen {
-                    self.parse_function_call(&name)
-                } else {
-                    Ok(ASTNode::Name { id: name })
-                }
+                Ok(ASTNode::Name { id: name, span })
+            }
+            Token::Async => {
+                self.advance();
+                Ok(ASTNode::Name {
+                    id: "async".to_string(),
+                    span,
+                })
+            }
+            // Only statements start with the `set` keyword, so elsewhere it
+            // names the builtin
+            Token::Set => {
+                self.advance();
+                Ok(ASTNode::Name {
+                    id: "set".to_string(),
+                    span,
+                })
             }
             Token::String(value) => {
                 self.advance();
-                Ok(ASTNode::Str { value })
+                Ok(ASTNode::Str { value, span })
             }
-            Token::Number(value) => {
+            Token::Integer(value) => {
                 self.advance();
-                Ok(ASTNode::Num { value })
+                Ok(ASTNode::Int { value, span })
             }
-            Token::True => {
+            Token::Float(value) => {
                 self.advance();
-                Ok(ASTNode::Bool { value: true })
+                Ok(ASTNode::Float { value, span })
             }
-            Token::False => {
+            Token::True => {
                 self.advance();
-                Ok(ASTNode::Bool { value: false })
+                Ok(ASTNode::Bool { value: true, span })
             }
-            Token::LeftParen => {
+            Token::False => {
                 self.advance();
-                let expr = self.parse_expression()?;
-                self.expect_token(Token::RightParen)?;
-                Ok(expr)
+                Ok(ASTNode::Bool { value: false, span })
             }
+            Token::Define | Token::Closure => self.parse_anonymous_function(),
+            Token::LeftParen => self.parse_parenthesized(),
             Token::LeftBracket => self.parse_list(),
-            Token::LeftBrace => self.parse_dict(),
-            _ => Err(BellronosError::Parser(format!(
-                "Unexpected token: {:?}",
-                self.current_token()
-            ))),
+            Token::LeftBrace => self.parse_dict_or_set(),
+            _ => Err(self.error(
+                ErrorCode::UnexpectedToken,
+                format!("Unexpected token: {:?}", self.current_token()),
+            )),
         }
     }
 
-    fn parse_function_call(&mut self, name: &str) -> Result<ASTNode, BellronosError> {
+    /// Parses `(expr)`, or a tuple literal: `()`, `(x,)` or `(x, y, ...)`.
+    fn parse_parenthesized(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume '('
-        let mut args = Vec::new();
-        if self.current_token() != Token::RightParen {
-            loop {
-                args.push(self.parse_expression()?);
-                if self.current_token() == Token::Comma {
+        let mut elements = Vec::new();
+        while self.current_token() != Token::RightParen {
+            elements.push(self.parse_expression()?);
+            if self.current_token() != Token::Comma {
+                if elements.len() == 1 {
+                    self.expect_token(Token::RightParen)?;
+                    return Ok(elements.remove(0));
+                }
+                break;
+            }
+            self.advance();
+        }
+        self.expect_token(Token::RightParen)?;
+        let span = self.span_from(&start);
+        Ok(ASTNode::Tuple { elements, span })
+    }
+
+    /// Parses an expression, or a bare comma-separated list of them as a
+    /// tuple, e.g. `return a, b`.
+    fn parse_expression_list(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
+        let first = self.parse_expression()?;
+        if self.current_token() != Token::Comma {
+            return Ok(first);
+        }
+        let mut elements = vec![first];
+        while self.current_token() == Token::Comma {
+            self.advance();
+            elements.push(self.parse_expression()?);
+        }
+        let span = self.span_from(&start);
+        Ok(ASTNode::Tuple { elements, span })
+    }
+
+    /// Parses the arguments of a call: positional ones first, then
+    /// `name=value` keywords. `*items` and `**mapping` unpack an iterable
+    /// or a dict into arguments.
+    fn parse_call(&mut self, callee: ASTNode) -> Result<ASTNode, BellronosError> {
+        self.advance(); // Consume '('
+        let mut args: Vec<Argument> = Vec::new();
+        while self.current_token() != Token::RightParen {
+            let arg = match (self.current_token(), self.peek_token()) {
+                (Token::Multiply, _) => {
                     self.advance();
-                } else {
-                    break;
+                    Argument::Unpack(self.parse_expression()?)
+                }
+                (Token::Power, _) => {
+                    self.advance();
+                    Argument::UnpackKeywords(self.parse_expression()?)
+                }
+                (Token::Identifier(name), Token::Set) => {
+                    if args
+                        .iter()
+                        .any(|a| matches!(a, Argument::Keyword(n, _) if *n == name))
+                    {
+                        return Err(self.error(
+                            ErrorCode::DuplicateParameter,
+                            format!("Keyword argument '{}' repeated", name),
+                        ));
+                    }
+                    self.advance();
+                    self.advance();
+                    Argument::Keyword(name, self.parse_expression()?)
+                }
+                _ => {
+                    if args
+                        .iter()
+                        .any(|a| matches!(a, Argument::Keyword(..) | Argument::UnpackKeywords(_)))
+                    {
+                        return Err(self.error(
+                            ErrorCode::ArgumentOrder,
+                            "Positional argument follows keyword argument",
+                        ));
+                    }
+                    Argument::Positional(self.parse_expression()?)
                 }
+            };
+            args.push(arg);
+            if self.current_token() == Token::Comma {
+                self.advance();
+            } else {
+                break;
             }
         }
         self.expect_token(Token::RightParen)?;
+        let span = self.span_from(callee.span());
         Ok(ASTNode::Call {
-            func: name.to_string(),
+            callee: Box::new(callee),
             args,
+            span,
         })
     }
 
     fn parse_list(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume '['
         let mut elements = Vec::new();
         if self.current_token() != Token::RightBracket {
@@ -321,43 +966,137 @@ impl Parser {
             }
         }
         self.expect_token(Token::RightBracket)?;
-        Ok(ASTNode::List { elements })
+        Ok(ASTNode::List {
+            elements,
+            span: self.span_from(&start),
+        })
     }
 
-    fn parse_dict(&mut self) -> Result<ASTNode, BellronosError> {
+    /// Parses a brace literal. `{}` and `{key: value, ...}` are dicts, while
+    /// `{x, ...}` is a set; the token after the first element decides.
+    fn parse_dict_or_set(&mut self) -> Result<ASTNode, BellronosError> {
+        let start = self.current_span();
         self.advance(); // Consume '{'
-        let mut pairs = Vec::new();
-        if self.current_token() != Token::RightBrace {
-            loop {
-                let key = self.parse_expression()?;
-                self.expect_token(Token::Colon)?;
-                let value = self.parse_expression()?;
-                pairs.push((key, value));
-                if self.current_token() == Token::Comma {
-                    self.advance();
-                } else {
+        if self.current_token() == Token::RightBrace {
+            self.advance();
+            return Ok(ASTNode::Dict {
+                pairs: Vec::new(),
+                span: self.span_from(&start),
+            });
+        }
+        let first = self.parse_expression()?;
+        if self.current_token() != Token::Colon {
+            let mut elements = vec![first];
+            while self.current_token() == Token::Comma {
+                self.advance();
+                if self.current_token() == Token::RightBrace {
                     break;
                 }
+                elements.push(self.parse_expression()?);
+            }
+            self.expect_token(Token::RightBrace)?;
+            return Ok(ASTNode::Set {
+                elements,
+                span: self.span_from(&start),
+            });
+        }
+        let mut pairs = Vec::new();
+        let mut key = first;
+        loop {
+            self.expect_token(Token::Colon)?;
+            let value = self.parse_expression()?;
+            pairs.push((key, value));
+            if self.current_token() != Token::Comma {
+                break;
             }
+            self.advance();
+            if self.current_token() == Token::RightBrace {
+                break;
+            }
+            key = self.parse_expression()?;
         }
         self.expect_token(Token::RightBrace)?;
-        Ok(ASTNode::Dict { pairs })
+        Ok(ASTNode::Dict {
+            pairs,
+            span: self.span_from(&start),
+        })
     }
 
-    fn parse_function_args(&mut self) -> Result<Vec<(String, Type)>, BellronosError> {
-        let mut args = Vec::new();
+    /// Parses `(a, b: int = 1, *rest: int, key: string = "", **options)`.
+    fn parse_function_args(&mut self) -> Result<Vec<Parameter>, BellronosError> {
+        let mut args: Vec<Parameter> = Vec::new();
         self.expect_token(Token::LeftParen)?;
-        if self.current_token() != Token::RightParen {
-            loop {
-                let name = self.expect_identifier()?;
-                self.expect_token(Token::Colon)?;
-                let type_ = self.parse_type()?;
-                args.push((name, type_));
-                if self.current_token() == Token::Comma {
+        while self.current_token() != Token::RightParen {
+            if args.last().map(|p| p.kind) == Some(ParamKind::KeywordVariadic) {
+                return Err(self.error(
+                    ErrorCode::ArgumentOrder,
+                    "'**' parameter must be the last parameter",
+                ));
+            }
+            let kind = match self.current_token() {
+                Token::Multiply if args.iter().any(|p| p.kind == ParamKind::Variadic) => {
+                    return Err(self.error(
+                        ErrorCode::ArgumentOrder,
+                        "Only one '*' parameter is allowed",
+                    ));
+                }
+                Token::Multiply => {
                     self.advance();
-                } else {
-                    break;
+                    ParamKind::Variadic
+                }
+                Token::Power => {
+                    self.advance();
+                    ParamKind::KeywordVariadic
                 }
+                _ => ParamKind::Required,
+            };
+            if let Token::Identifier(name) = self.current_token() {
+                if args.iter().any(|p| p.name == name) {
+                    return Err(self.error(
+                        ErrorCode::DuplicateParameter,
+                        format!("Duplicate parameter '{}'", name),
+                    ));
+                }
+            }
+            let name = self.expect_identifier()?;
+            // Unannotated parameters, such as a method's `self`, accept
+            // any value
+            let type_ = if self.current_token() == Token::Colon {
+                self.advance();
+                self.parse_type()?
+            } else {
+                Type::Any
+            };
+            let (kind, default) =
+                if kind == ParamKind::Required && self.current_token() == Token::Set {
+                    self.advance();
+                    (ParamKind::Optional, Some(self.parse_expression()?))
+                } else {
+                    (kind, None)
+                };
+            let keyword_only = args.iter().any(|p| p.kind == ParamKind::Variadic);
+            if kind == ParamKind::Required
+                && !keyword_only
+                && args.iter().any(|p| p.kind == ParamKind::Optional)
+            {
+                return Err(self.error(
+                    ErrorCode::ArgumentOrder,
+                    format!(
+                        "Parameter '{}' without a default follows one with a default",
+                        name
+                    ),
+                ));
+            }
+            args.push(Parameter {
+                name,
+                type_,
+                default,
+                kind,
+            });
+            if self.current_token() == Token::Comma {
+                self.advance();
+            } else {
+                break;
             }
         }
         self.expect_token(Token::RightParen)?;
@@ -382,44 +1121,443 @@ impl Parser {
                     "float" => Ok(Type::Float),
                     "string" => Ok(Type::String),
                     "bool" => Ok(Type::Bool),
-                    "list" => {
+                    "none" => Ok(Type::None),
+                    "any" => Ok(Type::Any),
+                    "range" => Ok(Type::Range),
+                    "file" => Ok(Type::File),
+                    // `generator` alone leaves the yielded type open
+                    "generator" if self.current_token() == Token::LeftBracket => {
+                        self.advance();
+                        let yield_type = self.parse_type()?;
+                        self.expect_token(Token::RightBracket)?;
+                        Ok(Type::Generator(Box::new(yield_type)))
+                    }
+                    "generator" => Ok(Type::Generator(Box::new(Type::Any))),
+                    "task" if self.current_token() == Token::LeftBracket => {
+                        self.advance();
+                        let result_type = self.parse_type()?;
+                        self.expect_token(Token::RightBracket)?;
+                        Ok(Type::Task(Box::new(result_type)))
+                    }
+                    "task" => Ok(Type::Task(Box::new(Type::Any))),
+                    "function" => Ok(Type::Callable),
+                    // Bare `list`, `dict` and `set` leave their items open
+                    "list" => Ok(Type::List(Box::new(self.parse_item_type()?))),
+                    "tuple" => {
                         self.expect_token(Token::LeftBracket)?;
-                        let inner_type = self.parse_type()?;
+                        let mut element_types = vec![self.parse_type()?];
+                        while self.current_token() == Token::Comma {
+                            self.advance();
+                            element_types.push(self.parse_type()?);
+                        }
                         self.expect_token(Token::RightBracket)?;
-                        Ok(Type::List(Box::new(inner_type)))
+                        Ok(Type::Tuple(element_types))
                     }
-                    "dict" => {
-                        self.expect_token(Token::LeftBrace)?;
+                    "dict" if self.current_token() == Token::LeftBrace => {
+                        self.advance();
                         let key_type = self.parse_type()?;
                         self.expect_token(Token::Colon)?;
                         let value_type = self.parse_type()?;
                         self.expect_token(Token::RightBrace)?;
                         Ok(Type::Dict(Box::new(key_type), Box::new(value_type)))
                     }
+                    "dict" => Ok(Type::Dict(Box::new(Type::Any), Box::new(Type::Any))),
                     _ => Ok(Type::Custom(name)),
                 }
             }
-            _ => Err(BellronosError::Parser("Expected type".to_string())),
+            // `set` is a keyword, so its type doesn't lex as a name
+            Token::Set => {
+                self.advance();
+                Ok(Type::Set(Box::new(self.parse_item_type()?)))
+            }
+            _ => Err(self.error(
+                ErrorCode::ExpectedType,
+                format!("Expected type, found {:?}", self.current_token()),
+            )),
+        }
+    }
+
+    /// The `[T]` after `list` or `set`, or `any` without one.
+    fn parse_item_type(&mut self) -> Result<Type, BellronosError> {
+        if self.current_token() != Token::LeftBracket {
+            return Ok(Type::Any);
         }
+        self.advance();
+        let item_type = self.parse_type()?;
+        self.expect_token(Token::RightBracket)?;
+        Ok(item_type)
     }
 
     fn parse_block(&mut self) -> Result<Vec<ASTNode>, BellronosError> {
+        if self.current_token() != Token::Indent {
+            return Err(BellronosError::Parser(Box::new(
+                Diagnostic::new(
+                    ErrorCode::ExpectedIndentedBlock,
+                    format!(
+                        "Expected an indented block, found {:?}",
+                        self.current_token()
+                    ),
+                )
+                .with_span(self.current_span())
+                .with_label(self.previous_span(), "block opened here")
+                .with_help("indent the body of the block further than its header"),
+            )));
+        }
+        self.advance(); // Consume indent
         let mut body = Vec::new();
-        while self.current_token() != Token::EOF && !matches!(self.current_token(), Token::Else) {
-            body.push(self.parse_statement()?);
+        while !matches!(self.current_token(), Token::Dedent | Token::EOF) {
+            let statement_start = self.position;
+            match self.parse_statement() {
+                Ok(statement) if self.in_async => body.extend(self.hoist_awaits(statement)),
+                Ok(statement) => body.push(statement),
+                Err(err) => self.recover(err, statement_start)?,
+            }
         }
+        self.expect_token(Token::Dedent)?;
         Ok(body)
     }
 
+    /// Splits nested `await`s out into statements of their own, since a
+    /// task only suspends between statements: `io.print(await t)` runs as
+    /// `set await#0 to await t` then `io.print(await#0)`.
+    fn hoist_awaits(&mut self, statement: ASTNode) -> Vec<ASTNode> {
+        self.temporaries = 0;
+        let mut hoisted = self.hoist_statement(statement);
+        let ends_scope = matches!(
+            hoisted.last(),
+            Some(ASTNode::Return { .. } | ASTNode::Raise { .. })
+        );
+        if self.temporaries > 0 && !ends_scope {
+            let span = hoisted.last().map(|s| s.span().clone()).unwrap_or_default();
+            hoisted.push(ASTNode::Unbind {
+                names: (0..self.temporaries).map(temporary_name).collect(),
+                span,
+            });
+        }
+        hoisted
+    }
+
+    fn hoist_statement(&mut self, statement: ASTNode) -> Vec<ASTNode> {
+        let mut hoisted = Vec::new();
+        let statement = match statement {
+            ASTNode::Expr { mut value, span } => {
+                self.hoist_operand(&mut value, &mut hoisted);
+                ASTNode::Expr { value, span }
+            }
+            ASTNode::Return {
+                value: Some(mut value),
+                span,
+            } => {
+                self.hoist_operand(&mut value, &mut hoisted);
+                ASTNode::Return {
+                    value: Some(value),
+                    span,
+                }
+            }
+            ASTNode::Assign {
+                mut target,
+                mut value,
+                span,
+            } => {
+                if target.awaits_within() {
+                    // The value is computed before the target's operands
+                    self.spill(&mut value, &mut hoisted);
+                    self.hoist_operands(&mut target, &mut hoisted);
+                } else {
+                    self.hoist_operand(&mut value, &mut hoisted);
+                }
+                ASTNode::Assign {
+                    target,
+                    value,
+                    span,
+                }
+            }
+            ASTNode::Raise { mut value, span } => {
+                self.hoist(&mut value, &mut hoisted);
+                ASTNode::Raise { value, span }
+            }
+            ASTNode::If {
+                mut condition,
+                body,
+                mut orelse,
+                span,
+            } => {
+                self.hoist(&mut condition, &mut hoisted);
+                // An `elif` is an `if` alone in the `else` block, which
+                // wasn't parsed as a block of its own
+                if let [ASTNode::If { .. }] = orelse.as_slice() {
+                    let elif = orelse.pop().unwrap();
+                    orelse = self.hoist_statement(elif);
+                }
+                ASTNode::If {
+                    condition,
+                    body,
+                    orelse,
+                    span,
+                }
+            }
+            ASTNode::While {
+                mut condition,
+                body,
+                span,
+            } if condition.awaits_within() => {
+                // The condition is awaited again on every iteration, so it
+                // moves into the loop as `if not condition: break`
+                let mut checked = Vec::new();
+                self.hoist(&mut condition, &mut checked);
+                let condition_span = condition.span().clone();
+                checked.push(ASTNode::If {
+                    condition: Box::new(ASTNode::UnaryOp {
+                        op: UnaryOperator::Not,
+                        operand: condition,
+                        span: condition_span.clone(),
+                    }),
+                    body: vec![ASTNode::Break {
+                        span: condition_span.clone(),
+                    }],
+                    orelse: Vec::new(),
+                    span: condition_span.clone(),
+                });
+                checked.extend(body);
+                ASTNode::While {
+                    condition: Box::new(ASTNode::Bool {
+                        value: true,
+                        span: condition_span,
+                    }),
+                    body: checked,
+                    span,
+                }
+            }
+            ASTNode::For {
+                target,
+                mut iter,
+                body,
+                span,
+            } => {
+                self.hoist(&mut iter, &mut hoisted);
+                ASTNode::For {
+                    target,
+                    iter,
+                    body,
+                    span,
+                }
+            }
+            other => other,
+        };
+        hoisted.push(statement);
+        hoisted
+    }
+
+    /// Hoists the `await`s inside `value`, which can itself be awaited in
+    /// place.
+    fn hoist_operand(&mut self, value: &mut ASTNode, hoisted: &mut Vec<ASTNode>) {
+        match value {
+            ASTNode::Await { value, .. } => self.hoist(value, hoisted),
+            value => self.hoist(value, hoisted),
+        }
+    }
+
+    /// Replaces every `await` inside `expr` with a temporary assigned by a
+    /// statement added to `hoisted`.
+    fn hoist(&mut self, expr: &mut ASTNode, hoisted: &mut Vec<ASTNode>) {
+        if !expr.awaits_within() {
+            return;
+        }
+        match expr {
+            ASTNode::Await { value, .. } => {
+                self.hoist(value, hoisted);
+                self.assign_temporary(expr, hoisted);
+            }
+            ASTNode::BinOp { op, right, .. } if op.is_logical() && right.awaits_within() => {
+                // The right operand, and what it awaits, only runs when it
+                // decides the result:
+                //     set tmp to left and true
+                //     if tmp:
+                //         set tmp to tmp and right
+                let temporary = self.temporary(expr.span());
+                let ASTNode::BinOp {
+                    mut left,
+                    op,
+                    mut right,
+                    span,
+                } = std::mem::replace(expr, temporary.clone())
+                else {
+                    unreachable!()
+                };
+                self.hoist(&mut left, hoisted);
+                let neutral = ASTNode::Bool {
+                    value: op == BinaryOperator::And,
+                    span: span.clone(),
+                };
+                hoisted.push(assign(&temporary, binary(left, op, neutral), &span));
+                let mut body = Vec::new();
+                self.hoist(&mut right, &mut body);
+                body.push(assign(
+                    &temporary,
+                    binary(Box::new(temporary.clone()), op, *right),
+                    &span,
+                ));
+                let mut condition = Box::new(temporary);
+                if op == BinaryOperator::Or {
+                    condition = Box::new(ASTNode::UnaryOp {
+                        op: UnaryOperator::Not,
+                        operand: condition,
+                        span: span.clone(),
+                    });
+                }
+                hoisted.push(ASTNode::If {
+                    condition,
+                    body,
+                    orelse: Vec::new(),
+                    span,
+                });
+            }
+            _ => self.hoist_operands(expr, hoisted),
+        }
+    }
+
+    /// Hoists the `await`s among the operands of `expr`, along with the
+    /// operands evaluated before the last of them.
+    fn hoist_operands(&mut self, expr: &mut ASTNode, hoisted: &mut Vec<ASTNode>) {
+        let operands = expr.subexpressions_mut();
+        let Some(last) = operands.iter().rposition(|operand| operand.awaits_within()) else {
+            return;
+        };
+        for (index, operand) in operands.into_iter().enumerate().take(last + 1) {
+            if index < last {
+                self.spill(operand, hoisted);
+            } else {
+                self.hoist(operand, hoisted);
+            }
+        }
+    }
+
+    /// Computes `expr` into a temporary ahead of the statement, unless it
+    /// only reads a literal or a name.
+    fn spill(&mut self, expr: &mut ASTNode, hoisted: &mut Vec<ASTNode>) {
+        self.hoist(expr, hoisted);
+        if !is_reference(expr) {
+            self.assign_temporary(expr, hoisted);
+        }
+    }
+
+    /// Moves `expr` into a temporary assigned ahead of the statement.
+    fn assign_temporary(&mut self, expr: &mut ASTNode, hoisted: &mut Vec<ASTNode>) {
+        let temporary = self.temporary(expr.span());
+        let value = std::mem::replace(expr, temporary.clone());
+        let span = value.span().clone();
+        hoisted.push(assign(&temporary, value, &span));
+    }
+
+    fn temporary(&mut self, span: &Span) -> ASTNode {
+        self.temporaries += 1;
+        ASTNode::Name {
+            id: temporary_name(self.temporaries - 1),
+            span: span.clone(),
+        }
+    }
+
+    /// Records a syntax error and skips ahead to the next statement. Fails
+    /// once `MAX_ERRORS` have been collected so that parsing stops.
+    fn recover(
+        &mut self,
+        err: BellronosError,
+        statement_start: usize,
+    ) -> Result<(), BellronosError> {
+        if self.aborted {
+            return Err(err);
+        }
+        self.errors.push(err);
+        if self.errors.len() >= MAX_ERRORS {
+            self.abort();
+            return Err(self.error(ErrorCode::TooManyErrors, "Parsing aborted"));
+        }
+        self.synchronize(statement_start)
+    }
+
+    /// Stops parsing, reporting the cap at the last error collected.
+    fn abort(&mut self) {
+        self.aborted = true;
+        let span = self
+            .errors
+            .last()
+            .and_then(|err| err.diagnostic())
+            .and_then(|d| d.span.clone())
+            .unwrap_or_else(|| self.current_span());
+        self.errors.push(BellronosError::Parser(Box::new(
+            Diagnostic::new(
+                ErrorCode::TooManyErrors,
+                format!("Too many syntax errors; stopping after {}", MAX_ERRORS),
+            )
+            .with_span(span),
+        )));
+    }
+
+    /// Skips the rest of a broken statement up to and including its newline.
+    /// An indented block the statement opened, and any trailing `else`
+    /// clauses, are still parsed so that errors inside them get reported.
+    fn synchronize(&mut self, statement_start: usize) -> Result<(), BellronosError> {
+        if self.position == statement_start
+            && !matches!(self.current_token(), Token::Indent | Token::EOF)
+        {
+            self.advance();
+        }
+        loop {
+            match self.current_token() {
+                Token::EOF | Token::Dedent => return Ok(()),
+                Token::Indent => {
+                    self.parse_recovered_block(statement_start)?;
+                }
+                Token::Newline => {
+                    self.advance();
+                    if self.current_token() == Token::Indent {
+                        self.parse_recovered_block(statement_start)?;
+                    }
+                    if !matches!(
+                        self.current_token(),
+                        Token::Elif | Token::Else | Token::Except | Token::Finally
+                    ) {
+                        return Ok(());
+                    }
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    /// Parses the body of a statement whose header failed, keeping the
+    /// function or loop context.
+    fn parse_recovered_block(
+        &mut self,
+        statement_start: usize,
+    ) -> Result<Vec<ASTNode>, BellronosError> {
+        // The kind of a decorated statement is that of its definition
+        let mut statement_start = statement_start;
+        while let Some(Token::At) = self.tokens.get(statement_start).map(|t| &t.token) {
+            statement_start += self
+                .tokens
+                .iter()
+                .skip(statement_start)
+                .position(|t| t.token == Token::Newline)
+                .map_or(1, |newline| newline + 1);
+        }
+        match self.tokens.get(statement_start).map(|t| &t.token) {
+            Some(Token::Define) => self.parse_function_body(false),
+            Some(Token::Async) => self.parse_function_body(true),
+            Some(Token::While) | Some(Token::For) => self.parse_loop_body(),
+            _ => self.parse_block(),
+        }
+    }
+
     fn expect_identifier(&mut self) -> Result<String, BellronosError> {
         if let Token::Identifier(name) = self.current_token() {
             self.advance();
             Ok(name)
         } else {
-            Err(BellronosError::Parser(format!(
-                "Expected identifier, found {:?}",
-                self.current_token()
-            )))
+            Err(self.error(
+                ErrorCode::ExpectedIdentifier,
+                format!("Expected identifier, found {:?}", self.current_token()),
+            ))
         }
     }
 
@@ -428,28 +1566,122 @@ impl Parser {
             self.advance();
             Ok(())
         } else {
-            Err(BellronosError::Parser(format!(
-                "Expected {:?}, found {:?}",
-                expected,
-                self.current_token()
-            )))
+            Err(self.error(
+                ErrorCode::UnexpectedToken,
+                format!("Expected {:?}, found {:?}", expected, self.current_token()),
+            ))
         }
     }
 
+    fn peek_token(&self) -> Token {
+        self.tokens
+            .get(self.position + 1)
+            .map(|t| t.token.clone())
+            .unwrap_or(Token::EOF)
+    }
+
     fn current_token(&self) -> Token {
         self.tokens
             .get(self.position)
-            .cloned()
+            .map(|t| t.token.clone())
             .unwrap_or(Token::EOF)
     }
 
+    fn current_span(&self) -> Span {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.span.clone())
+            .unwrap_or_default()
+    }
+
+    /// Span of the most recently consumed token.
+    fn previous_span(&self) -> Span {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|t| t.span.clone())
+            .unwrap_or_else(|| self.current_span())
+    }
+
+    /// Span from `start` up to the end of the most recently consumed token.
+    fn span_from(&self, start: &Span) -> Span {
+        start.to(&self.previous_span())
+    }
+
+    fn error(&self, code: ErrorCode, message: impl Into<String>) -> BellronosError {
+        BellronosError::Parser(Box::new(
+            Diagnostic::new(code, message).with_span(self.current_span()),
+        ))
+    }
+
     fn advance(&mut self) {
         self.position += 1;
     }
 }
 
+fn assign(target: &ASTNode, value: ASTNode, span: &Span) -> ASTNode {
+    ASTNode::Assign {
+        target: Box::new(target.clone()),
+        value: Box::new(value),
+        span: span.clone(),
+    }
+}
+
+fn binary(left: Box<ASTNode>, op: BinaryOperator, right: ASTNode) -> ASTNode {
+    let span = left.span().clone();
+    ASTNode::BinOp {
+        left,
+        op,
+        right: Box::new(right),
+        span,
+    }
+}
+
+fn temporary_name(index: usize) -> String {
+    format!("await#{}", index)
+}
+
+/// Whether `expr` is a literal, a name or an attribute of one, which read
+/// the same before and after an `await` unless the awaited task rebinds
+/// them.
+fn is_reference(expr: &ASTNode) -> bool {
+    match expr {
+        ASTNode::Str { .. }
+        | ASTNode::Int { .. }
+        | ASTNode::Float { .. }
+        | ASTNode::Bool { .. }
+        | ASTNode::Name { .. } => true,
+        ASTNode::Attribute { value, .. } => is_reference(value),
+        _ => false,
+    }
+}
+
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+fn binary_operator(token: &Token) -> Option<BinaryOperator> {
+    match token {
+        Token::Plus => Some(BinaryOperator::Add),
+        Token::Minus => Some(BinaryOperator::Subtract),
+        Token::Multiply => Some(BinaryOperator::Multiply),
+        Token::Divide => Some(BinaryOperator::Divide),
+        Token::FloorDivide => Some(BinaryOperator::FloorDivide),
+        Token::Modulo => Some(BinaryOperator::Modulo),
+        Token::Power => Some(BinaryOperator::Power),
+        Token::Equals => Some(BinaryOperator::Equal),
+        Token::NotEquals => Some(BinaryOperator::NotEqual),
+        Token::LessThan => Some(BinaryOperator::Less),
+        Token::GreaterThan => Some(BinaryOperator::Greater),
+        Token::LessThanOrEqual => Some(BinaryOperator::LessEqual),
+        Token::GreaterThanOrEqual => Some(BinaryOperator::GreaterEqual),
+        Token::Is => Some(BinaryOperator::Is),
+        Token::In => Some(BinaryOperator::In),
+        Token::And => Some(BinaryOperator::And),
+        Token::Or => Some(BinaryOperator::Or),
+        _ => None,
+    }
+}
@@ -13,35 +13,147 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::interpreter::interpreter::Value;
-use std::collections::HashMap;
+use crate::error::error::{BellronosError, ErrorCode};
+use crate::interop::interop::LanguageInterop;
+use crate::interpreter::interpreter::{
+    check_hashable, resolve_index, runtime_error, BellronosInterpreter, ControlFlow, NativeFn,
+    Task, Value,
+};
+use crate::lexer::lexer::Span;
+use crate::type_system::type_system::Type;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::rc::Rc;
+use std::time::Duration;
 
 pub struct StandardLibrary {
+    /// Functions available everywhere without an import, such as `range`.
+    builtins: HashMap<String, Value>,
     modules: HashMap<String, HashMap<String, Value>>,
+    /// Methods of built-in types by type name. They take the receiver as
+    /// their first argument.
+    methods: HashMap<&'static str, HashMap<String, Value>>,
 }
 
 impl StandardLibrary {
     pub fn new() -> Self {
         let mut stdlib = StandardLibrary {
+            builtins: HashMap::new(),
             modules: HashMap::new(),
+            methods: HashMap::new(),
         };
+        stdlib.init_builtins();
         stdlib.init_math();
         stdlib.init_io();
         stdlib.init_string();
+        stdlib.init_fs();
+        stdlib.init_async();
+        stdlib.init_list_methods();
+        stdlib.init_dict_methods();
+        stdlib.init_set_methods();
+        stdlib.init_file_methods();
+        stdlib.init_generator_methods();
         stdlib
     }
 
+    fn init_builtins(&mut self) {
+        let optional_int = || Type::Optional(Box::new(Type::Int));
+        self.builtins.insert(
+            "range".to_string(),
+            Value::native(
+                "range",
+                vec![Type::Int, optional_int(), optional_int()],
+                Type::Range,
+                builtin_range,
+            ),
+        );
+        self.builtins.insert(
+            "len".to_string(),
+            Value::native("len", vec![Type::Any], Type::Int, builtin_len),
+        );
+        self.builtins.insert(
+            "next".to_string(),
+            Value::intrinsic("next", vec![Type::Any], Type::Any, builtin_next),
+        );
+        self.builtins.insert(
+            "open".to_string(),
+            Value::native(
+                "open",
+                vec![Type::String, Type::Optional(Box::new(Type::String))],
+                Type::File,
+                fs_open,
+            ),
+        );
+        let list = || Type::List(Box::new(Type::Any));
+        let optional_any = || Type::Optional(Box::new(Type::Any));
+        self.builtins.insert(
+            "list".to_string(),
+            Value::intrinsic("list", vec![optional_any()], list(), builtin_list),
+        );
+        self.builtins.insert(
+            "tuple".to_string(),
+            Value::intrinsic("tuple", vec![optional_any()], Type::Any, builtin_tuple),
+        );
+        self.builtins.insert(
+            "set".to_string(),
+            Value::intrinsic(
+                "set",
+                vec![optional_any()],
+                Type::Set(Box::new(Type::Any)),
+                builtin_set,
+            ),
+        );
+        self.builtins.insert(
+            "map".to_string(),
+            Value::intrinsic("map", vec![Type::Callable, Type::Any], list(), builtin_map),
+        );
+        self.builtins.insert(
+            "filter".to_string(),
+            Value::intrinsic(
+                "filter",
+                vec![Type::Callable, Type::Any],
+                list(),
+                builtin_filter,
+            ),
+        );
+        self.builtins.insert(
+            "reduce".to_string(),
+            Value::intrinsic(
+                "reduce",
+                vec![
+                    Type::Callable,
+                    Type::Any,
+                    Type::Optional(Box::new(Type::Any)),
+                ],
+                Type::Any,
+                builtin_reduce,
+            ),
+        );
+        let interop: [(&str, NativeFn); 6] = [
+            ("execute_c", execute_c),
+            ("execute_python", execute_python),
+            ("execute_javascript", execute_javascript),
+            ("execute_java", execute_java),
+            ("execute_rust", execute_rust),
+            ("execute_swift", execute_swift),
+        ];
+        for (name, function) in interop {
+            self.builtins.insert(
+                name.to_string(),
+                Value::native(name, vec![Type::String], Type::String, function),
+            );
+        }
+    }
+
     fn init_math(&mut self) {
         let mut math = HashMap::new();
         math.insert("pi".to_string(), Value::Float(std::f64::consts::PI));
         math.insert("e".to_string(), Value::Float(std::f64::consts::E));
         math.insert(
             "sqrt".to_string(),
-            Value::Function(
-                vec!["x".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
-            ),
+            Value::native("sqrt", vec![Type::Float], Type::Float, math_sqrt),
         );
         self.modules.insert("math".to_string(), math);
     }
@@ -50,19 +162,11 @@ impl StandardLibrary {
         let mut io = HashMap::new();
         io.insert(
             "print".to_string(),
-            Value::Function(
-                vec!["args".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
-            ),
+            Value::native("print", vec![Type::Any], Type::None, io_print),
         );
         io.insert(
             "input".to_string(),
-            Value::Function(
-                vec!["prompt".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
-            ),
+            Value::native("input", vec![Type::String], Type::String, io_input),
         );
         self.modules.insert("io".to_string(), io);
     }
@@ -71,32 +175,1062 @@ impl StandardLibrary {
         let mut string = HashMap::new();
         string.insert(
             "length".to_string(),
-            Value::Function(
-                vec!["s".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
-            ),
+            Value::native("length", vec![Type::String], Type::Int, string_length),
         );
         string.insert(
             "to_upper".to_string(),
-            Value::Function(
-                vec!["s".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
+            Value::native(
+                "to_upper",
+                vec![Type::String],
+                Type::String,
+                string_to_upper,
             ),
         );
         string.insert(
             "to_lower".to_string(),
-            Value::Function(
-                vec!["s".to_string()],
-                vec![],
-                std::rc::Rc::new(std::cell::RefCell::new(HashMap::new())),
+            Value::native(
+                "to_lower",
+                vec![Type::String],
+                Type::String,
+                string_to_lower,
             ),
         );
+        string.insert(
+            "to_string".to_string(),
+            Value::native("to_string", vec![Type::Any], Type::String, string_to_string),
+        );
         self.modules.insert("string".to_string(), string);
     }
 
+    fn init_fs(&mut self) {
+        let mut fs = HashMap::new();
+        fs.insert(
+            "open".to_string(),
+            Value::native(
+                "open",
+                vec![Type::String, Type::Optional(Box::new(Type::String))],
+                Type::File,
+                fs_open,
+            ),
+        );
+        self.modules.insert("fs".to_string(), fs);
+    }
+
+    fn init_async(&mut self) {
+        let task = |result: Type| Type::Task(Box::new(result));
+        let mut tasks = HashMap::new();
+        tasks.insert(
+            "sleep".to_string(),
+            Value::native("sleep", vec![Type::Float], task(Type::None), async_sleep),
+        );
+        tasks.insert(
+            "gather".to_string(),
+            Value::native(
+                "gather",
+                vec![Type::List(Box::new(task(Type::Any)))],
+                task(Type::List(Box::new(Type::Any))),
+                async_gather,
+            ),
+        );
+        tasks.insert(
+            "timeout".to_string(),
+            Value::native(
+                "timeout",
+                vec![task(Type::Any), Type::Float],
+                task(Type::Any),
+                async_timeout,
+            ),
+        );
+        tasks.insert(
+            "spawn".to_string(),
+            Value::intrinsic("spawn", vec![task(Type::Any)], task(Type::Any), async_spawn),
+        );
+        tasks.insert(
+            "run".to_string(),
+            Value::intrinsic("run", vec![task(Type::Any)], Type::Any, async_run),
+        );
+        self.modules.insert("async".to_string(), tasks);
+    }
+
+    /// In the signatures `Any` stands for the list's element type.
+    fn init_list_methods(&mut self) {
+        let list = || Type::List(Box::new(Type::Any));
+        let mut methods = HashMap::new();
+        methods.insert(
+            "append".to_string(),
+            Value::native("append", vec![list(), Type::Any], Type::None, list_append),
+        );
+        methods.insert(
+            "extend".to_string(),
+            Value::native("extend", vec![list(), list()], Type::None, list_extend),
+        );
+        methods.insert(
+            "insert".to_string(),
+            Value::native(
+                "insert",
+                vec![list(), Type::Int, Type::Any],
+                Type::None,
+                list_insert,
+            ),
+        );
+        methods.insert(
+            "remove".to_string(),
+            Value::native("remove", vec![list(), Type::Any], Type::None, list_remove),
+        );
+        methods.insert(
+            "pop".to_string(),
+            Value::native(
+                "pop",
+                vec![list(), Type::Optional(Box::new(Type::Int))],
+                Type::Any,
+                list_pop,
+            ),
+        );
+        methods.insert(
+            "index".to_string(),
+            Value::native("index", vec![list(), Type::Any], Type::Int, list_index),
+        );
+        methods.insert(
+            "count".to_string(),
+            Value::native("count", vec![list(), Type::Any], Type::Int, list_count),
+        );
+        methods.insert(
+            "reverse".to_string(),
+            Value::native("reverse", vec![list()], Type::None, list_reverse),
+        );
+        methods.insert(
+            "clear".to_string(),
+            Value::native("clear", vec![list()], Type::None, list_clear),
+        );
+        methods.insert(
+            "copy".to_string(),
+            Value::native("copy", vec![list()], list(), list_copy),
+        );
+        self.methods.insert("list", methods);
+    }
+
+    /// In the signatures `Any` stands for the dict's value type. Keys are
+    /// always strings.
+    fn init_dict_methods(&mut self) {
+        let dict = || Type::Dict(Box::new(Type::String), Box::new(Type::Any));
+        let mut methods = HashMap::new();
+        methods.insert(
+            "keys".to_string(),
+            Value::native(
+                "keys",
+                vec![dict()],
+                Type::List(Box::new(Type::String)),
+                dict_keys,
+            ),
+        );
+        methods.insert(
+            "values".to_string(),
+            Value::native(
+                "values",
+                vec![dict()],
+                Type::List(Box::new(Type::Any)),
+                dict_values,
+            ),
+        );
+        methods.insert(
+            "items".to_string(),
+            Value::native(
+                "items",
+                vec![dict()],
+                Type::List(Box::new(Type::Tuple(vec![Type::String, Type::Any]))),
+                dict_items,
+            ),
+        );
+        methods.insert(
+            "get".to_string(),
+            Value::native(
+                "get",
+                vec![dict(), Type::String, Type::Optional(Box::new(Type::Any))],
+                Type::Any,
+                dict_get,
+            ),
+        );
+        methods.insert(
+            "pop".to_string(),
+            Value::native("pop", vec![dict(), Type::String], Type::Any, dict_pop),
+        );
+        methods.insert(
+            "update".to_string(),
+            Value::native("update", vec![dict(), dict()], Type::None, dict_update),
+        );
+        methods.insert(
+            "clear".to_string(),
+            Value::native("clear", vec![dict()], Type::None, dict_clear),
+        );
+        methods.insert(
+            "copy".to_string(),
+            Value::native("copy", vec![dict()], dict(), dict_copy),
+        );
+        self.methods.insert("dict", methods);
+    }
+
+    fn init_set_methods(&mut self) {
+        let set = || Type::Set(Box::new(Type::Any));
+        let mut methods = HashMap::new();
+        methods.insert(
+            "union".to_string(),
+            Value::native("union", vec![set(), set()], set(), set_union),
+        );
+        methods.insert(
+            "intersection".to_string(),
+            Value::native("intersection", vec![set(), set()], set(), set_intersection),
+        );
+        methods.insert(
+            "difference".to_string(),
+            Value::native("difference", vec![set(), set()], set(), set_difference),
+        );
+        methods.insert(
+            "add".to_string(),
+            Value::native("add", vec![set(), Type::Any], Type::None, set_add),
+        );
+        self.methods.insert("set", methods);
+    }
+
+    fn init_file_methods(&mut self) {
+        let mut methods = HashMap::new();
+        methods.insert(
+            "read".to_string(),
+            Value::native("read", vec![Type::File], Type::String, file_read),
+        );
+        methods.insert(
+            "write".to_string(),
+            Value::native(
+                "write",
+                vec![Type::File, Type::String],
+                Type::None,
+                file_write,
+            ),
+        );
+        methods.insert(
+            "close".to_string(),
+            Value::native("close", vec![Type::File], Type::None, file_close),
+        );
+        self.methods.insert("file", methods);
+    }
+
+    fn init_generator_methods(&mut self) {
+        let mut methods = HashMap::new();
+        methods.insert(
+            "send".to_string(),
+            Value::intrinsic(
+                "send",
+                vec![Type::Generator(Box::new(Type::Any)), Type::Any],
+                Type::Any,
+                generator_send,
+            ),
+        );
+        self.methods.insert("generator", methods);
+    }
+
+    pub fn builtins(&self) -> &HashMap<String, Value> {
+        &self.builtins
+    }
+
     pub fn get_module(&self, name: &str) -> Option<&HashMap<String, Value>> {
         self.modules.get(name)
     }
+
+    pub fn get_method(&self, type_name: &str, name: &str) -> Option<&Value> {
+        self.methods.get(type_name)?.get(name)
+    }
+
+    /// The static type of every builtin, for the type checker.
+    pub fn builtin_types(&self) -> HashMap<String, Type> {
+        self.builtins
+            .iter()
+            .map(|(name, value)| (name.clone(), value_type(value)))
+            .collect()
+    }
+
+    /// The static type of every member of every module, for the type
+    /// checker.
+    pub fn module_types(&self) -> HashMap<String, HashMap<String, Type>> {
+        self.modules
+            .iter()
+            .map(|(name, members)| {
+                let types = members
+                    .iter()
+                    .map(|(member, value)| (member.clone(), value_type(value)))
+                    .collect();
+                (name.clone(), types)
+            })
+            .collect()
+    }
+
+    /// The signatures of the methods of each built-in type, receiver
+    /// included, for the type checker.
+    pub fn method_types(&self) -> HashMap<String, HashMap<String, Type>> {
+        self.methods
+            .iter()
+            .map(|(type_name, methods)| {
+                let types = methods
+                    .iter()
+                    .map(|(method, value)| (method.clone(), value_type(value)))
+                    .collect();
+                (type_name.to_string(), types)
+            })
+            .collect()
+    }
+}
+
+fn value_type(value: &Value) -> Type {
+    match value {
+        Value::Int(_) => Type::Int,
+        Value::Float(_) => Type::Float,
+        Value::String(_) => Type::String,
+        Value::Bool(_) => Type::Bool,
+        Value::NativeFunction(native) => native.signature(),
+        _ => Type::Any,
+    }
+}
+
+fn math_sqrt(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let x = match &args[0] {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        other => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("sqrt expects a number, not {:?}", other),
+            ))
+        }
+    };
+    if x < 0.0 {
+        return Err(runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            format!("sqrt of negative number {}", x),
+        ));
+    }
+    Ok(Value::Float(x.sqrt()))
+}
+
+fn io_print(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", args[0])?;
+    Ok(Value::None)
+}
+
+/// Shows the prompt, then reads one line from stdin without its line
+/// ending. At end of input it returns an empty string.
+fn io_input(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{}", args[0])?;
+    stdout.flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Value::String(line))
+}
+
+fn string_arg<'a>(name: &str, args: &'a [Value], span: &Span) -> Result<&'a str, BellronosError> {
+    match &args[0] {
+        Value::String(s) => Ok(s),
+        other => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{} expects a string, not {:?}", name, other),
+        )),
+    }
+}
+
+fn string_length(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let s = string_arg("length", args, span)?;
+    Ok(Value::Int(s.chars().count() as i64))
+}
+
+fn string_to_upper(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    Ok(Value::String(
+        string_arg("to_upper", args, span)?.to_uppercase(),
+    ))
+}
+
+fn string_to_lower(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    Ok(Value::String(
+        string_arg("to_lower", args, span)?.to_lowercase(),
+    ))
+}
+
+fn string_to_string(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    Ok(Value::String(args[0].to_string()))
+}
+
+/// `len(collection)`: the number of characters, elements or entries.
+fn builtin_len(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let len = match &args[0] {
+        Value::String(s) => s.chars().count(),
+        Value::List(items) => items.borrow().len(),
+        Value::Tuple(items) => items.len(),
+        Value::Dict(entries) => entries.borrow().len(),
+        Value::Set(elements) => elements.borrow().len(),
+        Value::Range(start, stop, step) => {
+            let len = (stop - start + step - step.signum()) / step;
+            len.max(0) as usize
+        }
+        other => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("{} has no length", other.type_name()),
+            ))
+        }
+    };
+    Ok(Value::Int(len as i64))
+}
+
+fn list_receiver(args: &[Value]) -> &Rc<RefCell<Vec<Value>>> {
+    let Value::List(items) = &args[0] else {
+        unreachable!("list methods are only bound to lists");
+    };
+    items
+}
+
+fn list_append(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    list_receiver(args).borrow_mut().push(args[1].clone());
+    Ok(Value::None)
+}
+
+fn list_extend(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let Value::List(other) = &args[1] else {
+        return Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("extend expects a list, not {:?}", args[1]),
+        ));
+    };
+    // Copied first, since a list may be extended with itself
+    let other = other.borrow().clone();
+    list_receiver(args).borrow_mut().extend(other);
+    Ok(Value::None)
+}
+
+/// `list.insert(index, value)`. An index past either end inserts there.
+fn list_insert(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let mut items = list_receiver(args).borrow_mut();
+    let len = items.len() as i64;
+    let index = int_arg("insert", &args[1], span)?;
+    let index = if index < 0 { index + len } else { index };
+    items.insert(index.clamp(0, len) as usize, args[2].clone());
+    Ok(Value::None)
+}
+
+/// Removes the first element equal to the argument.
+fn list_remove(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let mut items = list_receiver(args).borrow_mut();
+    match items.iter().position(|item| *item == args[1]) {
+        Some(position) => {
+            items.remove(position);
+            Ok(Value::None)
+        }
+        None => Err(runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            format!("list.remove: {:?} is not in the list", args[1]),
+        )),
+    }
+}
+
+/// `list.pop()` removes and returns the last element, `list.pop(index)`
+/// the one at `index`.
+fn list_pop(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let mut items = list_receiver(args).borrow_mut();
+    let index = match args.get(1) {
+        Some(index) => int_arg("pop", index, span)?,
+        None => -1,
+    };
+    match resolve_index(index, items.len()) {
+        Some(position) => Ok(items.remove(position)),
+        None if items.is_empty() => Err(runtime_error(
+            span,
+            ErrorCode::IndexOutOfRange,
+            "pop from an empty list".to_string(),
+        )),
+        None => Err(runtime_error(
+            span,
+            ErrorCode::IndexOutOfRange,
+            format!(
+                "pop index {} out of range for length {}",
+                index,
+                items.len()
+            ),
+        )),
+    }
+}
+
+/// The position of the first element equal to the argument.
+fn list_index(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let items = list_receiver(args).borrow();
+    match items.iter().position(|item| *item == args[1]) {
+        Some(position) => Ok(Value::Int(position as i64)),
+        None => Err(runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            format!("list.index: {:?} is not in the list", args[1]),
+        )),
+    }
+}
+
+fn list_count(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let items = list_receiver(args).borrow();
+    let count = items.iter().filter(|item| **item == args[1]).count();
+    Ok(Value::Int(count as i64))
+}
+
+fn list_reverse(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    list_receiver(args).borrow_mut().reverse();
+    Ok(Value::None)
+}
+
+fn list_clear(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    list_receiver(args).borrow_mut().clear();
+    Ok(Value::None)
+}
+
+/// A shallow copy: the new list shares its elements with the old one.
+fn list_copy(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    Ok(Value::list(list_receiver(args).borrow().clone()))
+}
+
+fn dict_receiver(args: &[Value]) -> &Rc<RefCell<HashMap<String, Value>>> {
+    let Value::Dict(entries) = &args[0] else {
+        unreachable!("dict methods are only bound to dicts");
+    };
+    entries
+}
+
+fn dict_keys(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let entries = dict_receiver(args).borrow();
+    Ok(Value::list(
+        entries.keys().cloned().map(Value::String).collect(),
+    ))
+}
+
+fn dict_values(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let entries = dict_receiver(args).borrow();
+    Ok(Value::list(entries.values().cloned().collect()))
+}
+
+/// The entries as `(key, value)` tuples.
+fn dict_items(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    let entries = dict_receiver(args).borrow();
+    Ok(Value::list(
+        entries
+            .iter()
+            .map(|(key, value)| Value::Tuple(Rc::from([Value::String(key.clone()), value.clone()])))
+            .collect(),
+    ))
+}
+
+/// `dict.get(key, default)`: the value for `key`, or `default` (`None`
+/// when left out) if there is none.
+fn dict_get(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let key = string_arg("get", &args[1..], span)?;
+    let entries = dict_receiver(args).borrow();
+    Ok(entries
+        .get(key)
+        .cloned()
+        .unwrap_or_else(|| args.get(2).cloned().unwrap_or(Value::None)))
+}
+
+/// Removes the entry for `key` and returns its value.
+fn dict_pop(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let key = string_arg("pop", &args[1..], span)?;
+    dict_receiver(args).borrow_mut().remove(key).ok_or_else(|| {
+        runtime_error(
+            span,
+            ErrorCode::KeyNotFound,
+            format!("Key {:?} not found", key),
+        )
+    })
+}
+
+fn dict_update(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let Value::Dict(other) = &args[1] else {
+        return Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("update expects a dict, not {:?}", args[1]),
+        ));
+    };
+    let other = other.borrow().clone();
+    dict_receiver(args).borrow_mut().extend(other);
+    Ok(Value::None)
+}
+
+fn dict_clear(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    dict_receiver(args).borrow_mut().clear();
+    Ok(Value::None)
+}
+
+/// A shallow copy: the new dict shares its values with the old one.
+fn dict_copy(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    Ok(Value::dict(dict_receiver(args).borrow().clone()))
+}
+
+/// The elements of a set method's receiver and of its set argument.
+fn set_operands(
+    name: &str,
+    args: &[Value],
+    span: &Span,
+) -> Result<(HashSet<Value>, HashSet<Value>), BellronosError> {
+    match (&args[0], &args[1]) {
+        (Value::Set(receiver), Value::Set(other)) => {
+            Ok((receiver.borrow().clone(), other.borrow().clone()))
+        }
+        (_, other) => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{} expects a set, not {:?}", name, other),
+        )),
+    }
+}
+
+fn set_union(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let (a, b) = set_operands("union", args, span)?;
+    Ok(Value::set(a.union(&b).cloned()))
+}
+
+fn set_intersection(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let (a, b) = set_operands("intersection", args, span)?;
+    Ok(Value::set(a.intersection(&b).cloned()))
+}
+
+fn set_difference(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let (a, b) = set_operands("difference", args, span)?;
+    Ok(Value::set(a.difference(&b).cloned()))
+}
+
+fn set_add(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let Value::Set(elements) = &args[0] else {
+        unreachable!("set methods are only bound to sets");
+    };
+    check_hashable(&args[1], span)?;
+    elements.borrow_mut().insert(args[1].clone());
+    Ok(Value::None)
+}
+
+fn int_arg(name: &str, value: &Value, span: &Span) -> Result<i64, BellronosError> {
+    match value {
+        Value::Int(i) => Ok(*i),
+        other => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{} expects an integer, not {:?}", name, other),
+        )),
+    }
+}
+
+/// `range(stop)`, `range(start, stop)` or `range(start, stop, step)`. The
+/// numbers are produced one at a time as the range is iterated.
+fn builtin_range(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let mut bounds = Vec::new();
+    for arg in args {
+        bounds.push(int_arg("range", arg, span)?);
+    }
+    let (start, stop, step) = match bounds[..] {
+        [stop] => (0, stop, 1),
+        [start, stop] => (start, stop, 1),
+        [start, stop, step] => (start, stop, step),
+        _ => unreachable!("the interpreter checks the argument count"),
+    };
+    if step == 0 {
+        return Err(runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            "range step cannot be zero".to_string(),
+        ));
+    }
+    Ok(Value::Range(start, stop, step))
+}
+
+/// `next(iterator)`: the next value of a generator or of an instance with a
+/// `__next__` method.
+fn builtin_next(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    interpreter.send(&args[0], Value::None, span)
+}
+
+/// `list(iterable)`: a new list of the items, or an empty list.
+fn builtin_list(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    Ok(Value::list(collect_items(interpreter, args, span)?))
+}
+
+/// `tuple(iterable)`: a tuple of the items, or an empty tuple.
+fn builtin_tuple(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    Ok(Value::Tuple(collect_items(interpreter, args, span)?.into()))
+}
+
+/// `set(iterable)`: a set of the items, which must be hashable, or an
+/// empty set.
+fn builtin_set(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let items = collect_items(interpreter, args, span)?;
+    for item in &items {
+        check_hashable(item, span)?;
+    }
+    Ok(Value::set(items))
+}
+
+/// The items of the optional iterable argument of a collection builtin.
+fn collect_items(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Vec<Value>, ControlFlow> {
+    let mut items = Vec::new();
+    if let Some(iterable) = args.first() {
+        interpreter.for_each(iterable.clone(), span, |_, item| {
+            items.push(item);
+            Ok(())
+        })?;
+    }
+    Ok(items)
+}
+
+/// `map(function, iterable)`: the list of `function` applied to each item.
+fn builtin_map(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let mut results = Vec::new();
+    interpreter.for_each(args[1].clone(), span, |interpreter, item| {
+        results.push(interpreter.call_value(args[0].clone(), vec![item], span)?);
+        Ok(())
+    })?;
+    Ok(Value::list(results))
+}
+
+/// `filter(function, iterable)`: the list of items for which `function`
+/// returns a truthy value.
+fn builtin_filter(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let mut kept = Vec::new();
+    interpreter.for_each(args[1].clone(), span, |interpreter, item| {
+        if interpreter
+            .call_value(args[0].clone(), vec![item.clone()], span)?
+            .is_truthy()
+        {
+            kept.push(item);
+        }
+        Ok(())
+    })?;
+    Ok(Value::list(kept))
+}
+
+/// `reduce(function, iterable, initial)`: folds the items from the left
+/// with a two-argument `function`, starting from `initial` or else from
+/// the first item.
+fn builtin_reduce(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let mut accumulator = args.get(2).cloned();
+    interpreter.for_each(args[1].clone(), span, |interpreter, item| {
+        accumulator = Some(match accumulator.take() {
+            Some(acc) => interpreter.call_value(args[0].clone(), vec![acc, item], span)?,
+            None => item,
+        });
+        Ok(())
+    })?;
+    accumulator.ok_or_else(|| {
+        ControlFlow::from(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            "reduce of an empty iterable with no initial value".to_string(),
+        ))
+    })
+}
+
+/// `generator.send(value)`: resumes the generator with `value` as the
+/// result of the `yield` it is suspended at.
+fn generator_send(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    interpreter.send(&args[0], args[1].clone(), span)
+}
+
+fn task_arg(name: &str, value: &Value, span: &Span) -> Result<Rc<Task>, BellronosError> {
+    match value {
+        Value::Task(task) => Ok(Rc::clone(task)),
+        other => Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("{} expects a task, not {:?}", name, other),
+        )),
+    }
+}
+
+fn seconds_arg(name: &str, value: &Value, span: &Span) -> Result<Duration, BellronosError> {
+    let seconds = match value {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        other => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("{} expects a number of seconds, not {:?}", name, other),
+            ))
+        }
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        runtime_error(
+            span,
+            ErrorCode::InvalidValue,
+            format!(
+                "{} expects a non-negative number of seconds, not {}",
+                name, seconds
+            ),
+        )
+    })
+}
+
+/// `async.sleep(seconds)`: a task that finishes after the delay.
+fn async_sleep(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    Ok(Task::sleep(
+        seconds_arg("async.sleep", &args[0], span)?,
+        span,
+    ))
+}
+
+/// `async.gather(tasks)`: runs the tasks concurrently. Gives the list of
+/// their results, or raises the first exception one of them raises.
+fn async_gather(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let Value::List(items) = &args[0] else {
+        return Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("async.gather expects a list of tasks, not {:?}", args[0]),
+        ));
+    };
+    let tasks = items
+        .borrow()
+        .iter()
+        .map(|item| task_arg("async.gather", item, span))
+        .collect::<Result<_, _>>()?;
+    Ok(Task::gather(tasks, span))
+}
+
+/// `async.timeout(task, seconds)`: the task's result, or a `TimeoutError`
+/// that also cancels the task once the delay runs out first.
+fn async_timeout(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let task = task_arg("async.timeout", &args[0], span)?;
+    let duration = seconds_arg("async.timeout", &args[1], span)?;
+    Ok(Task::timeout(task, duration, span))
+}
+
+/// `async.spawn(task)`: starts the task without waiting for it. It runs
+/// whenever the running task awaits.
+fn async_spawn(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let task = task_arg("async.spawn", &args[0], span)?;
+    interpreter.start_task(&task);
+    Ok(Value::Task(task))
+}
+
+/// `async.run(task)`: the task's result, once it has run to completion.
+fn async_run(
+    interpreter: &mut BellronosInterpreter,
+    args: &[Value],
+    span: &Span,
+) -> Result<Value, ControlFlow> {
+    let task = task_arg("async.run", &args[0], span)?;
+    interpreter.run_task(&task, span)
+}
+
+/// How an open file is accessed. Reads are buffered so that iterating a
+/// file takes one line at a time.
+enum FileState {
+    Reading(BufReader<File>),
+    Writing(File),
+    Closed,
+}
+
+/// A file opened by `fs.open`.
+pub struct FileHandle {
+    pub path: String,
+    state: RefCell<FileState>,
+}
+
+impl FileHandle {
+    /// The next line, line ending included, or `None` at the end of the
+    /// file.
+    pub fn read_line(&self, span: &Span) -> Result<Option<String>, BellronosError> {
+        let mut state = self.state.borrow_mut();
+        let reader = reading(&mut state, &self.path, span)?;
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+fn reading<'a>(
+    state: &'a mut FileState,
+    path: &str,
+    span: &Span,
+) -> Result<&'a mut BufReader<File>, BellronosError> {
+    match state {
+        FileState::Reading(reader) => Ok(reader),
+        FileState::Writing(_) => Err(runtime_error(
+            span,
+            ErrorCode::InvalidOperation,
+            format!("File '{}' is not open for reading", path),
+        )),
+        FileState::Closed => Err(closed_file(path, span)),
+    }
+}
+
+fn closed_file(path: &str, span: &Span) -> BellronosError {
+    runtime_error(
+        span,
+        ErrorCode::InvalidValue,
+        format!("I/O operation on closed file '{}'", path),
+    )
+}
+
+fn file_arg(args: &[Value]) -> &FileHandle {
+    let Value::File(file) = &args[0] else {
+        unreachable!("file methods are only bound to files");
+    };
+    file
+}
+
+/// Runs `code` as a program in another language, returning what it
+/// printed.
+fn run_interop(language: &str, args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let code = string_arg(&format!("execute_{}", language), args, span)?;
+    LanguageInterop::new().execute(language, code)
+}
+
+fn execute_c(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("c", args, span)
+}
+
+fn execute_python(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("python", args, span)
+}
+
+fn execute_javascript(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("javascript", args, span)
+}
+
+fn execute_java(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("java", args, span)
+}
+
+fn execute_rust(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("rust", args, span)
+}
+
+fn execute_swift(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    run_interop("swift", args, span)
+}
+
+/// `fs.open(path, mode)` with mode `"r"` (the default), `"w"` or `"a"`.
+fn fs_open(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let path = string_arg("open", args, span)?;
+    let mode = match args.get(1) {
+        Some(Value::String(mode)) => mode.as_str(),
+        Some(other) => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("open expects a string mode, not {:?}", other),
+            ))
+        }
+        None => "r",
+    };
+    let named = |err: io::Error| io::Error::new(err.kind(), format!("{}: {}", path, err));
+    let state = match mode {
+        "r" => FileState::Reading(BufReader::new(File::open(path).map_err(named)?)),
+        "w" => FileState::Writing(File::create(path).map_err(named)?),
+        "a" => FileState::Writing(
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .map_err(named)?,
+        ),
+        other => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::InvalidValue,
+                format!("Invalid file mode '{}'", other),
+            ))
+        }
+    };
+    Ok(Value::File(Rc::new(FileHandle {
+        path: path.to_string(),
+        state: RefCell::new(state),
+    })))
+}
+
+/// Reads everything from the current position to the end of the file.
+fn file_read(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let file = file_arg(args);
+    let mut state = file.state.borrow_mut();
+    let mut content = String::new();
+    reading(&mut state, &file.path, span)?.read_to_string(&mut content)?;
+    Ok(Value::String(content))
+}
+
+fn file_write(args: &[Value], span: &Span) -> Result<Value, BellronosError> {
+    let file = file_arg(args);
+    let Value::String(text) = &args[1] else {
+        return Err(runtime_error(
+            span,
+            ErrorCode::TypeMismatch,
+            format!("write expects a string, not {:?}", args[1]),
+        ));
+    };
+    match &mut *file.state.borrow_mut() {
+        FileState::Writing(writer) => writer.write_all(text.as_bytes())?,
+        FileState::Reading(_) => {
+            return Err(runtime_error(
+                span,
+                ErrorCode::InvalidOperation,
+                format!("File '{}' is not open for writing", file.path),
+            ))
+        }
+        FileState::Closed => return Err(closed_file(&file.path, span)),
+    }
+    Ok(Value::None)
+}
+
+/// Closes the file. Closing it again does nothing.
+fn file_close(args: &[Value], _span: &Span) -> Result<Value, BellronosError> {
+    *file_arg(args).state.borrow_mut() = FileState::Closed;
+    Ok(Value::None)
 }
@@ -13,99 +13,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::ast::ast::ASTNode;
-use crate::error::error::BellronosError;
-use std::collections::HashMap;
-use std::str::FromStr;
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum InteropType {
-    CInt,
-    CFloat,
-    CString,
-    PyInt,
-    PyFloat,
-    PyString,
-    PyList,
-    PyDict,
-    JsNumber,
-    JsString,
-    JsBoolean,
-    JsArray,
-    JsObject,
-    Unknown,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum InteropLanguage {
-    C,
-    Python,
-    JavaScript,
-}
-
-impl FromStr for InteropLanguage {
-    type Err = BellronosError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "c" => Ok(InteropLanguage::C),
-            "python" => Ok(InteropLanguage::Python),
-            "javascript" | "js" => Ok(InteropLanguage::JavaScript),
-            _ => Err(BellronosError::Type(format!(
-                "Unknown interop language: {}",
-                s
-            ))),
-        }
-    }
-}
-
-impl InteropLanguage {
-    pub fn infer_type(&self, code: &str) -> Result<InteropType, BellronosError> {
-        match self {
-            InteropLanguage::C => {
-                if code.contains("int") {
-                    Ok(InteropType::CInt)
-                } else if code.contains("float") {
-                    Ok(InteropType::CFloat)
-                } else if code.contains("char*") {
-                    Ok(InteropType::CString)
-                } else {
-                    Ok(InteropType::Unknown)
-                }
-            }
-            InteropLanguage::Python => {
-                if code.contains("int(") {
-                    Ok(InteropType::PyInt)
-                } else if code.contains("float(") {
-                    Ok(InteropType::PyFloat)
-                } else if code.contains("str(") {
-                    Ok(InteropType::PyString)
-                } else if code.contains("list(") {
-                    Ok(InteropType::PyList)
-                } else if code.contains("dict(") {
-                    Ok(InteropType::PyDict)
-                } else {
-                    Ok(InteropType::Unknown)
-                }
-            }
-            InteropLanguage::JavaScript => {
-                if code.contains("Number(") {
-                    Ok(InteropType::JsNumber)
-                } else if code.contains("String(") {
-                    Ok(InteropType::JsString)
-                } else if code.contains("Boolean(") {
-                    Ok(InteropType::JsBoolean)
-                } else if code.contains("Array(") {
-                    Ok(InteropType::JsArray)
-                } else if code.contains("Object(") {
-                    Ok(InteropType::JsObject)
-                } else {
-                    Ok(InteropType::Unknown)
-                }
-            }
-        }
-    }
-}
+use crate::ast::ast::{ASTNode, Argument, BinaryOperator, Parameter, UnaryOperator};
+use crate::error::error::{BellronosError, Diagnostic, ErrorCode, BUILTIN_EXCEPTIONS};
+use crate::lexer::lexer::Span;
+use crate::standard_library::standard_library::StandardLibrary;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
@@ -114,41 +26,199 @@ pub enum Type {
     String,
     Bool,
     List(Box<Type>),
+    Tuple(Vec<Type>),
+    Set(Box<Type>),
     Dict(Box<Type>, Box<Type>),
-    Function(Vec<Type>, Box<Type>),
+    Function(Vec<ParamType>, Box<Type>),
     Class(String),
     Instance(String),
+    /// An imported standard library module.
+    Module(String),
+    /// The lazy integer sequence made by `range`.
+    Range,
+    /// A file opened with `fs.open`.
+    File,
+    /// The lazy sequence returned by a function containing `yield`, with
+    /// the type of the values it yields.
+    Generator(Box<Type>),
+    /// A call of an `async` function, or a timer or combinator from the
+    /// `async` module. Awaiting it gives the result type.
+    Task(Box<Type>),
+    /// A function whose signature is left open, as annotated with a bare
+    /// `function`.
+    Callable,
+    /// A trailing parameter of a builtin that a call may leave out. Its
+    /// function type has it as a `ParamKind::Optional` parameter.
+    Optional(Box<Type>),
     None,
     Any,
     Custom(String),
-    Interop(InteropType),
+}
+
+/// How a parameter takes the arguments of a call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    Required,
+    /// Has a default, so a call may leave it out.
+    Optional,
+    /// `*args`: the positional arguments left over, as a list.
+    Variadic,
+    /// `**kwargs`: the keyword arguments left over, as a dict.
+    KeywordVariadic,
+}
+
+/// A parameter of a function type. Those declared after `*args` can only
+/// be passed by keyword.
+#[derive(Clone, PartialEq)]
+pub struct ParamType {
+    /// Builtins leave their parameters unnamed, so they take no keywords.
+    pub name: Option<String>,
+    pub type_: Type,
+    pub kind: ParamKind,
+}
+
+impl ParamType {
+    /// An unnamed, required parameter.
+    pub fn positional(type_: Type) -> Self {
+        ParamType {
+            name: None,
+            type_,
+            kind: ParamKind::Required,
+        }
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        self.name.as_deref() == Some(name)
+    }
+
+    /// Whether a positional argument can fill it.
+    fn is_positional(&self) -> bool {
+        matches!(self.kind, ParamKind::Required | ParamKind::Optional)
+    }
+}
+
+/// Shown as in the definition, e.g. `count: Int = ...` or `*args: Any`.
+impl std::fmt::Debug for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParamKind::Variadic => write!(f, "*")?,
+            ParamKind::KeywordVariadic => write!(f, "**")?,
+            ParamKind::Required | ParamKind::Optional => {}
+        }
+        if let Some(name) = &self.name {
+            write!(f, "{}: ", name)?;
+        }
+        write!(f, "{:?}", self.type_)?;
+        if self.kind == ParamKind::Optional {
+            write!(f, " = ...")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the checker knows about a class: its bases, method signatures and
+/// the fields its methods assign through `self`.
+#[derive(Debug, Clone, Default)]
+pub struct ClassInfo {
+    pub bases: Vec<String>,
+    pub methods: HashMap<String, Type>,
+    pub fields: HashMap<String, Type>,
 }
 
 #[derive(Clone)]
 pub struct TypeChecker {
     type_env: HashMap<String, Type>,
-    class_env: HashMap<String, HashMap<String, Type>>,
+    class_env: HashMap<String, ClassInfo>,
+    /// Member types of the standard library modules.
+    modules: HashMap<String, HashMap<String, Type>>,
+    /// Method signatures of built-in types, keyed by type name.
+    methods: HashMap<String, HashMap<String, Type>>,
+    /// The type the enclosing generator function yields, or `None`
+    /// outside of one.
+    yield_type: Option<Type>,
+    /// Variables of the function being checked that shadow an enclosing
+    /// name and aren't assigned on every path yet. Reading one could
+    /// silently read the enclosing variable instead.
+    unassigned: HashSet<String>,
 }
 
 impl TypeChecker {
     pub fn new() -> Self {
-        TypeChecker {
+        let mut checker = TypeChecker {
             type_env: HashMap::new(),
             class_env: HashMap::new(),
+            modules: HashMap::new(),
+            methods: HashMap::new(),
+            yield_type: None,
+            unassigned: HashSet::new(),
+        };
+        let stdlib = StandardLibrary::new();
+        checker.modules = stdlib.module_types();
+        checker.methods = stdlib.method_types();
+        checker.type_env.extend(stdlib.builtin_types());
+        for (name, base) in BUILTIN_EXCEPTIONS {
+            checker
+                .type_env
+                .insert(name.to_string(), Type::Class(name.to_string()));
+            let mut info = ClassInfo {
+                bases: base.iter().map(|b| b.to_string()).collect(),
+                ..ClassInfo::default()
+            };
+            if base.is_none() {
+                info.methods.insert(
+                    "__init__".to_string(),
+                    Type::Function(
+                        vec![
+                            ParamType::positional(Type::Instance(name.to_string())),
+                            ParamType::positional(Type::Any),
+                        ],
+                        Box::new(Type::None),
+                    ),
+                );
+                info.fields.insert("message".to_string(), Type::Any);
+            }
+            checker.class_env.insert(name.to_string(), info);
         }
+        checker
     }
 
     pub fn check(&mut self, node: &ASTNode) -> Result<Type, BellronosError> {
         match node {
-            ASTNode::Module { body } => {
+            ASTNode::Module { body, .. } => {
+                self.declare_signatures(body);
                 for stmt in body {
                     self.check(stmt)?;
                 }
                 Ok(Type::None)
             }
-            ASTNode::Import { names } => {
+            ASTNode::Import { names, .. } => {
                 for name in names {
-                    self.type_env.insert(name.clone(), Type::Any);
+                    // Packages are not checked, so their members stay dynamic
+                    let module_type = if self.modules.contains_key(name) {
+                        Type::Module(name.clone())
+                    } else {
+                        Type::Any
+                    };
+                    self.type_env.insert(name.clone(), module_type);
+                }
+                Ok(Type::None)
+            }
+            ASTNode::Nonlocal { names, span } => {
+                for name in names {
+                    if !self.type_env.contains_key(name) {
+                        return Err(type_error(
+                            span,
+                            ErrorCode::UndefinedName,
+                            format!("No binding for nonlocal '{}' in an enclosing scope", name),
+                        ));
+                    }
+                    self.unassigned.remove(name);
+                }
+                Ok(Type::None)
+            }
+            ASTNode::Unbind { names, .. } => {
+                for name in names {
+                    self.type_env.remove(name);
                 }
                 Ok(Type::None)
             }
@@ -157,266 +227,1172 @@ impl TypeChecker {
                 args,
                 return_type,
                 body,
+                decorators,
+                span: def_span,
             } => {
-                let arg_types: Vec<Type> = args.iter().map(|(_, t)| t.clone()).collect();
-                let func_type = Type::Function(arg_types.clone(), Box::new(return_type.clone()));
-                self.type_env.insert(name.clone(), func_type);
-
-                let mut func_checker = self.clone();
-                for (arg_name, arg_type) in args {
-                    func_checker
-                        .type_env
-                        .insert(arg_name.clone(), arg_type.clone());
-                }
-
-                for stmt in body {
-                    let stmt_type = func_checker.check(stmt)?;
-                    if let ASTNode::Return { .. } = stmt {
-                        if stmt_type != *return_type {
-                            return Err(BellronosError::Type(format!(
-                                "Function {} return type mismatch: expected {:?}, found {:?}",
-                                name, return_type, stmt_type
-                            )));
-                        }
-                    }
-                }
+                let func_type = function_type(args, return_type, body, false);
+                let bound_type = self.decorated_type(decorators, func_type)?;
+                self.type_env.insert(name.clone(), bound_type);
+                self.check_function_body(name, args, return_type, body, def_span)?;
                 Ok(Type::None)
             }
-            ASTNode::ClassDef { name, methods } => {
-                let mut class_methods = HashMap::new();
+            ASTNode::ClassDef {
+                name,
+                bases,
+                methods,
+                decorators,
+                span,
+            } => {
+                for base in bases {
+                    if !matches!(self.type_env.get(base), Some(Type::Class(_))) {
+                        return Err(type_error(
+                            span,
+                            ErrorCode::UnknownClass,
+                            format!("Base class '{}' not found", base),
+                        ));
+                    }
+                }
+                let mut info = ClassInfo {
+                    bases: bases.clone(),
+                    ..ClassInfo::default()
+                };
                 for method in methods {
+                    let (method, is_async) = method.unwrap_async();
                     if let ASTNode::FunctionDef {
                         name: method_name,
                         args,
                         return_type,
+                        body,
+                        decorators,
                         ..
                     } = method
                     {
-                        let arg_types: Vec<Type> = args.iter().map(|(_, t)| t.clone()).collect();
-                        class_methods.insert(
+                        let method_type = function_type(args, return_type, body, is_async);
+                        info.methods.insert(
                             method_name.clone(),
-                            Type::Function(arg_types, Box::new(return_type.clone())),
+                            self.decorated_type(decorators, method_type)?,
                         );
                     }
                 }
-                self.class_env.insert(name.clone(), class_methods);
+                self.class_env.insert(name.clone(), info);
                 self.type_env
                     .insert(name.clone(), Type::Class(name.clone()));
+
+                for method in methods {
+                    if let ASTNode::FunctionDef {
+                        name: method_name,
+                        args,
+                        return_type,
+                        body,
+                        span: def_span,
+                        ..
+                    } = method.unwrap_async().0
+                    {
+                        // An unannotated receiver is an instance of the class
+                        let mut args = args.clone();
+                        if let Some(receiver) = args.first_mut() {
+                            if receiver.type_ == Type::Any {
+                                receiver.type_ = Type::Instance(name.clone());
+                            }
+                        }
+                        let mut method_checker = self.clone();
+                        if let Some(base) = bases.first() {
+                            method_checker
+                                .type_env
+                                .insert("super".to_string(), Type::Instance(base.clone()));
+                        }
+                        method_checker.check_function_body(
+                            method_name,
+                            &args,
+                            return_type,
+                            body,
+                            def_span,
+                        )?;
+                        // Keep the fields the method assigned
+                        self.class_env = method_checker.class_env;
+                    }
+                }
+                if !decorators.is_empty() {
+                    let bound_type = self.decorated_type(decorators, Type::Class(name.clone()))?;
+                    self.type_env.insert(name.clone(), bound_type);
+                }
                 Ok(Type::None)
             }
-            ASTNode::Assign { target, value } => {
-                let value_type = self.check(value)?;
-                self.type_env.insert(target.clone(), value_type);
+            ASTNode::Assign { target, value, .. } => {
+                let value_type = self.check_value(value)?;
+                self.assign_target(target, value_type, value.span())?;
                 Ok(Type::None)
             }
-            ASTNode::Expr { value } => self.check(value),
-            ASTNode::Call { func, args } => {
-                let func_type =
-                    self.type_env.get(func).cloned().ok_or_else(|| {
-                        BellronosError::Type(format!("Undefined function: {}", func))
-                    })?;
+            ASTNode::Expr { value, .. } => self.check_value(value),
+            ASTNode::Call { callee, args, span } => {
+                let func = match &**callee {
+                    ASTNode::Name { id, .. } => id.clone(),
+                    ASTNode::Attribute { attr, .. } => attr.clone(),
+                    _ => "expression".to_string(),
+                };
+                let func_type = match &**callee {
+                    ASTNode::Name { id, .. } => {
+                        self.type_env.get(id).cloned().ok_or_else(|| {
+                            type_error(
+                                span,
+                                ErrorCode::UndefinedName,
+                                format!("Undefined function: {}", id),
+                            )
+                        })?
+                    }
+                    _ => self.check(callee)?,
+                };
 
                 if let Type::Function(param_types, return_type) = func_type {
-                    if args.len() != param_types.len() {
-                        return Err(BellronosError::Type(format!(
-                            "Function {} expects {} arguments, but {} were given",
-                            func,
-                            param_types.len(),
-                            args.len()
-                        )));
-                    }
-                    for (arg, expected_type) in args.iter().zip(param_types.iter()) {
-                        let arg_type = self.check(arg)?;
-                        if !self.is_compatible(&arg_type, expected_type) {
-                            return Err(BellronosError::Type(format!(
-                                "Type mismatch: expected {:?}, found {:?}",
-                                expected_type, arg_type
-                            )));
+                    self.check_arguments(&format!("Function {}", func), &param_types, args, span)?;
+                    Ok(*return_type)
+                } else if let Type::Class(class_name) = func_type {
+                    match self.find_method(&class_name, "__init__") {
+                        Some(Type::Function(params, _)) => {
+                            // The constructor's receiver is the new instance
+                            let params = &params[1.min(params.len())..];
+                            self.check_arguments(
+                                &format!("Class {}", class_name),
+                                params,
+                                args,
+                                span,
+                            )?;
+                        }
+                        _ => {
+                            for arg in args {
+                                self.check(arg.value())?;
+                            }
                         }
                     }
-                    Ok(*return_type)
+                    Ok(Type::Instance(class_name))
+                } else if matches!(func_type, Type::Any | Type::Callable) {
+                    for arg in args {
+                        self.check(arg.value())?;
+                    }
+                    Ok(Type::Any)
                 } else {
-                    Err(BellronosError::Type(format!("{} is not a function", func)))
+                    Err(type_error(
+                        span,
+                        ErrorCode::NotCallable,
+                        format!("{} is not a function", func),
+                    ))
                 }
             }
             ASTNode::Str { .. } => Ok(Type::String),
-            ASTNode::Num { .. } => Ok(Type::Float),
+            ASTNode::Int { .. } => Ok(Type::Int),
+            ASTNode::Float { .. } => Ok(Type::Float),
             ASTNode::Bool { .. } => Ok(Type::Bool),
-            ASTNode::Name { id } => self
-                .type_env
-                .get(id)
-                .cloned()
-                .ok_or_else(|| BellronosError::Type(format!("Undefined variable: {}", id))),
-            ASTNode::BinOp { left, op, right } => {
+            ASTNode::Name { id, span } if self.unassigned.contains(id) => {
+                Err(BellronosError::Type(Box::new(
+                    Diagnostic::new(
+                        ErrorCode::UndefinedName,
+                        format!("Local variable '{}' is read before it is assigned", id),
+                    )
+                    .with_span(span.clone())
+                    .with_help(format!(
+                        "assigning '{}' in this function makes it local; declare `nonlocal {}` to update the enclosing variable",
+                        id, id
+                    )),
+                )))
+            }
+            ASTNode::Name { id, span } => self.type_env.get(id).cloned().ok_or_else(|| {
+                type_error(
+                    span,
+                    ErrorCode::UndefinedName,
+                    format!("Undefined variable: {}", id),
+                )
+            }),
+            ASTNode::BinOp {
+                left,
+                op,
+                right,
+                span,
+            } => {
                 let left_type = self.check(left)?;
                 let right_type = self.check(right)?;
-                self.check_binary_op(&left_type, op, &right_type)
+                self.check_binary_op(&left_type, *op, &right_type)
+                    .map_err(|message| type_error(span, ErrorCode::InvalidOperation, message))
+            }
+            ASTNode::UnaryOp { op, operand, span } => {
+                let operand_type = self.check(operand)?;
+                match (op, operand_type) {
+                    (UnaryOperator::Not, _) => Ok(Type::Bool),
+                    (UnaryOperator::Negate, t @ (Type::Int | Type::Float | Type::Any)) => Ok(t),
+                    (UnaryOperator::Negate, t) => Err(type_error(
+                        span,
+                        ErrorCode::InvalidOperation,
+                        format!("Invalid operation: -{:?}", t),
+                    )),
+                }
             }
             ASTNode::If {
                 condition,
                 body,
                 orelse,
+                ..
             } => {
-                let cond_type = self.check(condition)?;
-                if cond_type != Type::Bool {
-                    return Err(BellronosError::Type(
-                        "If condition must be a boolean".to_string(),
-                    ));
-                }
-                for stmt in body {
-                    self.check(stmt)?;
-                }
-                for stmt in orelse {
-                    self.check(stmt)?;
-                }
+                // Any value can be a condition through truthiness
+                self.check(condition)?;
+                let mut unassigned = self.check_branch(body)?;
+                unassigned.extend(self.check_branch(orelse)?);
+                self.unassigned = unassigned;
                 Ok(Type::None)
             }
-            ASTNode::While { condition, body } => {
-                let cond_type = self.check(condition)?;
-                if cond_type != Type::Bool {
-                    return Err(BellronosError::Type(
-                        "While condition must be a boolean".to_string(),
-                    ));
-                }
-                for stmt in body {
-                    self.check(stmt)?;
-                }
+            ASTNode::While {
+                condition, body, ..
+            } => {
+                self.check(condition)?;
+                self.check_branch(body)?;
                 Ok(Type::None)
             }
-            ASTNode::For { target, iter, body } => {
+            ASTNode::For {
+                target, iter, body, ..
+            } => {
                 let iter_type = self.check(iter)?;
-                if let Type::List(element_type) = iter_type {
-                    self.type_env.insert(target.clone(), *element_type);
-                    for stmt in body {
-                        self.check(stmt)?;
-                    }
-                    Ok(Type::None)
-                } else {
-                    Err(BellronosError::Type(
-                        "For loop iterable must be a list".to_string(),
-                    ))
+                let element_type = self.element_type(&iter_type).ok_or_else(|| {
+                    type_error(
+                        iter.span(),
+                        ErrorCode::TypeMismatch,
+                        format!("Type {:?} is not iterable", iter_type),
+                    )
+                })?;
+                let unassigned = self.unassigned.clone();
+                self.assign_target(target, element_type, iter.span())?;
+                for stmt in body {
+                    self.check(stmt)?;
                 }
+                // The body may not run at all
+                self.unassigned = unassigned;
+                Ok(Type::None)
             }
-            ASTNode::Return { value } => {
+            ASTNode::Return { value, .. } => {
                 if let Some(v) = value {
-                    self.check(v)
+                    self.check_value(v)
                 } else {
                     Ok(Type::None)
                 }
             }
-            ASTNode::Closure { params, body } => {
-                let param_types: Vec<Type> = params.iter().map(|(_, t)| t.clone()).collect();
-                let mut closure_checker = self.clone();
-                for (param_name, param_type) in params {
-                    closure_checker
-                        .type_env
-                        .insert(param_name.clone(), param_type.clone());
+            ASTNode::Break { .. } | ASTNode::Continue { .. } => Ok(Type::None),
+            ASTNode::Try {
+                body,
+                handlers,
+                finally,
+                ..
+            } => {
+                let mut unassigned = self.check_branch(body)?;
+                for handler in handlers {
+                    let exception_type = match &handler.class {
+                        Some(class) => match self.type_env.get(class) {
+                            Some(Type::Class(_)) => Type::Instance(class.clone()),
+                            _ => {
+                                return Err(type_error(
+                                    &handler.span,
+                                    ErrorCode::UnknownClass,
+                                    format!("Exception class '{}' not found", class),
+                                ))
+                            }
+                        },
+                        None => Type::Instance("Exception".to_string()),
+                    };
+                    if let Some(name) = &handler.name {
+                        self.type_env.insert(name.clone(), exception_type);
+                    }
+                    unassigned.extend(self.check_branch(&handler.body)?);
                 }
-                let return_type = closure_checker.check(body)?;
-                Ok(Type::Function(param_types, Box::new(return_type)))
+                self.unassigned = unassigned;
+                for stmt in finally {
+                    self.check(stmt)?;
+                }
+                Ok(Type::None)
             }
-            ASTNode::Generator { body } => {
+            ASTNode::Raise { value, .. } => match self.check(value)? {
+                Type::Class(_) | Type::Instance(_) | Type::Any => Ok(Type::None),
+                other => Err(type_error(
+                    value.span(),
+                    ErrorCode::TypeMismatch,
+                    format!("Exceptions must be class instances, found {:?}", other),
+                )),
+            },
+            ASTNode::Closure {
+                params,
+                return_type: Some(return_type),
+                body,
+                span,
+            } => {
+                self.check_function_body("<anonymous>", params, return_type, body, span)?;
+                Ok(Type::Function(
+                    param_types(params),
+                    Box::new(return_type.clone()),
+                ))
+            }
+            // Unannotated, it returns whatever its expression gives
+            ASTNode::Closure {
+                params,
+                return_type: None,
+                body,
+                ..
+            } => {
+                let mut closure_checker = self.clone();
+                closure_checker.yield_type = None;
+                closure_checker.unassigned.clear();
+                self.bind_parameters(&mut closure_checker, params)?;
+                let mut return_type = Type::None;
                 for stmt in body {
-                    self.check(stmt)?;
+                    return_type = closure_checker.check(stmt)?;
+                }
+                Ok(Type::Function(param_types(params), Box::new(return_type)))
+            }
+            ASTNode::Yield { value, span } => {
+                let value_type = self.check(value)?;
+                match &self.yield_type {
+                    // What `yield` evaluates to is whatever `send` passes in
+                    Some(expected) if self.is_compatible(&value_type, expected) => Ok(Type::Any),
+                    Some(expected) => Err(type_error(
+                        value.span(),
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Yield type mismatch: expected {:?}, found {:?}",
+                            expected, value_type
+                        ),
+                    )),
+                    None => Err(type_error(
+                        span,
+                        ErrorCode::YieldOutsideGenerator,
+                        "Yield outside of generator".to_string(),
+                    )),
                 }
-                Ok(Type::List(Box::new(Type::Any)))
             }
-            ASTNode::Yield { value } => self.check(value),
-            ASTNode::Async { body } => {
+            ASTNode::Async { body, .. } => {
                 for stmt in body {
-                    self.check(stmt)?;
+                    if let ASTNode::FunctionDef {
+                        name,
+                        args,
+                        return_type,
+                        body,
+                        decorators,
+                        span: def_span,
+                    } = stmt
+                    {
+                        let func_type = function_type(args, return_type, body, true);
+                        let bound_type = self.decorated_type(decorators, func_type)?;
+                        self.type_env.insert(name.clone(), bound_type);
+                        self.check_function_body(name, args, return_type, body, def_span)?;
+                    }
                 }
-                Ok(Type::Any)
+                Ok(Type::None)
             }
-            ASTNode::Await { value } => self.check(value),
-            ASTNode::List { elements } => {
+            // Statements check the awaits they may suspend at themselves, and
+            // the parser moves the rest of them into statements of their own
+            ASTNode::Await { span, .. } => Err(type_error(
+                span,
+                ErrorCode::MisplacedAwait,
+                "'await' can't be used in a parameter default or a decorator".to_string(),
+            )),
+            ASTNode::List { elements, .. } => {
                 if elements.is_empty() {
                     Ok(Type::List(Box::new(Type::Any)))
                 } else {
-                    let first_type = self.check(&elements[0])?;
+                    let mut element_type = self.check(&elements[0])?;
                     for element in elements.iter().skip(1) {
-                        let element_type = self.check(element)?;
-                        if !self.is_compatible(&element_type, &first_type) {
-                            return Err(BellronosError::Type(
-                                "All list elements must have compatible types".to_string(),
-                            ));
-                        }
+                        let next_type = self.check(element)?;
+                        element_type = self.unify(&element_type, &next_type);
+                    }
+                    Ok(Type::List(Box::new(element_type)))
+                }
+            }
+            ASTNode::Tuple { elements, .. } => {
+                let mut element_types = Vec::new();
+                for element in elements {
+                    element_types.push(self.check(element)?);
+                }
+                Ok(Type::Tuple(element_types))
+            }
+            ASTNode::Set { elements, .. } => {
+                let mut element_type: Option<Type> = None;
+                for element in elements {
+                    let next_type = self.check(element)?;
+                    if matches!(next_type, Type::List(_) | Type::Dict(..) | Type::Set(_)) {
+                        return Err(type_error(
+                            element.span(),
+                            ErrorCode::TypeMismatch,
+                            format!("Unhashable set element of type {:?}", next_type),
+                        ));
                     }
-                    Ok(Type::List(Box::new(first_type)))
+                    element_type = Some(match element_type {
+                        Some(known) => self.unify(&known, &next_type),
+                        None => next_type,
+                    });
                 }
+                Ok(Type::Set(Box::new(element_type.unwrap_or(Type::Any))))
             }
-            ASTNode::Dict { pairs } => {
+            ASTNode::Dict { pairs, .. } => {
                 if pairs.is_empty() {
                     Ok(Type::Dict(Box::new(Type::Any), Box::new(Type::Any)))
                 } else {
                     let (first_key, first_value) = &pairs[0];
-                    let key_type = self.check(first_key)?;
-                    let value_type = self.check(first_value)?;
+                    let mut key_type = self.check(first_key)?;
+                    let mut value_type = self.check(first_value)?;
                     for (key, value) in pairs.iter().skip(1) {
                         let k_type = self.check(key)?;
                         let v_type = self.check(value)?;
-                        if !self.is_compatible(&k_type, &key_type)
-                            || !self.is_compatible(&v_type, &value_type)
-                        {
-                            return Err(BellronosError::Type(
-                                "All dictionary keys must have compatible types, and all values must have compatible types".to_string(),
-                            ));
-                        }
+                        key_type = self.unify(&key_type, &k_type);
+                        value_type = self.unify(&value_type, &v_type);
                     }
                     Ok(Type::Dict(Box::new(key_type), Box::new(value_type)))
                 }
             }
-            ASTNode::Attribute { value, attr } => {
+            ASTNode::Subscript { value, index, span } => {
+                let container_type = self.check(value)?;
+                self.check_subscript(&container_type, index, span)
+            }
+            ASTNode::Slice { .. } => Ok(Type::Any),
+            ASTNode::Attribute { value, attr, span } => {
                 let value_type = self.check(value)?;
-                if let Type::Instance(class_name) = value_type {
-                    if let Some(class_methods) = self.class_env.get(&class_name) {
-                        class_methods.get(attr).cloned().ok_or_else(|| {
-                            BellronosError::Type(format!(
-                                "Attribute '{}' not found in class '{}'",
-                                attr, class_name
-                            ))
+                match &value_type {
+                    Type::Instance(class_name)
+                    | Type::Class(class_name)
+                    | Type::Custom(class_name) => {
+                        if !self.class_env.contains_key(class_name) {
+                            return Err(type_error(
+                                span,
+                                ErrorCode::UnknownClass,
+                                format!("Class '{}' not found", class_name),
+                            ));
+                        }
+                        let is_instance = !matches!(value_type, Type::Class(_));
+                        if is_instance {
+                            if let Some(field_type) = self.find_field(class_name, attr) {
+                                return Ok(field_type);
+                            }
+                        }
+                        match self.find_method(class_name, attr) {
+                            // Through an instance the receiver is already bound
+                            Some(Type::Function(params, return_type)) if is_instance => {
+                                Ok(Type::Function(
+                                    params.iter().skip(1).cloned().collect(),
+                                    return_type,
+                                ))
+                            }
+                            Some(method_type) => Ok(method_type),
+                            // Fields may also be assigned outside the class's
+                            // own methods, so unknown members stay dynamic
+                            None => Ok(Type::Any),
+                        }
+                    }
+                    Type::List(_) => self.builtin_method(&value_type, "list", attr, span),
+                    Type::Dict(..) => self.builtin_method(&value_type, "dict", attr, span),
+                    Type::Set(_) => self.builtin_method(&value_type, "set", attr, span),
+                    Type::File => self.builtin_method(&value_type, "file", attr, span),
+                    Type::Generator(_) => self.builtin_method(&value_type, "generator", attr, span),
+                    Type::Module(module) => {
+                        self.modules[module].get(attr).cloned().ok_or_else(|| {
+                            type_error(
+                                span,
+                                ErrorCode::UnknownAttribute,
+                                format!("Module '{}' has no attribute '{}'", module, attr),
+                            )
                         })
-                    } else {
-                        Err(BellronosError::Type(format!(
-                            "Class '{}' not found",
-                            class_name
-                        )))
                     }
-                } else {
-                    Err(BellronosError::Type(format!(
-                        "Cannot access attribute '{}' on non-instance type {:?}",
-                        attr, value_type
-                    )))
+                    Type::Any => Ok(Type::Any),
+                    _ => Err(type_error(
+                        span,
+                        ErrorCode::UnknownAttribute,
+                        format!(
+                            "Cannot access attribute '{}' on non-instance type {:?}",
+                            attr, value_type
+                        ),
+                    )),
+                }
+            }
+        }
+    }
+
+    /// The type of a built-in type's method bound to `receiver`, with the
+    /// `Any`s of its signature standing for the receiver's item types.
+    fn builtin_method(
+        &self,
+        receiver: &Type,
+        type_name: &str,
+        attr: &str,
+        span: &Span,
+    ) -> Result<Type, BellronosError> {
+        let generic = Type::Set(Box::new(Type::Any));
+        let specialize = |t: &Type| match receiver {
+            Type::List(element) | Type::Dict(_, element) => substitute_any(t, element),
+            _ if *t == generic => receiver.clone(),
+            _ => t.clone(),
+        };
+        match self.methods.get(type_name).and_then(|m| m.get(attr)) {
+            Some(Type::Function(params, return_type)) => Ok(Type::Function(
+                params
+                    .iter()
+                    .skip(1)
+                    .map(|param| ParamType {
+                        type_: specialize(&param.type_),
+                        ..param.clone()
+                    })
+                    .collect(),
+                Box::new(specialize(return_type)),
+            )),
+            _ => Err(type_error(
+                span,
+                ErrorCode::UnknownAttribute,
+                format!("Type '{}' has no method '{}'", type_name, attr),
+            )),
+        }
+    }
+
+    /// The type of the items a `for` loop draws from `iterable`, or `None`
+    /// when it cannot be iterated.
+    fn element_type(&self, iterable: &Type) -> Option<Type> {
+        match iterable {
+            Type::List(element_type) | Type::Set(element_type) => Some(*element_type.clone()),
+            Type::Tuple(element_types) => Some(
+                element_types
+                    .iter()
+                    .cloned()
+                    .reduce(|a, b| self.unify(&a, &b))
+                    .unwrap_or(Type::Any),
+            ),
+            Type::Dict(key_type, _) => Some(*key_type.clone()),
+            Type::String | Type::File => Some(Type::String),
+            Type::Range => Some(Type::Int),
+            Type::Generator(yield_type) => Some(*yield_type.clone()),
+            Type::Instance(class) | Type::Custom(class) => {
+                let Some(Type::Function(_, iterator)) = self.find_method(class, "__iter__") else {
+                    return None;
+                };
+                match *iterator {
+                    Type::Instance(iterator_class) | Type::Custom(iterator_class) => {
+                        match self.find_method(&iterator_class, "__next__") {
+                            Some(Type::Function(_, item_type)) => Some(*item_type),
+                            _ if iterator_class == *class => None,
+                            _ => self.element_type(&Type::Instance(iterator_class)),
+                        }
+                    }
+                    iterator => self.element_type(&iterator),
+                }
+            }
+            Type::Any => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    /// Binds `value_type` to an assignment or `for` target. `value_span`
+    /// locates the assigned value for mismatch errors.
+    fn assign_target(
+        &mut self,
+        target: &ASTNode,
+        value_type: Type,
+        value_span: &Span,
+    ) -> Result<(), BellronosError> {
+        match target {
+            ASTNode::Name { id, .. } => {
+                self.unassigned.remove(id);
+                self.type_env.insert(id.clone(), value_type);
+            }
+            ASTNode::Tuple { elements, span } => {
+                let element_types = self.unpack(&value_type, elements.len(), span)?;
+                for (element, element_type) in elements.iter().zip(element_types) {
+                    self.assign_target(element, element_type, value_span)?;
+                }
+            }
+            ASTNode::Subscript {
+                value: container,
+                index,
+                span,
+            } => {
+                let container_type = self.check(container)?;
+                if matches!(container_type, Type::Tuple(_) | Type::String) {
+                    return Err(type_error(
+                        span,
+                        ErrorCode::TypeMismatch,
+                        format!("{:?} does not support item assignment", container_type),
+                    ));
+                }
+                let element_type = self.check_subscript(&container_type, index, span)?;
+                if !self.is_compatible(&value_type, &element_type) {
+                    return Err(type_error(
+                        value_span,
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Type mismatch: expected {:?}, found {:?}",
+                            element_type, value_type
+                        ),
+                    ));
+                }
+            }
+            ASTNode::Attribute {
+                value: object,
+                attr,
+                ..
+            } => {
+                if let Type::Instance(class) | Type::Custom(class) = self.check(object)? {
+                    if let Some(field_type) = self.find_field(&class, attr) {
+                        if !self.is_compatible(&value_type, &field_type) {
+                            return Err(type_error(
+                                value_span,
+                                ErrorCode::TypeMismatch,
+                                format!(
+                                    "Field '{}' of {} is {:?}, found {:?}",
+                                    attr, class, field_type, value_type
+                                ),
+                            ));
+                        }
+                    }
+                    self.record_field(&class, attr, value_type);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The types of the `count` values destructured from `value_type`.
+    fn unpack(
+        &self,
+        value_type: &Type,
+        count: usize,
+        span: &Span,
+    ) -> Result<Vec<Type>, BellronosError> {
+        match value_type {
+            Type::Tuple(element_types) if element_types.len() == count => Ok(element_types.clone()),
+            Type::Tuple(element_types) => Err(type_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!(
+                    "Expected {} values to unpack, but the tuple has {}",
+                    count,
+                    element_types.len()
+                ),
+            )),
+            Type::List(element_type) => Ok(vec![*element_type.clone(); count]),
+            Type::Any => Ok(vec![Type::Any; count]),
+            other => Err(type_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("Cannot unpack {:?}", other),
+            )),
+        }
+    }
+
+    /// Checks the value of an expression statement, assignment or return,
+    /// which may await a task. Awaiting gives the task's result type.
+    fn check_value(&mut self, value: &ASTNode) -> Result<Type, BellronosError> {
+        let ASTNode::Await { value: task, .. } = value else {
+            return self.check(value);
+        };
+        match self.check(task)? {
+            Type::Task(result_type) => Ok(*result_type),
+            Type::Any => Ok(Type::Any),
+            other => Err(type_error(
+                task.span(),
+                ErrorCode::TypeMismatch,
+                format!("Only a task can be awaited, not {:?}", other),
+            )),
+        }
+    }
+
+    /// The type a name is bound to once `decorators` are applied to a
+    /// definition of type `defined`, the one nearest the definition first.
+    fn decorated_type(
+        &mut self,
+        decorators: &[ASTNode],
+        defined: Type,
+    ) -> Result<Type, BellronosError> {
+        let mut bound_type = defined;
+        for decorator in decorators.iter().rev() {
+            let span = decorator.span();
+            bound_type = match self.check(decorator)? {
+                Type::Function(params, return_type) => {
+                    let positional = positional_params(&params);
+                    let variadic = params.iter().find(|p| p.kind == ParamKind::Variadic);
+                    let expected = match positional.first().or(variadic) {
+                        Some(param) if required_params(&params) <= 1 => param,
+                        _ => {
+                            return Err(type_error(
+                                span,
+                                ErrorCode::ArgumentCount,
+                                format!(
+                                    "Decorator expects {} arguments, but 1 was given",
+                                    arity_text(&params)
+                                ),
+                            ))
+                        }
+                    };
+                    if !self.is_compatible(&bound_type, &expected.type_) {
+                        return Err(type_error(
+                            span,
+                            ErrorCode::TypeMismatch,
+                            format!(
+                                "Type mismatch: decorator expects {:?}, found {:?}",
+                                expected.type_, bound_type
+                            ),
+                        ));
+                    }
+                    *return_type
+                }
+                Type::Class(class_name) => Type::Instance(class_name),
+                Type::Any | Type::Callable => Type::Any,
+                other => {
+                    return Err(type_error(
+                        span,
+                        ErrorCode::NotCallable,
+                        format!("Decorator must be callable, found {:?}", other),
+                    ))
+                }
+            };
+        }
+        Ok(bound_type)
+    }
+
+    /// Declares a function's parameters in the checker of its body, and
+    /// checks their defaults in the enclosing scope.
+    fn bind_parameters(
+        &mut self,
+        body_checker: &mut TypeChecker,
+        params: &[Parameter],
+    ) -> Result<(), BellronosError> {
+        for param in params {
+            if let Some(default) = &param.default {
+                let default_type = self.check(default)?;
+                if !self.is_compatible(&default_type, &param.type_) {
+                    return Err(type_error(
+                        default.span(),
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Default of parameter '{}' is {:?}, expected {:?}",
+                            param.name, default_type, param.type_
+                        ),
+                    ));
+                }
+            }
+            let type_ = match param.kind {
+                // A tuple of any length, which `Type::Tuple` can't describe
+                ParamKind::Variadic => Type::Any,
+                ParamKind::KeywordVariadic => {
+                    Type::Dict(Box::new(Type::String), Box::new(param.type_.clone()))
+                }
+                ParamKind::Required | ParamKind::Optional => param.type_.clone(),
+            };
+            body_checker.type_env.insert(param.name.clone(), type_);
+        }
+        Ok(())
+    }
+
+    /// Checks the arguments of a call against `params`. `what` names the
+    /// callee in errors, e.g. `Function add`.
+    fn check_arguments(
+        &mut self,
+        what: &str,
+        params: &[ParamType],
+        args: &[Argument],
+        span: &Span,
+    ) -> Result<(), BellronosError> {
+        if args
+            .iter()
+            .any(|arg| matches!(arg, Argument::Unpack(_) | Argument::UnpackKeywords(_)))
+        {
+            for arg in args {
+                let arg_type = self.check(arg.value())?;
+                if let Argument::UnpackKeywords(value) = arg {
+                    if !matches!(arg_type, Type::Dict(..) | Type::Any) {
+                        return Err(type_error(
+                            value.span(),
+                            ErrorCode::TypeMismatch,
+                            format!("Argument after ** must be a dict, not {:?}", arg_type),
+                        ));
+                    }
+                }
+            }
+            return Ok(());
+        }
+        let positional = positional_params(params);
+        let variadic = params.iter().find(|p| p.kind == ParamKind::Variadic);
+        let keyword_variadic = params.iter().find(|p| p.kind == ParamKind::KeywordVariadic);
+        let count_error = || {
+            let given = args
+                .iter()
+                .filter(|arg| matches!(arg, Argument::Positional(_)))
+                .count();
+            type_error(
+                span,
+                ErrorCode::ArgumentCount,
+                format!(
+                    "{} expects {} arguments, but {} were given",
+                    what,
+                    arity_text(params),
+                    given
+                ),
+            )
+        };
+        let mut bound = vec![false; params.len()];
+        let mut has_keywords = false;
+        let mut index = 0;
+        for arg in args {
+            let (expected, value) = match arg {
+                Argument::Positional(value) => {
+                    let expected = match positional.get(index) {
+                        Some(param) => {
+                            bound[index] = true;
+                            param
+                        }
+                        None => variadic.ok_or_else(count_error)?,
+                    };
+                    index += 1;
+                    (expected, value)
+                }
+                Argument::Keyword(name, value) => {
+                    has_keywords = true;
+                    let named = params.iter().position(|p| {
+                        p.is_named(name)
+                            && matches!(p.kind, ParamKind::Required | ParamKind::Optional)
+                    });
+                    let expected = match named {
+                        Some(i) if bound[i] => {
+                            return Err(type_error(
+                                value.span(),
+                                ErrorCode::UnexpectedKeyword,
+                                format!("{} got multiple values for argument '{}'", what, name),
+                            ))
+                        }
+                        Some(i) => {
+                            bound[i] = true;
+                            &params[i]
+                        }
+                        None => keyword_variadic.ok_or_else(|| {
+                            type_error(
+                                value.span(),
+                                ErrorCode::UnexpectedKeyword,
+                                format!("{} got an unexpected keyword argument '{}'", what, name),
+                            )
+                        })?,
+                    };
+                    (expected, value)
+                }
+                Argument::Unpack(_) | Argument::UnpackKeywords(_) => unreachable!(),
+            };
+            let arg_type = self.check(value)?;
+            if !self.is_compatible(&arg_type, &expected.type_) {
+                return Err(type_error(
+                    value.span(),
+                    ErrorCode::TypeMismatch,
+                    format!(
+                        "Type mismatch: expected {:?}, found {:?}",
+                        expected.type_, arg_type
+                    ),
+                ));
+            }
+        }
+        for (param, bound) in params.iter().zip(bound) {
+            if bound || param.kind != ParamKind::Required {
+                continue;
+            }
+            match &param.name {
+                Some(name) if has_keywords || !param.is_positional() => {
+                    return Err(type_error(
+                        span,
+                        ErrorCode::ArgumentCount,
+                        format!("{} is missing argument '{}'", what, name),
+                    ))
+                }
+                _ => return Err(count_error()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a block that may not run, returning the variables still
+    /// unassigned at its end but keeping the ones from before it. A block
+    /// that never reaches its end leaves nothing unassigned.
+    fn check_branch(&mut self, body: &[ASTNode]) -> Result<HashSet<String>, BellronosError> {
+        let unassigned = self.unassigned.clone();
+        for stmt in body {
+            self.check(stmt)?;
+        }
+        let after = std::mem::replace(&mut self.unassigned, unassigned);
+        Ok(if ends_block(body) {
+            HashSet::new()
+        } else {
+            after
+        })
+    }
+
+    fn check_function_body(
+        &mut self,
+        name: &str,
+        args: &[Parameter],
+        return_type: &Type,
+        body: &[ASTNode],
+        def_span: &Span,
+    ) -> Result<(), BellronosError> {
+        let mut func_checker = self.clone();
+        func_checker.unassigned = local_names(body)
+            .into_iter()
+            .filter(|local| {
+                self.type_env.contains_key(local) && args.iter().all(|arg| &arg.name != local)
+            })
+            .collect();
+        self.bind_parameters(&mut func_checker, args)?;
+        func_checker.yield_type = None;
+        // A generator's `return` ends it, handing any value to the
+        // `StopIteration` raised, which no annotation describes
+        let mut return_type = return_type;
+        if body.iter().any(ASTNode::contains_yield) {
+            let yield_type = match return_type {
+                Type::Generator(yield_type) => *yield_type.clone(),
+                Type::None | Type::Any => Type::Any,
+                other => {
+                    return Err(type_error(
+                        def_span,
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Function {} yields, so it must return a generator, not {:?}",
+                            name, other
+                        ),
+                    ))
+                }
+            };
+            func_checker.yield_type = Some(yield_type);
+            return_type = &Type::Any;
+        }
+
+        for stmt in body {
+            let stmt_type = func_checker.check(stmt)?;
+            if let ASTNode::Return { span, .. } = stmt {
+                if !self.is_compatible(&stmt_type, return_type) {
+                    return Err(BellronosError::Type(Box::new(
+                        Diagnostic::new(
+                            ErrorCode::TypeMismatch,
+                            format!(
+                                "Function {} return type mismatch: expected {:?}, found {:?}",
+                                name, return_type, stmt_type
+                            ),
+                        )
+                        .with_span(span.clone())
+                        .with_label(def_span.clone(), "return type declared here"),
+                    )));
                 }
             }
-            ASTNode::InteropCall { language, code } => {
-                let interop_lang = InteropLanguage::from_str(language)?;
-                Ok(Type::Interop(interop_lang.infer_type(code)?))
+        }
+        self.class_env = func_checker.class_env;
+        Ok(())
+    }
+
+    /// Records the type of a field assigned on an instance of `class`. The
+    /// first assignment fixes the type, except that an int field assigned a
+    /// float widens to float.
+    fn record_field(&mut self, class: &str, field: &str, field_type: Type) {
+        let known = self.find_field(class, field);
+        if let Some(info) = self.class_env.get_mut(class) {
+            let field_type = match known {
+                Some(Type::Int) if field_type == Type::Float => Type::Float,
+                Some(known) => known,
+                None => field_type,
+            };
+            info.fields.insert(field.to_string(), field_type);
+        }
+    }
+
+    fn find_field(&self, class: &str, field: &str) -> Option<Type> {
+        let info = self.class_env.get(class)?;
+        info.fields.get(field).cloned().or_else(|| {
+            info.bases
+                .iter()
+                .find_map(|base| self.find_field(base, field))
+        })
+    }
+
+    /// Resolves a method through `class` and its bases, in the same order
+    /// as the interpreter.
+    fn find_method(&self, class: &str, method: &str) -> Option<Type> {
+        let info = self.class_env.get(class)?;
+        info.methods.get(method).cloned().or_else(|| {
+            info.bases
+                .iter()
+                .find_map(|base| self.find_method(base, method))
+        })
+    }
+
+    /// Whether `class` is `ancestor` or inherits from it.
+    fn is_subclass(&self, class: &str, ancestor: &str) -> bool {
+        class == ancestor
+            || self.class_env.get(class).is_some_and(|info| {
+                info.bases
+                    .iter()
+                    .any(|base| self.is_subclass(base, ancestor))
+            })
+    }
+
+    /// Registers every function and class defined in `body` up front so that
+    /// definitions can refer to each other regardless of their order.
+    fn declare_signatures(&mut self, body: &[ASTNode]) {
+        for stmt in body {
+            let (stmt, is_async) = stmt.unwrap_async();
+            match stmt {
+                // What a decorator makes of a definition is only known once
+                // it is checked
+                ASTNode::FunctionDef {
+                    name, decorators, ..
+                }
+                | ASTNode::ClassDef {
+                    name, decorators, ..
+                } if !decorators.is_empty() => {
+                    self.type_env.insert(name.clone(), Type::Any);
+                }
+                ASTNode::FunctionDef {
+                    name,
+                    args,
+                    return_type,
+                    body,
+                    ..
+                } => {
+                    self.type_env.insert(
+                        name.clone(),
+                        function_type(args, return_type, body, is_async),
+                    );
+                }
+                ASTNode::ClassDef { name, .. } => {
+                    self.type_env
+                        .insert(name.clone(), Type::Class(name.clone()));
+                }
+                _ => {}
             }
         }
     }
 
-    fn check_binary_op(&self, left: &Type, op: &str, right: &Type) -> Result<Type, BellronosError> {
+    fn check_binary_op(
+        &self,
+        left: &Type,
+        op: BinaryOperator,
+        right: &Type,
+    ) -> Result<Type, String> {
+        use BinaryOperator::*;
         match (left, op, right) {
-            (Type::Int, "+", Type::Int) => Ok(Type::Int),
-            (Type::Float, "+", Type::Float) => Ok(Type::Float),
-            (Type::String, "+", Type::String) => Ok(Type::String),
-            (Type::Int, "-", Type::Int) => Ok(Type::Int),
-            (Type::Float, "-", Type::Float) => Ok(Type::Float),
-            (Type::Int, "*", Type::Int) => Ok(Type::Int),
-            (Type::Float, "*", Type::Float) => Ok(Type::Float),
-            (Type::Int, "/", Type::Int) => Ok(Type::Float),
-            (Type::Float, "/", Type::Float) => Ok(Type::Float),
-            (Type::Int | Type::Float, op, Type::Int | Type::Float)
-                if op == ">" || op == "<" || op == ">=" || op == "<=" =>
-            {
+            _ if op.is_logical() || matches!(op, Is | IsNot) => Ok(Type::Bool),
+            (_, In | NotIn, Type::String) if matches!(left, Type::String | Type::Any) => {
                 Ok(Type::Bool)
             }
-            (_, "==" | "!=", _) => Ok(Type::Bool),
-            _ => Err(BellronosError::Type(format!(
-                "Invalid operation: {:?} {} {:?}",
-                left, op, right
-            ))),
+            (
+                _,
+                In | NotIn,
+                Type::List(_)
+                | Type::Tuple(_)
+                | Type::Set(_)
+                | Type::Dict(..)
+                | Type::Range
+                | Type::Any,
+            ) => Ok(Type::Bool),
+            (_, In | NotIn, _) => Err(format!("Invalid operation: {:?} {} {:?}", left, op, right)),
+            _ if op.is_comparison() && (left == &Type::Any || right == &Type::Any) => {
+                Ok(Type::Bool)
+            }
+            (Type::Any, _, _) | (_, _, Type::Any) => Ok(Type::Any),
+            // Mirrors the interpreter's promotion: int-only arithmetic stays
+            // int, true division and any float operand produce a float
+            (Type::Int | Type::Float, op, Type::Int | Type::Float) => match op {
+                _ if op.is_comparison() => Ok(Type::Bool),
+                Divide => Ok(Type::Float),
+                _ if left == &Type::Int && right == &Type::Int => Ok(Type::Int),
+                _ => Ok(Type::Float),
+            },
+            (Type::String, Add, Type::String) => Ok(Type::String),
+            (Type::List(l), Add, Type::List(r)) => Ok(Type::List(Box::new(self.unify(l, r)))),
+            (Type::String, Less | Greater | LessEqual | GreaterEqual, Type::String) => {
+                Ok(Type::Bool)
+            }
+            (_, Equal | NotEqual, _) => Ok(Type::Bool),
+            _ => Err(format!("Invalid operation: {:?} {} {:?}", left, op, right)),
+        }
+    }
+
+    /// The element type of a subscript, or the container type for slices.
+    fn check_subscript(
+        &mut self,
+        container: &Type,
+        index: &ASTNode,
+        span: &Span,
+    ) -> Result<Type, BellronosError> {
+        let index_type = match index {
+            ASTNode::Slice {
+                start, stop, step, ..
+            } => {
+                for part in [start, stop, step].into_iter().flatten() {
+                    let part_type = self.check(part)?;
+                    if !matches!(part_type, Type::Int | Type::None | Type::Any) {
+                        return Err(type_error(
+                            part.span(),
+                            ErrorCode::TypeMismatch,
+                            format!("Slice indices must be integers, found {:?}", part_type),
+                        ));
+                    }
+                }
+                None
+            }
+            _ => Some(self.check(index)?),
+        };
+        match (container, index_type) {
+            (Type::List(_) | Type::String, None) => Ok(container.clone()),
+            // Slicing a tuple keeps its type only when the bounds are known
+            (Type::Tuple(_), None) => Ok(Type::Any),
+            (Type::Tuple(element_types), Some(Type::Int)) => {
+                let constant = match index {
+                    ASTNode::Int { value, .. } => Some(*value),
+                    _ => None,
+                };
+                let len = element_types.len() as i64;
+                match constant.map(|i| if i < 0 { i + len } else { i }) {
+                    Some(i) if (0..len).contains(&i) => Ok(element_types[i as usize].clone()),
+                    Some(i) => Err(type_error(
+                        index.span(),
+                        ErrorCode::IndexOutOfRange,
+                        format!("Tuple index {} out of range for length {}", i, len),
+                    )),
+                    None => Ok(element_types
+                        .iter()
+                        .cloned()
+                        .reduce(|a, b| self.unify(&a, &b))
+                        .unwrap_or(Type::Any)),
+                }
+            }
+            (Type::Tuple(_), Some(Type::Any)) => Ok(Type::Any),
+            (Type::List(element), Some(Type::Int | Type::Any)) => Ok(*element.clone()),
+            (Type::String, Some(Type::Int | Type::Any)) => Ok(Type::String),
+            (Type::List(_) | Type::Tuple(_) | Type::String, Some(other)) => Err(type_error(
+                index.span(),
+                ErrorCode::TypeMismatch,
+                format!("Indices must be integers, found {:?}", other),
+            )),
+            (Type::Dict(key, value), Some(key_type)) if self.is_compatible(&key_type, key) => {
+                Ok(*value.clone())
+            }
+            (Type::Dict(key, _), Some(other)) => Err(type_error(
+                index.span(),
+                ErrorCode::TypeMismatch,
+                format!("Dictionary key must be {:?}, found {:?}", key, other),
+            )),
+            (Type::Any, _) => Ok(Type::Any),
+            _ => Err(type_error(
+                span,
+                ErrorCode::TypeMismatch,
+                format!("{:?} is not subscriptable", container),
+            )),
+        }
+    }
+
+    /// The narrowest type covering both, used for collection literals:
+    /// ints and floats widen to float, anything else unrelated to `Any`.
+    fn unify(&self, a: &Type, b: &Type) -> Type {
+        match (a, b) {
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+            (Type::Set(a), Type::Set(b)) => Type::Set(Box::new(self.unify(a, b))),
+            (Type::Generator(a), Type::Generator(b)) => Type::Generator(Box::new(self.unify(a, b))),
+            (Type::Task(a), Type::Task(b)) => Type::Task(Box::new(self.unify(a, b))),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+                Type::Tuple(a.iter().zip(b).map(|(a, b)| self.unify(a, b)).collect())
+            }
+            _ if a == b => a.clone(),
+            _ => Type::Any,
         }
     }
 
@@ -424,7 +1400,13 @@ impl TypeChecker {
         match (actual, expected) {
             (Type::Any, _) | (_, Type::Any) => true,
             (Type::Int, Type::Float) | (Type::Float, Type::Int) => true,
-            (Type::List(a), Type::List(b)) => self.is_compatible(a, b),
+            (Type::List(a), Type::List(b))
+            | (Type::Set(a), Type::Set(b))
+            | (Type::Generator(a), Type::Generator(b))
+            | (Type::Task(a), Type::Task(b)) => self.is_compatible(a, b),
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.is_compatible(a, b))
+            }
             (Type::Dict(ka, va), Type::Dict(kb, vb)) => {
                 self.is_compatible(ka, kb) && self.is_compatible(va, vb)
             }
@@ -433,11 +1415,17 @@ impl TypeChecker {
                     && params_a
                         .iter()
                         .zip(params_b.iter())
-                        .all(|(a, b)| self.is_compatible(a, b))
+                        .all(|(a, b)| a.kind == b.kind && self.is_compatible(&a.type_, &b.type_))
                     && self.is_compatible(return_a, return_b)
             }
-            (Type::Interop(a), Type::Interop(b)) => a == b,
-            (Type::Interop(_), _) | (_, Type::Interop(_)) => false, // Interop types are only compatible with themselves
+            // An instance of a subclass can stand in for its base class
+            (Type::Instance(a) | Type::Custom(a), Type::Instance(b) | Type::Custom(b)) => {
+                self.is_subclass(a, b)
+            }
+            // A signature left open matches any function either way, and
+            // calling a class constructs it
+            (Type::Function(..) | Type::Callable, Type::Function(..) | Type::Callable)
+            | (Type::Class(_), Type::Callable) => true,
             (a, b) => a == b,
         }
     }
@@ -453,55 +1441,160 @@ impl TypeChecker {
     pub fn get_class_method(&self, class_name: &str, method_name: &str) -> Option<&Type> {
         self.class_env
             .get(class_name)
-            .and_then(|methods| methods.get(method_name))
+            .and_then(|info| info.methods.get(method_name))
     }
 
     pub fn add_class_method(&mut self, class_name: &str, method_name: String, method_type: Type) {
         self.class_env
             .entry(class_name.to_string())
-            .or_insert_with(HashMap::new)
+            .or_default()
+            .methods
             .insert(method_name, method_type);
     }
 }
 
-impl From<InteropType> for Type {
-    fn from(interop_type: InteropType) -> Self {
-        match interop_type {
-            InteropType::CInt | InteropType::PyInt => Type::Int,
-            InteropType::CFloat | InteropType::PyFloat | InteropType::JsNumber => Type::Float,
-            InteropType::CString | InteropType::PyString | InteropType::JsString => Type::String,
-            InteropType::PyList | InteropType::JsArray => Type::List(Box::new(Type::Any)),
-            InteropType::PyDict | InteropType::JsObject => {
-                Type::Dict(Box::new(Type::Any), Box::new(Type::Any))
-            }
-            InteropType::JsBoolean => Type::Bool,
-            InteropType::Unknown => Type::Any,
+/// The type of a defined function, a generator's or task's for one that
+/// yields or is `async`.
+fn function_type(args: &[Parameter], return_type: &Type, body: &[ASTNode], is_async: bool) -> Type {
+    let arg_types = param_types(args);
+    let return_type = match return_type {
+        _ if is_async => Type::Task(Box::new(return_type.clone())),
+        Type::None | Type::Any if body.iter().any(ASTNode::contains_yield) => {
+            Type::Generator(Box::new(Type::Any))
         }
+        other => other.clone(),
+    };
+    Type::Function(arg_types, Box::new(return_type))
+}
+
+/// Whether running `body` always leaves it through `return`, `raise`,
+/// `break` or `continue`.
+fn ends_block(body: &[ASTNode]) -> bool {
+    match body.last() {
+        Some(
+            ASTNode::Return { .. }
+            | ASTNode::Raise { .. }
+            | ASTNode::Break { .. }
+            | ASTNode::Continue { .. },
+        ) => true,
+        Some(ASTNode::If { body, orelse, .. }) => ends_block(body) && ends_block(orelse),
+        _ => false,
     }
 }
 
-pub fn interop_type_to_bellronos_type(interop_type: InteropType) -> Type {
-    Type::from(interop_type)
+/// The variables a function body assigns, in nested blocks too, except
+/// the ones it declares `nonlocal`.
+fn local_names(body: &[ASTNode]) -> HashSet<String> {
+    fn visit(body: &[ASTNode], assigned: &mut HashSet<String>, nonlocals: &mut HashSet<String>) {
+        for stmt in body {
+            match stmt {
+                ASTNode::Nonlocal { names, .. } => nonlocals.extend(names.iter().cloned()),
+                ASTNode::Assign { target, .. } => target_names(target, assigned),
+                ASTNode::If { body, orelse, .. } => {
+                    visit(body, assigned, nonlocals);
+                    visit(orelse, assigned, nonlocals);
+                }
+                ASTNode::While { body, .. } => visit(body, assigned, nonlocals),
+                ASTNode::For { target, body, .. } => {
+                    target_names(target, assigned);
+                    visit(body, assigned, nonlocals);
+                }
+                ASTNode::Try {
+                    body,
+                    handlers,
+                    finally,
+                    ..
+                } => {
+                    visit(body, assigned, nonlocals);
+                    for handler in handlers {
+                        visit(&handler.body, assigned, nonlocals);
+                    }
+                    visit(finally, assigned, nonlocals);
+                }
+                _ => {}
+            }
+        }
+    }
+    fn target_names(target: &ASTNode, names: &mut HashSet<String>) {
+        match target {
+            ASTNode::Name { id, .. } => {
+                names.insert(id.clone());
+            }
+            ASTNode::Tuple { elements, .. } => {
+                for element in elements {
+                    target_names(element, names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut assigned = HashSet::new();
+    let mut nonlocals = HashSet::new();
+    visit(body, &mut assigned, &mut nonlocals);
+    &assigned - &nonlocals
+}
+
+/// `type_` with every `Any` in it replaced by `replacement`.
+fn substitute_any(type_: &Type, replacement: &Type) -> Type {
+    let substitute = |t: &Type| Box::new(substitute_any(t, replacement));
+    match type_ {
+        Type::Any => replacement.clone(),
+        Type::List(element) => Type::List(substitute(element)),
+        Type::Set(element) => Type::Set(substitute(element)),
+        Type::Optional(inner) => Type::Optional(substitute(inner)),
+        Type::Dict(key, value) => Type::Dict(substitute(key), substitute(value)),
+        Type::Tuple(elements) => Type::Tuple(
+            elements
+                .iter()
+                .map(|t| substitute_any(t, replacement))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn param_types(params: &[Parameter]) -> Vec<ParamType> {
+    params
+        .iter()
+        .map(|param| ParamType {
+            name: Some(param.name.clone()),
+            type_: param.type_.clone(),
+            kind: param.kind,
+        })
+        .collect()
+}
+
+/// The parameters a positional argument can fill: those before `*args`.
+pub fn positional_params(params: &[ParamType]) -> &[ParamType] {
+    let count = params.iter().take_while(|p| p.is_positional()).count();
+    &params[..count]
 }
 
-pub fn bellronos_type_to_interop_type(
-    bellronos_type: &Type,
-    language: &InteropLanguage,
-) -> InteropType {
-    match (bellronos_type, language) {
-        (Type::Int, InteropLanguage::C) => InteropType::CInt,
-        (Type::Float, InteropLanguage::C) => InteropType::CFloat,
-        (Type::String, InteropLanguage::C) => InteropType::CString,
-        (Type::Int, InteropLanguage::Python) => InteropType::PyInt,
-        (Type::Float, InteropLanguage::Python) => InteropType::PyFloat,
-        (Type::String, InteropLanguage::Python) => InteropType::PyString,
-        (Type::List(_), InteropLanguage::Python) => InteropType::PyList,
-        (Type::Dict(_, _), InteropLanguage::Python) => InteropType::PyDict,
-        (Type::Int | Type::Float, InteropLanguage::JavaScript) => InteropType::JsNumber,
-        (Type::String, InteropLanguage::JavaScript) => InteropType::JsString,
-        (Type::Bool, InteropLanguage::JavaScript) => InteropType::JsBoolean,
-        (Type::List(_), InteropLanguage::JavaScript) => InteropType::JsArray,
-        (Type::Dict(_, _), InteropLanguage::JavaScript) => InteropType::JsObject,
-        _ => InteropType::Unknown,
+/// How many leading parameters a call must supply.
+pub fn required_params(params: &[ParamType]) -> usize {
+    positional_params(params)
+        .iter()
+        .filter(|p| p.kind == ParamKind::Required)
+        .count()
+}
+
+/// The accepted argument count for error messages: `2`, `1 to 3` when
+/// some parameters are optional, or `at least 1` with `*args`.
+pub fn arity_text(params: &[ParamType]) -> String {
+    let required = required_params(params);
+    let total = positional_params(params).len();
+    if params.iter().any(|p| p.kind == ParamKind::Variadic) {
+        format!("at least {}", required)
+    } else if required == total {
+        total.to_string()
+    } else {
+        format!("{} to {}", required, total)
     }
 }
+
+fn type_error(span: &Span, code: ErrorCode, message: String) -> BellronosError {
+    BellronosError::Type(Box::new(
+        Diagnostic::new(code, message).with_span(span.clone()),
+    ))
+}
@@ -0,0 +1,184 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn gathered_tasks_run_concurrently() {
+    run(r#"
+        import io
+        import async
+        async define fetch(name: string, delay: float) -> string:
+            io.print("start " + name)
+            await async.sleep(delay)
+            io.print("done " + name)
+            return name
+        async define main() -> any:
+            return await async.gather([fetch("a", 0.2), fetch("b", 0.05), fetch("c", 0.1)])
+        io.print(async.run(main()))
+    "#)
+    .success()
+    .stdout(lines(&[
+        "start a",
+        "start b",
+        "start c",
+        "done b",
+        "done c",
+        "done a",
+        "[\"a\", \"b\", \"c\"]",
+    ]));
+}
+
+#[test]
+fn spawned_tasks_interleave_with_their_spawner() {
+    run(r#"
+        import io
+        import async
+        import string
+        async define worker() -> none:
+            for i in range(3):
+                io.print("worker " + string.to_string(i))
+                await async.sleep(0.02)
+        async define main() -> none:
+            set t to async.spawn(worker())
+            await async.sleep(0.01)
+            io.print("main")
+            await t
+        async.run(main())
+    "#)
+    .success()
+    .stdout(lines(&["worker 0", "main", "worker 1", "worker 2"]));
+}
+
+#[test]
+fn failures_and_timeouts_are_catchable() {
+    run(r#"
+        import io
+        import async
+        async define fails() -> int:
+            await async.sleep(0.01)
+            raise ValueError("boom")
+        async define slow() -> int:
+            await async.sleep(5.0)
+            return 1
+        async define main() -> none:
+            try:
+                await fails()
+            except ValueError as e:
+                io.print("caught " + e.message)
+            try:
+                set x to await async.timeout(slow(), 0.05)
+            except TimeoutError as e:
+                io.print(e.message)
+        async.run(main())
+    "#)
+    .success()
+    .stdout(lines(&[
+        "caught boom",
+        "Task did not finish within 0.05 seconds",
+    ]));
+}
+
+#[test]
+fn await_works_inside_expressions() {
+    run(r#"
+        import io
+        import async
+        import string
+        async define value(x: int) -> int:
+            return x * 10
+        async define check(flag: bool) -> bool:
+            io.print("checked")
+            return flag
+        async define main() -> int:
+            set t to async.spawn(value(4))
+            io.print(string.to_string(await t))
+            io.print(1 + await value(2) + await value(3))
+            io.print([await value(1), await value(2)])
+            if false and await check(true):
+                io.print("unreachable")
+            elif await check(true) or await check(false):
+                io.print("elif")
+            set n to 0
+            while await value(n) < 30:
+                set n to n + 1
+            io.print(n)
+            return await value(await value(1))
+        io.print(async.run(main()))
+    "#)
+    .success()
+    .stdout(lines(&[
+        "40", "51", "[10, 20]", "checked", "elif", "3", "100",
+    ]));
+}
+
+#[test]
+fn await_outside_async_function_is_rejected() {
+    run(r#"
+        import async
+        define f() -> int:
+            await async.sleep(0.1)
+            return 1
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "error[E0304]: 'await' outside async function",
+    ));
+}
+
+#[test]
+fn tasks_called_as_statements_still_run() {
+    run(r#"
+        import io
+        import async
+        async define fetch() -> string:
+            await async.sleep(0.01)
+            return "fetched"
+        async define log(message: string) -> none:
+            io.print(message)
+        async define process() -> none:
+            log("started")
+            io.print(await fetch())
+        process()
+        io.print("after")
+    "#)
+    .success()
+    .stdout(lines(&["started", "fetched", "after"]));
+}
+
+#[test]
+fn awaits_in_expressions_leave_user_variables_alone() {
+    run(r#"
+        import io
+        import async
+        async define double(x: int) -> int:
+            return x * 2
+        async define main() -> any:
+            set results to []
+            for i in range(3):
+                set a to await double(i) + 1
+                set b to [await double(a), a]
+                results.append(b[0] + await double(b[1]))
+            define total() -> int:
+                return a + b[0]
+            return [results, total()]
+        io.print(async.run(main()))
+    "#)
+    .success()
+    .stdout(lines(&["[[4, 12, 20], 15]"]));
+}
@@ -0,0 +1,106 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run, run_with};
+use predicates::prelude::*;
+
+const HELPERS: (&str, &str) = (
+    "packages/helpers.bellronos",
+    r#"
+    define double(n: int) -> int:
+        return n * 2
+    set name to "helpers"
+"#,
+);
+
+#[test]
+fn module_and_package_members_are_attributes() {
+    run_with(
+        &[],
+        &[HELPERS],
+        r#"
+        import io
+        import math
+        import helpers
+        io.print(math.sqrt(16.0))
+        io.print(helpers.double(21))
+        io.print(helpers.name)
+        io.print(io)
+    "#,
+    )
+    .success()
+    .stdout(lines(&["4.0", "42", "helpers", "<module io>"]));
+}
+
+#[test]
+fn missing_module_members_are_errors() {
+    run(r#"
+        import io
+        io.print(io.nothing)
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Type error[E0206]: Module 'io' has no attribute 'nothing'",
+    ));
+    run_with(
+        &[],
+        &[HELPERS],
+        r#"
+        import io
+        import helpers
+        io.print(helpers.missing)
+    "#,
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "Runtime error[E0206]: Module 'helpers' has no attribute 'missing'",
+    ));
+}
+
+#[test]
+fn dict_keys_are_not_attributes() {
+    run(r#"
+        import io
+        define show(x: any) -> any:
+            return x.print
+        io.print(show({"print": 1}))
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Cannot access attribute 'print' on {\"print\": 1}",
+    ));
+}
+
+#[test]
+fn methods_bind_their_receiver() {
+    run(r#"
+        import io
+        class Counter:
+            define __init__(self):
+                set self.count to 0
+            define bump(self) -> int:
+                set self.count to self.count + 1
+                return self.count
+        set c to Counter()
+        set bump to c.bump
+        bump()
+        io.print(c.bump())
+        io.print(Counter().bump())
+    "#)
+    .success()
+    .stdout(lines(&["2", "1"]));
+}
@@ -0,0 +1,96 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn constructors_inheritance_and_super() {
+    run(r#"
+        import io
+        import string
+        class Person:
+            define **init**(self, name: string, age: int):
+                set self.name to name
+                set self.age to age
+            define greet(self) -> string:
+                return "Hi, I'm " + self.name
+            define describe(self) -> string:
+                return self.greet() + " (" + string.to_string(self.age) + ")"
+        class Student(Person):
+            define __init__(self, name: string, age: int, school: string):
+                super.__init__(name, age)
+                set self.school to school
+            define greet(self) -> string:
+                return super.greet() + " from " + self.school
+        io.print(Student("Ada", 20, "MIT").describe())
+        io.print(Person("Bob", 30).describe())
+    "#)
+    .success()
+    .stdout(lines(&["Hi, I'm Ada from MIT (20)", "Hi, I'm Bob (30)"]));
+}
+
+#[test]
+fn instances_are_shared_between_references() {
+    run(r#"
+        import io
+        class Person:
+            define __init__(self, age: int):
+                set self.age to age
+        define birthday(p: Person) -> int:
+            set p.age to p.age + 1
+            return p.age
+        set p to Person(20)
+        set alias to p
+        birthday(alias)
+        io.print(p.age)
+    "#)
+    .success()
+    .stdout("21\n");
+}
+
+#[test]
+fn field_assignments_are_type_checked() {
+    run(r#"
+        class P:
+            define __init__(self, age: int):
+                set self.age to age
+        set p to P(1)
+        set p.age to "old"
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Field 'age' of P is Int, found String",
+    ));
+}
+
+#[test]
+fn unrelated_classes_are_not_compatible() {
+    run(r#"
+        class P:
+            define __init__(self):
+                set self.a to 1
+        class Q:
+            define __init__(self):
+                set self.b to 1
+        define f(p: P) -> int:
+            return 1
+        f(Q())
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Type mismatch"));
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn nested_functions_capture_their_scope() {
+    run(r#"
+        import io
+        define create_multiplier(factor: int) -> any:
+            define multiply(x: int) -> int:
+                return x * factor
+            return multiply
+        set triple to create_multiplier(3)
+        io.print(triple(5))
+        io.print(create_multiplier(2)(21))
+    "#)
+    .success()
+    .stdout(lines(&["15", "42"]));
+}
+
+#[test]
+fn anonymous_functions_are_callable_anywhere() {
+    run(r#"
+        import io
+        set square to define(x: int) -> int: x * x
+        io.print(square(4))
+        set add to closure (a: int, b: int): a + b
+        io.print(add(2, 3))
+        io.print((closure (x: int): x + 1)(1))
+    "#)
+    .success()
+    .stdout(lines(&["16", "5", "2"]));
+}
+
+#[test]
+fn higher_order_builtins() {
+    run(r#"
+        import io
+        set add to closure (a: int, b: int): a + b
+        io.print(map(define(x: int) -> int: x * x, [1, 2, 3]))
+        io.print(filter(define(x: int) -> bool: x % 2 == 0, range(6)))
+        io.print(reduce(add, [1, 2, 3, 4]))
+        io.print(reduce(add, [], 10))
+    "#)
+    .success()
+    .stdout(lines(&["[1, 4, 9]", "[0, 2, 4]", "10", "10"]));
+}
+
+#[test]
+fn collection_builtins_convert_any_iterable() {
+    run(r#"
+        import io
+        define count(n: int) -> generator[int]:
+            for i in range(n):
+                yield i
+        io.print(list(map(define(x) -> int: return x * x, [1, 2, 3])))
+        io.print(list(count(3)))
+        io.print(tuple("ab"))
+        io.print(set([1, 2, 2]) == {1, 2})
+        io.print([list(), tuple()])
+        try:
+            set s to set([[1]])
+        except TypeError as e:
+            io.print(e)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "[1, 4, 9]",
+        "[0, 1, 2]",
+        "(\"a\", \"b\")",
+        "true",
+        "[[], ()]",
+        "Unhashable type: 'list'",
+    ]));
+}
+
+#[test]
+fn nonlocal_rebinds_the_captured_variable() {
+    run(r#"
+        import io
+        define make_counter() -> any:
+            set count to 0
+            define increment() -> int:
+                nonlocal count
+                set count to count + 1
+                return count
+            return increment
+        set c to make_counter()
+        io.print(c())
+        io.print(c())
+        io.print(make_counter()())
+        set total to 10
+        define add(n: int) -> int:
+            nonlocal total
+            set total to total + n
+            return total
+        add(5)
+        io.print(total)
+    "#)
+    .success()
+    .stdout(lines(&["1", "2", "1", "15"]));
+}
+
+#[test]
+fn reading_a_local_before_it_shadows_is_rejected() {
+    run(r#"
+        import io
+        define make_counter() -> any:
+            set count to 0
+            define increment() -> int:
+                set count to count + 1
+                return count
+            return increment
+        io.print(make_counter()())
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Local variable 'count' is read before it is assigned",
+    ))
+    .stderr(predicate::str::contains("declare `nonlocal count`"));
+}
+
+#[test]
+fn locals_assigned_on_every_path_may_be_read() {
+    run(r#"
+        import io
+        set label to "global"
+        define describe(n: int) -> string:
+            if n > 0:
+                set label to "positive"
+            elif n < 0:
+                set label to "negative"
+            else:
+                return "zero"
+            return label
+        io.print(describe(1))
+        io.print(describe(-1))
+        io.print(describe(0))
+    "#)
+    .success()
+    .stdout(lines(&["positive", "negative", "zero"]));
+}
+
+#[test]
+fn locals_assigned_on_some_paths_are_rejected() {
+    run(r#"
+        set label to "global"
+        define describe(n: int) -> string:
+            if n > 0:
+                set label to "positive"
+            return label
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Local variable 'label' is read before it is assigned",
+    ));
+    run(r#"
+        set label to "global"
+        define describe(n: int) -> string:
+            for i in range(n):
+                set label to "looped"
+            return label
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Local variable 'label' is read before it is assigned",
+    ));
+}
+
+#[test]
+fn nonlocal_needs_an_enclosing_binding() {
+    run("nonlocal x").failure().stderr(predicate::str::contains(
+        "error[E0305]: 'nonlocal' outside function",
+    ));
+    run(r#"
+        define f() -> int:
+            nonlocal missing
+            return 1
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "No binding for nonlocal 'missing' in an enclosing scope",
+    ));
+}
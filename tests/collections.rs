@@ -0,0 +1,196 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn indexing_and_slicing() {
+    run(r#"
+        import io
+        set fruits to ["apple", "banana", "cherry", "date"]
+        io.print(fruits[0])
+        io.print(fruits[-1])
+        io.print(fruits[1:3])
+        io.print(fruits[::2])
+        io.print(fruits[::-1])
+        io.print("hello"[1:4])
+        io.print("hello"[-1])
+    "#)
+    .success()
+    .stdout(lines(&[
+        "apple",
+        "date",
+        "[\"banana\", \"cherry\"]",
+        "[\"apple\", \"cherry\"]",
+        "[\"date\", \"cherry\", \"banana\", \"apple\"]",
+        "ell",
+        "o",
+    ]));
+}
+
+#[test]
+fn subscript_assignment() {
+    run(r#"
+        import io
+        set fruits to ["apple", "banana"]
+        set fruits[1] to "blueberry"
+        io.print(fruits)
+        set person to {"name": "Ada"}
+        set person["job"] to "engineer"
+        io.print(person["job"])
+    "#)
+    .success()
+    .stdout(lines(&["[\"apple\", \"blueberry\"]", "engineer"]));
+}
+
+#[test]
+fn missing_indices_and_keys_are_catchable() {
+    run(r#"
+        import io
+        set fruits to ["apple"]
+        set person to {"name": "Ada"}
+        try:
+            io.print(fruits[10])
+        except IndexError as e:
+            io.print(e)
+        try:
+            io.print(person["age"])
+        except KeyError as e:
+            io.print(e)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "List index 10 out of range for length 1",
+        "Key \"age\" not found",
+    ]));
+}
+
+#[test]
+fn element_types_are_checked() {
+    run(r#"
+        set xs to [1, 2]
+        set xs[0] to "a"
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Type mismatch: expected Int, found String",
+    ));
+    run(r#"
+        set s to "abc"
+        set s[0] to "x"
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "String does not support item assignment",
+    ));
+}
+
+#[test]
+fn lists_are_shared_between_references() {
+    run(r#"
+        import io
+        define add(items: list[string]) -> int:
+            items.append("mango")
+            return len(items)
+        set fruits to ["apple"]
+        add(fruits)
+        io.print(fruits)
+        set copy to fruits.copy()
+        copy.append("kiwi")
+        io.print(len(fruits))
+        set same to fruits
+        io.print(same is fruits)
+        io.print([1, 2] is [1, 2])
+        io.print([1, 2] == [1, 2])
+    "#)
+    .success()
+    .stdout(lines(&[
+        "[\"apple\", \"mango\"]",
+        "2",
+        "true",
+        "false",
+        "true",
+    ]));
+}
+
+#[test]
+fn list_methods_and_concatenation() {
+    run(r#"
+        import io
+        set xs to [3, 1, 2]
+        xs.extend([5])
+        xs.insert(0, 9)
+        xs.remove(1)
+        io.print(xs.pop())
+        io.print(xs.index(2))
+        io.print(xs.count(3))
+        xs.reverse()
+        io.print(xs)
+        io.print([1] + [2, 3])
+    "#)
+    .success()
+    .stdout(lines(&["5", "2", "1", "[2, 3, 9]", "[1, 2, 3]"]));
+}
+
+#[test]
+fn dict_methods() {
+    run(r#"
+        import io
+        set d to {"a": 1}
+        d.update({"b": 2})
+        io.print(d.get("b"))
+        io.print(d.pop("a"))
+        io.print(d.keys())
+        io.print(d.items())
+    "#)
+    .success()
+    .stdout(lines(&["2", "1", "[\"b\"]", "[(\"b\", 2)]"]));
+}
+
+#[test]
+fn method_arguments_are_type_checked() {
+    run(r#"
+        set xs to [1]
+        xs.append("a")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Type mismatch: expected Int, found String",
+    ))
+    .stderr(predicate::str::contains("--> main.bellronos:2:11"));
+}
+
+#[test]
+fn self_containing_collections_print_and_compare() {
+    run(r#"
+        import io
+        set loop to []
+        loop.append(1)
+        loop.append(loop)
+        io.print(loop)
+        set other to []
+        other.append(1)
+        other.append(other)
+        io.print(loop == other)
+        set selfish to {}
+        set selfish["me"] to selfish
+        io.print(selfish)
+    "#)
+    .success()
+    .stdout(lines(&["[1, [...]]", "true", "{\"me\": {...}}"]));
+}
@@ -0,0 +1,91 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers shared by the integration tests, which run Bellronos programs
+//! through the `bellronos` binary.
+
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use std::fs;
+use tempfile::TempDir;
+
+/// The name programs are written to, and reported under in diagnostics.
+pub const MAIN: &str = "main.bellronos";
+
+/// Runs `source` in a fresh directory. The source may be indented as a
+/// whole to sit nicely in a raw string; the common indentation is removed.
+pub fn run(source: &str) -> Assert {
+    run_with(&[], &[], source)
+}
+
+/// Runs `source` like `run`, with `args` before the file name and `files`
+/// (relative path, contents) written next to it.
+pub fn run_with(args: &[&str], files: &[(&str, &str)], source: &str) -> Assert {
+    run_program(args, files, "", source)
+}
+
+/// Runs `source` like `run`, with `input` on its standard input.
+pub fn run_with_input(input: &str, source: &str) -> Assert {
+    run_program(&[], &[], input, source)
+}
+
+/// Runs `source` exactly as given, without removing its indentation.
+pub fn run_verbatim(source: &str) -> Assert {
+    run_file(&[], &[], "", source)
+}
+
+fn run_program(args: &[&str], files: &[(&str, &str)], input: &str, source: &str) -> Assert {
+    run_file(args, files, input, &dedent(source))
+}
+
+fn run_file(args: &[&str], files: &[(&str, &str)], input: &str, source: &str) -> Assert {
+    let dir = TempDir::new().unwrap();
+    for (path, contents) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, dedent(contents)).unwrap();
+    }
+    fs::write(dir.path().join(MAIN), source).unwrap();
+    Command::cargo_bin("bellronos")
+        .unwrap()
+        .current_dir(dir.path())
+        .args(args)
+        .arg(MAIN)
+        .write_stdin(input)
+        .assert()
+}
+
+/// The lines a program is expected to print, each followed by a newline.
+pub fn lines(lines: &[&str]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// `source` without its leading newline and common indentation.
+pub fn dedent(source: &str) -> String {
+    let source = source.strip_prefix('\n').unwrap_or(source);
+    let indent = source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    source
+        .lines()
+        .map(|line| format!("{}\n", line.get(indent..).unwrap_or("").trim_end()))
+        .collect()
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn return_unwinds_out_of_nested_loops() {
+    run(r#"
+        import io
+        import string
+        define find(items: list[int], wanted: int) -> int:
+            set index to 0
+            while true:
+                for item in items:
+                    if item == wanted:
+                        return index
+                    set index to index + 1
+                return -1
+            return -2
+        io.print(string.to_string(find([4, 5, 6], 5)))
+        io.print(string.to_string(find([4, 5, 6], 7)))
+    "#)
+    .success()
+    .stdout(lines(&["1", "-1"]));
+}
+
+#[test]
+fn early_return_skips_the_rest_of_the_function() {
+    run(r#"
+        import io
+        define divide(a: float, b: float) -> float:
+            if b == 0:
+                io.print("cannot divide by zero")
+                return 0.0
+            io.print("dividing")
+            return a / b
+        io.print(divide(1.0, 0.0))
+        io.print(divide(1.0, 4.0))
+    "#)
+    .success()
+    .stdout(lines(&["cannot divide by zero", "0.0", "dividing", "0.25"]));
+}
+
+#[test]
+fn runaway_recursion_raises_a_catchable_error() {
+    run(r#"
+        import io
+        import string
+        define forever(n: int) -> int:
+            return forever(n + 1)
+        try:
+            forever(0)
+        except RecursionError as e:
+            io.print(string.to_string(e))
+        io.print("still running")
+        forever(0)
+    "#)
+    .failure()
+    .stdout(lines(&[
+        "Maximum recursion depth of 1000 exceeded",
+        "still running",
+    ]))
+    .stderr(predicate::str::contains(
+        "error[E0218]: Maximum recursion depth of 1000 exceeded",
+    ));
+}
+
+#[test]
+fn deep_recursion_below_the_limit_succeeds() {
+    run(r#"
+        import io
+        import string
+        define depth(n: int) -> int:
+            if n == 0:
+                return 0
+            return 1 + depth(n - 1)
+        io.print(string.to_string(depth(900)))
+    "#)
+    .success()
+    .stdout("900\n");
+}
+
+#[test]
+fn elif_chains_take_the_first_true_branch() {
+    run(r#"
+        import io
+        define grade(score: int) -> string:
+            if score >= 90:
+                return "A"
+            elif score >= 80:
+                return "B"
+            elif score >= 70:
+                return "C"
+            else:
+                return "F"
+        for score in [95, 85, 75, 10]:
+            io.print(grade(score))
+        set n to 5
+        if n < 0:
+            io.print("negative")
+        elif n == 0:
+            io.print("zero")
+        io.print("done")
+    "#)
+    .success()
+    .stdout(lines(&["A", "B", "C", "F", "done"]));
+}
+
+#[test]
+fn elif_conditions_are_type_checked() {
+    run(r#"
+        import io
+        if 1:
+            io.print("a")
+        elif "x" + 1:
+            io.print("b")
+    "#)
+    .failure()
+    .stdout("")
+    .stderr(predicate::str::contains("--> main.bellronos:4:6"));
+}
+
+#[test]
+fn elif_after_else_is_rejected() {
+    run(r#"
+        if true:
+            set x to 1
+        else:
+            set x to 2
+        elif false:
+            set x to 3
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Unexpected token: Elif"));
+}
@@ -0,0 +1,111 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn stacked_decorators_apply_bottom_up() {
+    run(r#"
+        import io
+        import string
+        define shout(func: any) -> any:
+            define wrapper(*args, **kwargs) -> any:
+                return string.to_upper(func(*args, **kwargs))
+            return wrapper
+        define exclaim(func: any) -> any:
+            define wrapper(*args, **kwargs) -> any:
+                return func(*args, **kwargs) + "!"
+            return wrapper
+        @shout
+        @exclaim
+        define greet(name: string) -> string:
+            return "hi " + name
+        io.print(greet("ada"))
+    "#)
+    .success()
+    .stdout("HI ADA!\n");
+}
+
+#[test]
+fn decorators_can_take_arguments() {
+    run(r#"
+        import io
+        define retry(times: int) -> any:
+            define decorate(func: any) -> any:
+                define wrapper() -> any:
+                    set result to 0
+                    for i in range(times):
+                        set result to func()
+                    return result
+                return wrapper
+            return decorate
+        set calls to 0
+        @retry(3)
+        define tick() -> int:
+            nonlocal calls
+            set calls to calls + 1
+            return calls
+        io.print(tick())
+    "#)
+    .success()
+    .stdout("3\n");
+}
+
+#[test]
+fn classes_are_decorated_when_defined() {
+    run(r#"
+        import io
+        define register(cls: any) -> any:
+            io.print("registered")
+            return cls
+        @register
+        class Plugin:
+            define __init__(self):
+                set self.name to "p"
+        io.print("defined")
+        io.print(Plugin().name)
+    "#)
+    .success()
+    .stdout(lines(&["registered", "defined", "p"]));
+}
+
+#[test]
+fn decorated_names_take_the_decorator_return_type() {
+    run(r#"
+        define make_int(func: any) -> int:
+            return 1
+        @make_int
+        define f() -> string:
+            return "x"
+        f()
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("f is not a function"));
+}
+
+#[test]
+fn undefined_decorators_are_reported() {
+    run(r#"
+        @missing
+        define f() -> int:
+            return 1
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Undefined variable: missing"))
+    .stderr(predicate::str::contains("--> main.bellronos:1:2"));
+}
@@ -0,0 +1,72 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run, run_with};
+use predicates::prelude::*;
+
+const RETURN_MISMATCH: &str = r#"
+    define f() -> int:
+        return "s"
+"#;
+
+#[test]
+fn human_output_underlines_the_source_and_labels() {
+    run(RETURN_MISMATCH).failure().stderr(lines(&[
+        "Type error[E0202]: Function f return type mismatch: expected Int, found String",
+        " --> main.bellronos:2:5",
+        "  |",
+        "2 |     return \"s\"",
+        "  |     ^^^^^^^^^^",
+        "  |",
+        "1 | define f() -> int:",
+        "  | ----------------- return type declared here",
+    ]));
+}
+
+#[test]
+fn notes_and_help_are_rendered() {
+    run(r#"
+        import io
+        if true:
+            io.print("in")
+          io.print("out")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "= note: this line is indented 2 columns",
+    ))
+    .stderr(predicate::str::contains(
+        "= help: indent to the same column as an enclosing block",
+    ));
+}
+
+#[test]
+fn json_output_has_one_object_per_error() {
+    run_with(&["--error-format=json"], &[], RETURN_MISMATCH)
+        .failure()
+        .stderr(
+            r#"{"code":"E0202","help":null,"kind":"type","labels":[{"message":"return type declared here","span":{"column":1,"end":17,"file":"main.bellronos","line":1,"start":0}}],"message":"Function f return type mismatch: expected Int, found String","notes":[],"span":{"column":5,"end":33,"file":"main.bellronos","line":2,"start":23}}
+"#,
+        );
+}
+
+#[test]
+fn unknown_error_format_is_rejected() {
+    run_with(&["--error-format=xml"], &[], RETURN_MISMATCH)
+        .code(2)
+        .stderr(predicate::str::contains("Unknown error format: xml"));
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::run;
+use predicates::prelude::*;
+
+#[test]
+fn lexical_and_syntax_errors_are_reported_together_in_order() {
+    let output = run(r#"
+        set x to
+        set y to "abc
+        set z to 1 +
+        set w to 0x
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "aborting due to 4 previous errors",
+    ))
+    .get_output()
+    .stderr
+    .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    let codes: Vec<&str> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("Parser error["))
+        .map(|line| &line[..5])
+        .collect();
+    assert_eq!(codes, ["E0107", "E0102", "E0107", "E0103"]);
+}
+
+#[test]
+fn errors_inside_a_broken_definition_are_still_reported() {
+    run(r#"
+        import io
+        io.print("ran")
+        define f(x int) -> int:
+            return x +
+    "#)
+    .failure()
+    .stdout("")
+    .stderr(predicate::str::contains("main.bellronos:3:12"))
+    .stderr(predicate::str::contains("main.bellronos:4:15"))
+    .stderr(predicate::str::contains("'return' outside function").not());
+}
+
+#[test]
+fn parsing_stops_after_too_many_errors() {
+    let source: String = (0..30).map(|i| format!("set a{} to\n", i)).collect();
+    run(&source)
+        .failure()
+        .stderr(predicate::str::contains(
+            "error[E0110]: Too many syntax errors; stopping after 20",
+        ))
+        .stderr(predicate::str::contains("set a20").not())
+        .stderr(predicate::str::contains(
+            "aborting due to 21 previous errors",
+        ));
+}
+
+#[test]
+fn lexical_errors_count_towards_the_limit() {
+    let source: String = (0..30).map(|i| format!("set a{} to 1 $\n", i)).collect();
+    run(&source)
+        .failure()
+        .stderr(predicate::str::contains(
+            "error[E0110]: Too many syntax errors; stopping after 20",
+        ))
+        .stderr(predicate::str::contains("set a20").not())
+        .stderr(predicate::str::contains(
+            "aborting due to 21 previous errors",
+        ));
+}
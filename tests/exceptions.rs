@@ -0,0 +1,168 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn user_exceptions_are_caught_by_their_base_class() {
+    run(r#"
+        import io
+        import string
+        class InsufficientFunds(Exception):
+            define __init__(self, amount: int):
+                super.__init__("need " + string.to_string(amount))
+                set self.amount to amount
+        define withdraw(amount: int) -> int:
+            raise InsufficientFunds(amount)
+        try:
+            withdraw(5)
+        except ValueError as e:
+            io.print("wrong handler")
+        except Exception as e:
+            io.print(e)
+            io.print(e.amount)
+        finally:
+            io.print("finally")
+    "#)
+    .success()
+    .stdout(lines(&["need 5", "5", "finally"]));
+}
+
+#[test]
+fn exceptions_show_as_their_message() {
+    run(r#"
+        import io
+        import string
+        try:
+            raise ValueError("bad")
+        except ValueError as e:
+            io.print([e])
+            io.print("got: " + string.to_string(e))
+    "#)
+    .success()
+    .stdout(lines(&["[ValueError(\"bad\")]", "got: bad"]));
+}
+
+#[test]
+fn finally_runs_on_return_and_break() {
+    run(r#"
+        import io
+        import string
+        define early() -> int:
+            try:
+                return 1
+            finally:
+                io.print("cleanup on return")
+        io.print(early())
+        for i in range(3):
+            try:
+                if i == 1:
+                    break
+            finally:
+                io.print("cleanup " + string.to_string(i))
+    "#)
+    .success()
+    .stdout(lines(&["cleanup on return", "1", "cleanup 0", "cleanup 1"]));
+}
+
+#[test]
+fn finally_runs_before_an_outer_handler() {
+    run(r#"
+        import io
+        import string
+        try:
+            try:
+                raise ValueError("inner")
+            finally:
+                io.print("inner finally")
+        except ValueError as e:
+            io.print("outer caught " + string.to_string(e))
+        try:
+            raise ValueError("x")
+        except:
+            io.print("bare except")
+    "#)
+    .success()
+    .stdout(lines(&[
+        "inner finally",
+        "outer caught inner",
+        "bare except",
+    ]));
+}
+
+#[test]
+fn builtin_failures_raise_exceptions() {
+    run(r#"
+        import io
+        import fs
+        try:
+            set xs to [1]
+            io.print(xs[5])
+        except IndexError as e:
+            io.print("index")
+        try:
+            fs.open("nope.txt", "r")
+        except IOError as e:
+            io.print("io")
+    "#)
+    .success()
+    .stdout(lines(&["index", "io"]));
+}
+
+#[test]
+fn uncaught_exceptions_end_the_program() {
+    run(r#"
+        import io
+        io.print("before")
+        raise ValueError("boom")
+        io.print("after")
+    "#)
+    .failure()
+    .stdout("before\n")
+    .stderr(predicate::str::contains("error[E0210]: ValueError: boom"))
+    .stderr(predicate::str::contains("--> main.bellronos:3:1"));
+}
+
+#[test]
+fn open_is_a_builtin_raising_file_not_found() {
+    run(r#"
+        import io
+        try:
+            set file to open("nope.txt", "r")
+        except FileNotFoundError:
+            io.print("not found")
+    "#)
+    .success()
+    .stdout(lines(&["not found"]));
+}
+
+#[test]
+fn special_methods_may_end_in_underscores() {
+    run(r#"
+        import io
+        class InvalidInputError(Exception):
+            define **init**(self, message: string) -> none:
+                super.**init__(message)
+        try:
+            raise InvalidInputError("negative")
+        except InvalidInputError as e:
+            io.print(e)
+    "#)
+    .success()
+    .stdout(lines(&["negative"]));
+}
@@ -0,0 +1,124 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn generators_work_with_for() {
+    run(r#"
+        import io
+        define fibonacci(n: int) -> generator[int]:
+            set (a, b) to (0, 1)
+            for i in range(n):
+                yield a
+                set (a, b) to (b, a + b)
+        for value in fibonacci(7):
+            io.print(value)
+    "#)
+    .success()
+    .stdout(lines(&["0", "1", "1", "2", "3", "5", "8"]));
+}
+
+#[test]
+fn generators_run_lazily_until_exhausted() {
+    run(r#"
+        import io
+        define lazy() -> generator[int]:
+            io.print("started")
+            yield 1
+            io.print("resumed")
+            yield 2
+        set g to lazy()
+        io.print("created")
+        io.print(next(g))
+        io.print(next(g))
+        try:
+            next(g)
+        except StopIteration as e:
+            io.print("exhausted")
+    "#)
+    .success()
+    .stdout(lines(&[
+        "created",
+        "started",
+        "1",
+        "resumed",
+        "2",
+        "exhausted",
+    ]));
+}
+
+#[test]
+fn return_ends_a_generator_with_a_value() {
+    run(r#"
+        import io
+        define countdown(n: int) -> generator[int]:
+            while n > 0:
+                yield n
+                set n to n - 1
+            return "liftoff"
+        set c to countdown(2)
+        io.print(next(c))
+        io.print(next(c))
+        try:
+            next(c)
+        except StopIteration as e:
+            io.print(e.value)
+        for n in countdown(1):
+            io.print(n)
+    "#)
+    .success()
+    .stdout(lines(&["2", "1", "liftoff", "1"]));
+}
+
+#[test]
+fn send_resumes_with_a_value() {
+    run(r#"
+        import io
+        define doubler() -> generator[any]:
+            set received to yield 0
+            while true:
+                set received to yield received * 2
+        set d to doubler()
+        io.print(next(d))
+        io.print(d.send(5))
+        io.print(d.send(21))
+    "#)
+    .success()
+    .stdout(lines(&["0", "10", "42"]));
+}
+
+#[test]
+fn yield_types_are_checked() {
+    run(r#"
+        define g() -> generator[int]:
+            yield "s"
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Yield type mismatch: expected Int, found String",
+    ));
+    run(r#"
+        define g() -> int:
+            yield 1
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Function g yields, so it must return a generator, not Int",
+    ));
+}
@@ -0,0 +1,115 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run, run_verbatim};
+use predicates::prelude::*;
+
+#[test]
+fn function_body_ends_at_dedent() {
+    run(r#"
+        import io
+        define greet() -> int:
+            io.print("in body")
+            return 0
+        io.print("after")
+        greet()
+    "#)
+    .success()
+    .stdout(lines(&["after", "in body"]));
+}
+
+#[test]
+fn dedent_closes_several_blocks_at_once() {
+    run(r#"
+        import io
+        define check(n: int) -> int:
+            if n > 0:
+                if n > 10:
+                    io.print("big")
+                io.print("positive")
+            io.print("checked")
+            return n
+        check(11)
+        check(-1)
+    "#)
+    .success()
+    .stdout(lines(&["big", "positive", "checked", "checked"]));
+}
+
+#[test]
+fn blank_lines_and_comments_keep_the_block_open() {
+    run(r#"
+        import io
+        define f() -> int:
+            io.print("one")
+
+        # a comment at the margin
+            io.print("two")
+            return 0
+        f()
+    "#)
+    .success()
+    .stdout(lines(&["one", "two"]));
+}
+
+#[test]
+fn inconsistent_dedent_is_reported() {
+    run(r#"
+        import io
+        if true:
+            io.print("in")
+          io.print("out")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("error[E0104]"))
+    .stderr(predicate::str::contains("main.bellronos:4:1"))
+    .stderr(predicate::str::contains(
+        "enclosing blocks are indented [0, 4]",
+    ));
+}
+
+#[test]
+fn unexpected_indent_is_reported() {
+    run(r#"
+        import io
+            io.print("x")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("error[E0105]: Unexpected indent"));
+}
+
+#[test]
+fn missing_block_is_reported() {
+    run(r#"
+        import io
+        if true:
+        io.print("x")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "error[E0106]: Expected an indented block",
+    ));
+}
+
+#[test]
+fn crlf_blank_lines_keep_the_block_open() {
+    run_verbatim(
+        "import io\r\nif true:\r\n    io.print(\"one\")\r\n\r\n    io.print(\"two\")\r\n\r\nio.print(\"three\")\r\n",
+    )
+    .success()
+    .stdout(lines(&["one", "two", "three"]));
+}
@@ -0,0 +1,97 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run, run_with};
+use predicates::prelude::*;
+
+#[test]
+fn ranges_count_in_either_direction() {
+    run(r#"
+        import io
+        for i in range(2, 10, 3):
+            io.print(i)
+        for i in range(3, 0, -1):
+            io.print(i)
+        io.print(range(5))
+    "#)
+    .success()
+    .stdout(lines(&["2", "5", "8", "3", "2", "1", "range(0, 5)"]));
+}
+
+#[test]
+fn builtin_collections_are_iterable() {
+    run(r#"
+        import io
+        for key in {"b": 2, "a": 1}:
+            io.print(key)
+        for ch in "hi":
+            io.print(ch)
+        for item in (7, 8):
+            io.print(item)
+        for n in {3, 1, 2}:
+            io.print(n)
+    "#)
+    .success()
+    .stdout(lines(&["a", "b", "h", "i", "7", "8", "1", "2", "3"]));
+}
+
+#[test]
+fn files_iterate_over_their_lines() {
+    run_with(
+        &[],
+        &[("data.txt", "first\nsecond\n")],
+        r#"
+        import io
+        import fs
+        for line in fs.open("data.txt", "r"):
+            io.print(line)
+    "#,
+    )
+    .success()
+    .stdout("first\n\nsecond\n\n");
+}
+
+#[test]
+fn classes_can_define_the_iterator_protocol() {
+    run(r#"
+        import io
+        class Countdown:
+            define __init__(self, start: int):
+                set self.current to start
+            define __iter__(self) -> any:
+                return self
+            define __next__(self) -> int:
+                if self.current == 0:
+                    raise StopIteration("done")
+                set self.current to self.current - 1
+                return self.current + 1
+        for n in Countdown(3):
+            io.print(n)
+    "#)
+    .success()
+    .stdout(lines(&["3", "2", "1"]));
+}
+
+#[test]
+fn non_iterables_are_rejected() {
+    run(r#"
+        for x in 5:
+            set y to x
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Type Int is not iterable"));
+}
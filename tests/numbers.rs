@@ -0,0 +1,140 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn integer_literals_in_every_base() {
+    run(r#"
+        import io
+        io.print(0xff)
+        io.print(0o17)
+        io.print(0b1010)
+        io.print(1_000_000)
+        io.print(0x_ff_ff)
+        io.print(0b_1010)
+        io.print(9223372036854775807)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "255",
+        "15",
+        "10",
+        "1000000",
+        "65535",
+        "10",
+        "9223372036854775807",
+    ]));
+}
+
+#[test]
+fn integers_and_floats_stay_distinct() {
+    run(r#"
+        import io
+        io.print(7 + 2)
+        io.print(7 // 2)
+        io.print(2.0)
+        io.print(1.5e3)
+        io.print(1_0.2_5)
+    "#)
+    .success()
+    .stdout(lines(&["9", "3", "2.0", "1500.0", "10.25"]));
+}
+
+#[test]
+fn malformed_literals_are_reported() {
+    run(r#"
+        set x to 0b102
+        set y to 1__0
+        set z to 99999999999999999999
+        set w to 0x_
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "error[E0103]: Invalid number literal: 0b102",
+    ))
+    .stderr(predicate::str::contains("'_' must sit between two digits"))
+    .stderr(predicate::str::contains(
+        "number too large to fit in target type",
+    ))
+    .stderr(predicate::str::contains(
+        "expected digits after the base prefix",
+    ));
+}
+
+#[test]
+fn mixed_arithmetic_promotes_to_float() {
+    run(r#"
+        import io
+        io.print(1 + 2.5)
+        io.print(2 * 1.5)
+        io.print(6 / 3)
+        io.print(7.5 % 2)
+        io.print(2.0 ** 3)
+        io.print(7.0 // 2)
+        io.print(1 < 1.5)
+        io.print(2 == 2.0)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "3.5", "3.0", "2.0", "1.5", "8.0", "3.0", "true", "true",
+    ]));
+}
+
+#[test]
+fn integer_division_and_powers_follow_floor_semantics() {
+    run(r#"
+        import io
+        io.print(7 % 3)
+        io.print(-7 % 3)
+        io.print(-7 // 2)
+        io.print(2 ** 10)
+        io.print(2 ** -1)
+    "#)
+    .success()
+    .stdout(lines(&["1", "2", "-4", "1024", "0.5"]));
+}
+
+#[test]
+fn division_by_zero_is_catchable() {
+    run(r#"
+        import io
+        try:
+            io.print(1 / 0)
+        except DivisionByZeroError as e:
+            io.print("caught")
+        try:
+            io.print(1.0 % 0)
+        except DivisionByZeroError as e:
+            io.print(e)
+    "#)
+    .success()
+    .stdout(lines(&["caught", "Division by zero: 1.0 % 0"]));
+}
+
+#[test]
+fn integer_overflow_is_an_error() {
+    run(r#"
+        import io
+        io.print(9223372036854775807 + 1)
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Integer overflow in 9223372036854775807 + 1",
+    ));
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    run(r#"
+        import io
+        io.print(1 + 2 * 3)
+        io.print((1 + 2) * 3)
+        io.print(17 // 5 * 5 + 17 % 5)
+        io.print(1 + 2 < 4)
+    "#)
+    .success()
+    .stdout(lines(&["7", "9", "17", "true"]));
+}
+
+#[test]
+fn power_is_right_associative_and_binds_tighter_than_unary_minus() {
+    run(r#"
+        import io
+        io.print(2 ** 3 ** 2)
+        io.print(-2 ** 2)
+        io.print(2 * -3)
+    "#)
+    .success()
+    .stdout(lines(&["512", "-4", "-6"]));
+}
+
+#[test]
+fn other_operators_are_left_associative() {
+    run(r#"
+        import io
+        io.print(10 - 4 - 3)
+        io.print(100 / 10 / 5)
+    "#)
+    .success()
+    .stdout(lines(&["3", "2.0"]));
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    run(r#"
+        import io
+        define loud(value: bool) -> bool:
+            io.print("evaluated")
+            return value
+        io.print(false and loud(true))
+        io.print(true or loud(true))
+        io.print(true and loud(false))
+        io.print(not true)
+        io.print(not 1 == 2)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "false",
+        "true",
+        "evaluated",
+        "false",
+        "false",
+        "true",
+    ]));
+}
+
+#[test]
+fn empty_and_zero_values_are_falsy() {
+    run(r#"
+        import io
+        set items to []
+        if items:
+            io.print("items")
+        else:
+            io.print("empty")
+        if "" or 0 or 0.0:
+            io.print("truthy")
+        else:
+            io.print("falsy")
+        if [0] and "x" and 3:
+            io.print("all truthy")
+        io.print(1 and 2)
+    "#)
+    .success()
+    .stdout(lines(&["empty", "falsy", "all truthy", "true"]));
+}
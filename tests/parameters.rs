@@ -0,0 +1,146 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+const GREET: &str = r#"
+    import io
+    define greet(name: string, greeting: string = "Hello") -> string:
+        return greeting + ", " + name
+"#;
+
+fn with_greet(rest: &str) -> String {
+    format!("{}\n{}", common::dedent(GREET), common::dedent(rest))
+}
+
+#[test]
+fn defaults_and_keyword_arguments() {
+    run(&with_greet(
+        r#"
+        io.print(greet("Ada"))
+        io.print(greet("Ada", greeting="Hi"))
+        io.print(greet(greeting="Yo", name="Bob"))
+    "#,
+    ))
+    .success()
+    .stdout(lines(&["Hello, Ada", "Hi, Ada", "Yo, Bob"]));
+}
+
+#[test]
+fn variadic_parameters_collect_the_rest() {
+    run(r#"
+        import io
+        define total(*numbers) -> int:
+            return reduce(closure (a: int, b: int): a + b, numbers, 0)
+        io.print(total(1, 2, 3))
+        io.print(total(*[4, 5]))
+        define describe(**options) -> any:
+            return options
+        io.print(describe(color="red"))
+        define both(first: int, *rest, **extra) -> any:
+            return (first, rest, extra)
+        io.print(both(1, 2, 3, key=4))
+    "#)
+    .success()
+    .stdout(lines(&[
+        "6",
+        "9",
+        "{\"color\": \"red\"}",
+        "(1, (2, 3), {\"key\": 4})",
+    ]));
+}
+
+#[test]
+fn defaults_are_evaluated_once() {
+    run(r#"
+        import io
+        define appender(item: int, items: list[int] = []) -> list[int]:
+            items.append(item)
+            return items
+        io.print(appender(1))
+        io.print(appender(2))
+    "#)
+    .success()
+    .stdout(lines(&["[1]", "[1, 2]"]));
+}
+
+#[test]
+fn arity_and_keyword_errors_are_type_errors() {
+    for (call, message) in [
+        (
+            "greet()",
+            "Function greet expects 1 to 2 arguments, but 0 were given",
+        ),
+        (
+            "greet(\"a\", \"b\", \"c\")",
+            "Function greet expects 1 to 2 arguments, but 3 were given",
+        ),
+        (
+            "greet(\"a\", colour=\"x\")",
+            "Function greet got an unexpected keyword argument 'colour'",
+        ),
+        (
+            "greet(\"a\", name=\"b\")",
+            "Function greet got multiple values for argument 'name'",
+        ),
+    ] {
+        run(&with_greet(call))
+            .failure()
+            .stderr(predicate::str::contains("Type error[E02"))
+            .stderr(predicate::str::contains(message));
+    }
+}
+
+#[test]
+fn arity_errors_are_also_caught_at_runtime() {
+    run(r#"
+        import io
+        define greet(name: string) -> string:
+            return name
+        define call(f: any, args: any) -> any:
+            return f(*args)
+        io.print(call(greet, [1, 2]))
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Runtime error[E0203]: Function greet expects 1 arguments, but 2 were given",
+    ));
+    run(r#"
+        define greet(name: string) -> string:
+            return name
+        define call(f: any, options: any) -> any:
+            return f(**options)
+        call(greet, {"nickname": "x"})
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Function greet got an unexpected keyword argument 'nickname'",
+    ));
+}
+
+#[test]
+fn required_parameters_cannot_follow_defaults() {
+    run(r#"
+        define f(a: int = 1, b: int) -> int:
+            return a
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Parameter 'b' without a default follows one with a default",
+    ));
+}
@@ -0,0 +1,90 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn function_locals_do_not_leak() {
+    run(r#"
+        import io
+        define f() -> int:
+            set secret to 1
+            return secret
+        f()
+        io.print(secret)
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Undefined variable: secret"));
+}
+
+#[test]
+fn recursive_calls_keep_their_own_locals() {
+    run(r#"
+        import io
+        import string
+        define fact(n: int) -> int:
+            set result to n
+            if n <= 1:
+                return 1
+            set rest to fact(n - 1)
+            return result * rest
+        io.print(string.to_string(fact(5)))
+    "#)
+    .success()
+    .stdout("120\n");
+}
+
+#[test]
+fn functions_see_later_changes_to_enclosing_names() {
+    run(r#"
+        import io
+        import string
+        set greeting to "hello"
+        define show() -> string:
+            return greeting
+        set greeting to "bye"
+        io.print(show())
+        define outer() -> any:
+            set x to 1
+            define inner() -> int:
+                return x
+            set x to 2
+            return inner
+        io.print(string.to_string(outer()()))
+    "#)
+    .success()
+    .stdout(lines(&["bye", "2"]));
+}
+
+#[test]
+fn loop_targets_in_functions_are_local() {
+    run(r#"
+        import io
+        import string
+        set i to 100
+        define last() -> int:
+            set seen to 0
+            for i in range(3):
+                set seen to i
+            return seen
+        io.print(string.to_string(last()))
+        io.print(string.to_string(i))
+    "#)
+    .success()
+    .stdout(lines(&["2", "100"]));
+}
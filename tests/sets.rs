@@ -0,0 +1,99 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn set_literals_drop_duplicates() {
+    run(r#"
+        import io
+        set numbers to {1, 2, 3, 4, 5, 5}
+        io.print(numbers)
+        io.print(len(numbers))
+        io.print({(1, 2), (1, 2)})
+        io.print({})
+    "#)
+    .success()
+    .stdout(lines(&["{1, 2, 3, 4, 5}", "5", "{(1, 2)}", "{}"]));
+}
+
+#[test]
+fn set_algebra_and_membership() {
+    run(r#"
+        import io
+        set numbers to {1, 2, 3, 4, 5}
+        set other to {4, 5, 6}
+        io.print(numbers.union(other))
+        io.print(numbers.intersection(other))
+        io.print(numbers.difference(other))
+        io.print(3 in numbers)
+        io.print(9 not in numbers)
+    "#)
+    .success()
+    .stdout(lines(&[
+        "{1, 2, 3, 4, 5, 6}",
+        "{4, 5}",
+        "{1, 2, 3}",
+        "true",
+        "true",
+    ]));
+}
+
+#[test]
+fn whole_floats_hash_and_compare_like_ints() {
+    run(r#"
+        import io
+        io.print({1, 1.0, 2.5})
+        io.print(1 in {1.0})
+        io.print(1 == 1.0)
+        io.print(0.1 + 0.2 == 0.3)
+    "#)
+    .success()
+    .stdout(lines(&["{1, 2.5}", "true", "true", "false"]));
+}
+
+#[test]
+fn mutable_elements_are_rejected() {
+    run("set s to {[1], [2]}")
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unhashable set element of type List(Int)",
+        ));
+}
+
+#[test]
+fn set_types_annotate_parameters() {
+    run(r#"
+        import io
+        define total(s: set[int], l: list, d: dict, t: set) -> set[int]:
+            io.print(len(s) + len(l) + len(d) + len(t))
+            return s
+        io.print(total({1, 2}, [1], {"a": 1}, {"x"}))
+    "#)
+    .success()
+    .stdout(lines(&["5", "{1, 2}"]));
+    run(r#"
+        define size(s: set[int]) -> int:
+            return len(s)
+        size({"a"})
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Type mismatch: expected Set(Int), found Set(String)",
+    ));
+}
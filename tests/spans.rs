@@ -0,0 +1,98 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{run, run_with};
+use predicates::prelude::*;
+
+#[test]
+fn type_errors_point_at_the_expression() {
+    run(r#"
+        set x to 1
+        set y to x + "a"
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("--> main.bellronos:2:10"))
+    .stderr(predicate::str::contains(
+        "set y to x + \"a\"\n  |          ^^^^^^^",
+    ));
+}
+
+#[test]
+fn runtime_errors_point_inside_the_function() {
+    run(r#"
+        import io
+        define f(n: int) -> int:
+            return 10 // n
+        io.print(f(0))
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("Division by zero: 10 // 0"))
+    .stderr(predicate::str::contains("--> main.bellronos:3:12"));
+}
+
+#[test]
+fn failed_import_is_reported_at_the_import() {
+    run_with(
+        &["--error-format=json"],
+        &[],
+        r#"
+        import io
+
+        import nope
+    "#,
+    )
+    .failure()
+    .stderr(predicate::str::contains(r#""code":"E0401""#))
+    .stderr(predicate::str::contains(
+        r#""span":{"column":1,"end":22,"file":"main.bellronos","line":3,"start":11}"#,
+    ));
+}
+
+#[test]
+fn io_errors_are_reported_at_the_call() {
+    run_with(
+        &["--error-format=json"],
+        &[],
+        r#"
+        import fs
+        set f to fs.open("missing.txt", "r")
+    "#,
+    )
+    .failure()
+    .stderr(predicate::str::contains(r#""code":"E0216""#))
+    .stderr(predicate::str::contains("missing.txt: No such file"))
+    .stderr(predicate::str::contains(r#""line":2"#));
+}
+
+#[test]
+fn package_errors_are_reported_in_the_package() {
+    run_with(
+        &[],
+        &[(
+            "packages/broken.bellronos",
+            r#"
+            set a to 1
+            set b to a + "x"
+        "#,
+        )],
+        "import broken",
+    )
+    .failure()
+    .stderr(predicate::str::contains(
+        "--> packages/broken.bellronos:2:10",
+    ));
+}
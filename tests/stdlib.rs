@@ -0,0 +1,89 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run, run_with_input};
+use predicates::prelude::*;
+
+#[test]
+fn math_and_string_functions() {
+    run(r#"
+        import io
+        import math
+        import string
+        io.print(math.sqrt(2.0))
+        io.print(math.pi)
+        io.print(string.to_upper("abc"))
+        io.print(string.to_lower("ABC"))
+        io.print(string.length("héllo"))
+        io.print(string.to_string(1.5) + "!")
+    "#)
+    .success()
+    .stdout(lines(&[
+        "1.4142135623730951",
+        "3.141592653589793",
+        "ABC",
+        "abc",
+        "5",
+        "1.5!",
+    ]));
+}
+
+#[test]
+fn input_reads_a_line_from_stdin() {
+    run_with_input(
+        "Ada\n",
+        r#"
+        import io
+        set name to io.input("name? ")
+        io.print("hello " + name)
+    "#,
+    )
+    .success()
+    .stdout("name? hello Ada\n");
+}
+
+#[test]
+fn builtin_signatures_are_type_checked() {
+    run(r#"
+        import math
+        math.sqrt("x")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Type mismatch: expected Float, found String",
+    ));
+    run(r#"
+        import string
+        string.to_upper("a", "b")
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Function to_upper expects 1 arguments, but 2 were given",
+    ));
+}
+
+#[test]
+fn builtin_failures_are_runtime_errors() {
+    run(r#"
+        import io
+        import math
+        io.print(math.sqrt(-1.0))
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("sqrt of negative number -1"))
+    .stderr(predicate::str::contains("--> main.bellronos:3:10"));
+}
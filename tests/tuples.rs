@@ -0,0 +1,85 @@
+// Copyright (C) 2024 Bellande Architecture Mechanism Research Innovation Center, Ronaldson Bellande
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{lines, run};
+use predicates::prelude::*;
+
+#[test]
+fn destructuring_in_set_for_and_returns() {
+    run(r#"
+        import io
+        set (a, b) to (0, 1)
+        for i in range(5):
+            set (a, b) to (b, a + b)
+        io.print(a)
+        set coordinates to (3, 4)
+        set (x, y) to coordinates
+        io.print(x * y)
+        define bounds() -> tuple[int, int]:
+            return (1, 9)
+        set (lo, hi) to bounds()
+        io.print(hi - lo)
+        for (name, score) in [("a", 1), ("b", 2)]:
+            io.print(name)
+    "#)
+    .success()
+    .stdout(lines(&["5", "12", "8", "a", "b"]));
+}
+
+#[test]
+fn tuple_literals_compare_by_value() {
+    run(r#"
+        import io
+        io.print((1,))
+        io.print(())
+        io.print((1, "a") == (1, "a"))
+        set t to (1, 2)
+        io.print(t[1])
+    "#)
+    .success()
+    .stdout(lines(&["(1,)", "()", "true", "2"]));
+}
+
+#[test]
+fn unpacking_arity_is_checked() {
+    run("set (a, b) to (1, 2, 3)")
+        .failure()
+        .stderr(predicate::str::contains(
+            "Type error[E0202]: Expected 2 values to unpack, but the tuple has 3",
+        ));
+    run(r#"
+        import io
+        define first(x: any) -> any:
+            set (a, b) to x
+            return a
+        io.print(first([1, 2, 3]))
+    "#)
+    .failure()
+    .stderr(predicate::str::contains(
+        "Runtime error[E0213]: Expected 2 values to unpack, but got 3",
+    ));
+}
+
+#[test]
+fn tuples_are_immutable() {
+    run(r#"
+        set t to (1, 2)
+        set t[0] to 5
+    "#)
+    .failure()
+    .stderr(predicate::str::contains("does not support item assignment"));
+}